@@ -0,0 +1,25 @@
+-- noinspection SqlNoDataSourceInspectionForFile
+
+CREATE TABLE public.schedule_runs (
+    run_id      uuid        NOT NULL,
+    started_at  timestamptz NOT NULL,
+    finished_at timestamptz NULL,
+    CONSTRAINT schedule_runs_pkey PRIMARY KEY (run_id)
+);
+COMMENT ON TABLE public.schedule_runs IS 'One entry per execution of the task schedule, finished_at stays empty if the run was aborted';
+
+CREATE TABLE public.task_runs (
+    run_id       uuid         NOT NULL,
+    task_name    varchar(100) NOT NULL,
+    started_at   timestamptz  NULL,
+    finished_at  timestamptz  NOT NULL,
+    state        varchar(20)  NOT NULL,
+    attempts     int4         NOT NULL,
+    error        text         NULL,
+    custom_stats jsonb        NULL,
+    CONSTRAINT task_runs_pkey PRIMARY KEY (run_id, task_name),
+    CONSTRAINT task_runs_run_id_fkey FOREIGN KEY (run_id) REFERENCES public.schedule_runs (run_id)
+);
+COMMENT ON COLUMN public.task_runs.started_at IS 'Empty if the task was not executed, e.g. because it was skipped';
+COMMENT ON COLUMN public.task_runs.attempts IS 'Number of executions of the task including retries';
+COMMENT ON COLUMN public.task_runs.error IS 'Error including all its causes if the task failed';
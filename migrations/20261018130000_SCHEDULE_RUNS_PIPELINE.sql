@@ -0,0 +1,4 @@
+-- noinspection SqlNoDataSourceInspectionForFile
+
+ALTER TABLE public.schedule_runs ADD COLUMN pipeline varchar(100) NOT NULL DEFAULT 'default';
+COMMENT ON COLUMN public.schedule_runs.pipeline IS 'Name of the pipeline whose schedule was executed';
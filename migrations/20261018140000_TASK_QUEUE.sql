@@ -0,0 +1,21 @@
+-- noinspection SqlNoDataSourceInspectionForFile
+
+CREATE TABLE public.task_queue (
+    queue_id     uuid         NOT NULL,
+    pipeline     varchar(100) NOT NULL,
+    task_name    varchar(100) NOT NULL,
+    state        varchar(20)  NOT NULL,
+    worker_id    uuid         NULL,
+    enqueued_at  timestamptz  NOT NULL,
+    claimed_at   timestamptz  NULL,
+    heartbeat_at timestamptz  NULL,
+    finished_at  timestamptz  NULL,
+    error        text         NULL,
+    retryable    bool         NOT NULL DEFAULT false,
+    custom_stats jsonb        NULL,
+    CONSTRAINT task_queue_pkey PRIMARY KEY (queue_id)
+);
+CREATE INDEX task_queue_queued_idx ON public.task_queue (enqueued_at) WHERE state = 'queued';
+COMMENT ON TABLE public.task_queue IS 'Tasks queued by the scheduler and claimed by worker processes';
+COMMENT ON COLUMN public.task_queue.heartbeat_at IS 'Updated regularly by the worker while the task runs, a stale heartbeat queues the task again';
+COMMENT ON COLUMN public.task_queue.retryable IS 'Whether the error of a failed task is worth a retry';
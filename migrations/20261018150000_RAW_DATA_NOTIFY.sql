@@ -0,0 +1,70 @@
+-- noinspection SqlNoDataSourceInspectionForFile
+
+-- Notifies listeners on channel raw_data_inserted about new rows of a raw data table.
+-- The optional argument names the date column whose range is part of the payload.
+CREATE OR REPLACE FUNCTION notify_raw_data_inserted()
+RETURNS TRIGGER AS $$
+DECLARE
+    num_rows bigint;
+    min_date date;
+    max_date date;
+BEGIN
+    IF TG_NARGS > 0 THEN
+        EXECUTE format('SELECT count(*), min(%I), max(%I) FROM new_rows', TG_ARGV[0], TG_ARGV[0])
+            INTO num_rows, min_date, max_date;
+    ELSE
+        SELECT count(*) INTO num_rows FROM new_rows;
+    END IF;
+    IF num_rows > 0 THEN
+        PERFORM pg_notify('raw_data_inserted', json_build_object(
+            'table', TG_TABLE_NAME,
+            'rows', num_rows,
+            'min_date', min_date,
+            'max_date', max_date
+        )::text);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE TRIGGER nyse_events_notify_insert
+AFTER INSERT ON nyse_events
+REFERENCING NEW TABLE AS new_rows
+FOR EACH STATEMENT
+EXECUTE FUNCTION notify_raw_data_inserted('action_date');
+
+CREATE OR REPLACE TRIGGER nyse_instruments_notify_insert
+AFTER INSERT ON nyse_instruments
+REFERENCING NEW TABLE AS new_rows
+FOR EACH STATEMENT
+EXECUTE FUNCTION notify_raw_data_inserted('dateloaded');
+
+CREATE OR REPLACE TRIGGER sec_companies_notify_insert
+AFTER INSERT ON sec_companies
+REFERENCING NEW TABLE AS new_rows
+FOR EACH STATEMENT
+EXECUTE FUNCTION notify_raw_data_inserted('date_loaded');
+
+CREATE OR REPLACE TRIGGER polygon_grouped_daily_notify_insert
+AFTER INSERT ON polygon_grouped_daily
+REFERENCING NEW TABLE AS new_rows
+FOR EACH STATEMENT
+EXECUTE FUNCTION notify_raw_data_inserted('business_date');
+
+CREATE OR REPLACE TRIGGER polygon_open_close_notify_insert
+AFTER INSERT ON polygon_open_close
+REFERENCING NEW TABLE AS new_rows
+FOR EACH STATEMENT
+EXECUTE FUNCTION notify_raw_data_inserted('business_date');
+
+CREATE OR REPLACE TRIGGER financialmodelingprep_company_profile_notify_insert
+AFTER INSERT ON financialmodelingprep_company_profile
+REFERENCING NEW TABLE AS new_rows
+FOR EACH STATEMENT
+EXECUTE FUNCTION notify_raw_data_inserted('date_loaded');
+
+CREATE OR REPLACE TRIGGER financialmodelingprep_market_cap_notify_insert
+AFTER INSERT ON financialmodelingprep_market_cap
+REFERENCING NEW TABLE AS new_rows
+FOR EACH STATEMENT
+EXECUTE FUNCTION notify_raw_data_inserted('business_date');
@@ -0,0 +1,8 @@
+-- noinspection SqlNoDataSourceInspectionForFile
+
+CREATE TABLE public.task_freshness (
+    task_name    varchar(100) NOT NULL,
+    succeeded_at timestamptz  NOT NULL,
+    CONSTRAINT task_freshness_pkey PRIMARY KEY (task_name)
+);
+COMMENT ON TABLE public.task_freshness IS 'Last successful execution of every task, a task with freshness window is skipped while its last success lies within the window';
@@ -0,0 +1,14 @@
+-- noinspection SqlNoDataSourceInspectionForFile
+
+CREATE TABLE public.task_controls (
+    task_name      varchar(100) NOT NULL,
+    paused         bool         NOT NULL DEFAULT false,
+    disabled_until timestamptz  NULL,
+    force_run      bool         NOT NULL DEFAULT false,
+    updated_at     timestamptz  NOT NULL,
+    CONSTRAINT task_controls_pkey PRIMARY KEY (task_name)
+);
+COMMENT ON TABLE public.task_controls IS 'Operator controls of tasks, consulted by the scheduler before a task is started';
+COMMENT ON COLUMN public.task_controls.paused IS 'The task is skipped until it is resumed';
+COMMENT ON COLUMN public.task_controls.disabled_until IS 'The task is skipped until the given time';
+COMMENT ON COLUMN public.task_controls.force_run IS 'The task is executed once even if it is fresh or not due, cleared when the task is started';
@@ -1,7 +1,14 @@
-use super::collect::financialmodelingprep_company_profile::FinancialmodelingprepCompanyProfileCollector;
-use super::collect::financialmodelingprep_market_capitalization::FinancialmodelingprepMarketCapitalizationCollector;
-use super::collect::polygon_grouped_daily::PolygonGroupedDailyCollector;
-use super::collect::polygon_open_close::PolygonOpenCloseCollector;
+use super::collect::financialmodelingprep_company_profile::{
+    FinancialmodelingprepCompanyProfileCollector, FinancialmodelingprepCompanyProfileParams,
+};
+use super::collect::financialmodelingprep_market_capitalization::{
+    FinancialmodelingprepMarketCapitalizationCollector,
+    FinancialmodelingprepMarketCapitalizationParams,
+};
+use super::collect::polygon_grouped_daily::{
+    PolygonGroupedDailyCollector, PolygonGroupedDailyParams,
+};
+use super::collect::polygon_open_close::{PolygonOpenCloseCollector, PolygonOpenCloseParams};
 use super::stage::financialmodelingprep_company_profile::FinancialmodelingprepCompanyProfileStager;
 use super::stage::financialmodelingprep_market_capitalization::FinancialmodelingprepMarketCapitalizationStager;
 use super::stage::polygon_grouped_daily::PolygonGroupedDailyStager;
@@ -11,33 +18,79 @@ use crate::{actions::collect::dummy::DummyCollector, api_keys::api_key::PolygonK
 use crate::actions::collect::nyse_events::NyseEventCollector;
 use crate::actions::collect::nyse_instruments::NyseInstrumentCollector;
 use crate::actions::collect::sec_companies::SecCompanyCollector;
+use crate::actions::sensor::{Sensor, SensorCondition};
 use crate::actions::stage::nyse_instruments::NyseInstrumentStager;
 use crate::actions::stage::sec_companies::SecCompanyStager;
 use crate::api_keys::api_key::FinancialmodelingprepKey;
 use crate::api_keys::key_manager;
 use crate::api_keys::key_manager::KeyManager;
-use crate::configuration::SecretKeys;
+use crate::configuration::{SecretKeys, SensorConditionSetting, SensorSetting};
+use crate::dag_schedule::fan_out::{FanOut, FanOutOptions, FanOutRunnable};
 use crate::dag_schedule::task::Runnable;
+use crate::utils::action_helpers::DateRange;
+use crate::utils::notification::Notifier;
+use anyhow::Context;
 use reqwest::Client;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use sqlx::PgPool;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tracing::debug;
 
 /// Action is a boxed trait object of Runnable.
 pub type Action = Arc<dyn Runnable + Send + Sync>;
 
-/// create_action creates a boxed trait object of Action from a ActionType.
+/// create_action creates a boxed trait object of Action from a ActionType. Fails if the params do
+/// not match the action type, action types without params do not accept any.
 pub fn create_action(
     action_type: &ActionType,
+    params: Option<&config::Value>,
     pool: &PgPool,
     client: &Client,
     secrets: &SecretKeys,
-) -> Action {
-    let key_store = Arc::new(Mutex::new(key_manager::KeyManager::new()));
+    notifier: &Notifier,
+) -> Result<Action, anyhow::Error> {
+    let key_store = Arc::new(Mutex::new(
+        key_manager::KeyManager::new().with_notifier(notifier.clone()),
+    ));
     fill_key_store(&key_store, secrets.clone());
 
-    match action_type {
+    Ok(match action_type {
+        ActionType::PolygonGroupedDaily => create_action_polygon_grouped_daily(
+            pool,
+            client,
+            Arc::clone(&key_store),
+            parse_params(params)?,
+        ),
+        ActionType::PolygonOpenClose => create_action_polygon_open_close(
+            pool,
+            client,
+            Arc::clone(&key_store),
+            parse_params(params)?,
+        ),
+        ActionType::FinancialmodelingprepCompanyProfileCollet => {
+            create_action_financial_modeling_company_profile(
+                pool,
+                client,
+                Arc::clone(&key_store),
+                parse_params(params)?,
+            )
+        }
+        ActionType::FinmodMarketCapCollect => {
+            create_action_financial_modeling_market_capitalization(
+                pool,
+                client,
+                Arc::clone(&key_store),
+                parse_params(params)?,
+            )
+        }
+        ActionType::MassiveDividends => Arc::new(
+            PolygonDividendsCollector::new(pool.clone(), client.clone(), Arc::clone(&key_store))
+                .with_params(parse_params(params)?),
+        ),
+        _ if params.is_some() => anyhow::bail!("The action of the task does not take params"),
         ActionType::NyseEventsCollect => {
             Arc::new(NyseEventCollector::new(pool.clone(), client.clone()))
         }
@@ -50,35 +103,90 @@ pub fn create_action(
         ActionType::NyseInstrumentsStage => Arc::new(NyseInstrumentStager::new(pool.clone())),
         ActionType::SecCompaniesStage => Arc::new(SecCompanyStager::new(pool.clone())),
         ActionType::Dummy => Arc::new(DummyCollector::new()),
-        ActionType::PolygonGroupedDaily => {
-            create_action_polygon_grouped_daily(pool, client, Arc::clone(&key_store))
-        }
         ActionType::PolygonGroupedDailyStager => create_action_polygon_grouped_daily_stager(pool),
-        ActionType::PolygonOpenClose => {
-            create_action_polygon_open_close(pool, client, Arc::clone(&key_store))
-        }
-        ActionType::FinancialmodelingprepCompanyProfileCollet => {
-            create_action_financial_modeling_company_profile(pool, client, Arc::clone(&key_store))
-        }
         ActionType::FinmodCompanyProfileStage => {
             Arc::new(FinancialmodelingprepCompanyProfileStager::new(pool.clone()))
         }
+        ActionType::FinmodMarketCapStager => Arc::new(
+            FinancialmodelingprepMarketCapitalizationStager::new(pool.clone()),
+        ),
+        ActionType::Sensor(setting) => create_action_sensor(setting, pool, client),
+    })
+}
+
+/// Deserializes the params of a task, missing params and missing fields take their defaults
+fn parse_params<T: DeserializeOwned + Default>(
+    params: Option<&config::Value>,
+) -> Result<T, anyhow::Error> {
+    match params {
+        Some(params) => params.clone().try_deserialize().context("Invalid params"),
+        None => Ok(T::default()),
+    }
+}
+
+/// Creates the action of a collector which collects the given date range again, fails for action types
+/// which do not support a backfill.
+pub fn create_backfill_action(
+    action_type: &ActionType,
+    params: Option<&config::Value>,
+    range: DateRange,
+    pool: &PgPool,
+    client: &Client,
+    secrets: &SecretKeys,
+    notifier: &Notifier,
+) -> Result<Action, anyhow::Error> {
+    match action_type {
+        ActionType::NyseEventsCollect if params.is_some() => {
+            anyhow::bail!("The action of the task does not take params")
+        }
+        ActionType::NyseEventsCollect => Ok(Arc::new(
+            NyseEventCollector::new(pool.clone(), client.clone()).with_backfill(range),
+        )),
+        ActionType::PolygonGroupedDaily => {
+            let key_store = Arc::new(Mutex::new(
+                key_manager::KeyManager::new().with_notifier(notifier.clone()),
+            ));
+            fill_key_store(&key_store, secrets.clone());
+            Ok(Arc::new(
+                PolygonGroupedDailyCollector::new(pool.clone(), client.clone(), key_store)
+                    .with_params(parse_params(params)?)
+                    .with_backfill(range),
+            ))
+        }
+        _ => anyhow::bail!("The action of the task does not support a backfill"),
+    }
+}
+
+/// Creates the action of a collector which processes its symbols as items of a fan-out, fails for action
+/// types which do not support a fan-out.
+pub fn create_fan_out_action(
+    action_type: &ActionType,
+    params: Option<&config::Value>,
+    options: FanOutOptions,
+    pool: &PgPool,
+    client: &Client,
+    secrets: &SecretKeys,
+    notifier: &Notifier,
+) -> Result<Action, anyhow::Error> {
+    let key_store = Arc::new(Mutex::new(
+        key_manager::KeyManager::new().with_notifier(notifier.clone()),
+    ));
+    fill_key_store(&key_store, secrets.clone());
+    let fan_out: Arc<dyn FanOut> = match action_type {
+        ActionType::PolygonOpenClose => {
+            create_action_polygon_open_close(pool, client, key_store, parse_params(params)?)
+        }
         ActionType::FinmodMarketCapCollect => {
             create_action_financial_modeling_market_capitalization(
                 pool,
                 client,
-                Arc::clone(&key_store),
+                key_store,
+                parse_params(params)?,
             )
         }
-        ActionType::FinmodMarketCapStager => Arc::new(
-            FinancialmodelingprepMarketCapitalizationStager::new(pool.clone()),
-        ),
-        ActionType::MassiveDividends => Arc::new(PolygonDividendsCollector::new(
-            pool.clone(),
-            client.clone(),
-            Arc::clone(&key_store),
-        )),
-    }
+        _ => anyhow::bail!("The action of the task does not support a fan-out"),
+    };
+    Ok(Arc::new(FanOutRunnable::new(fan_out, options)))
 }
 
 fn fill_key_store(key_store: &Arc<Mutex<KeyManager>>, secrets: SecretKeys) {
@@ -109,36 +217,44 @@ fn create_action_financial_modeling_market_capitalization(
     pool: &sqlx::Pool<sqlx::Postgres>,
     client: &Client,
     key_manager: Arc<Mutex<KeyManager>>,
-) -> Arc<dyn Runnable + Send + Sync> {
-    Arc::new(FinancialmodelingprepMarketCapitalizationCollector::new(
-        pool.clone(),
-        client.clone(),
-        key_manager,
-    ))
+    params: FinancialmodelingprepMarketCapitalizationParams,
+) -> Arc<FinancialmodelingprepMarketCapitalizationCollector> {
+    Arc::new(
+        FinancialmodelingprepMarketCapitalizationCollector::new(
+            pool.clone(),
+            client.clone(),
+            key_manager,
+        )
+        .with_params(params),
+    )
 }
 
 fn create_action_financial_modeling_company_profile(
     pool: &sqlx::Pool<sqlx::Postgres>,
     client: &Client,
     key_manager: Arc<Mutex<KeyManager>>,
+    params: FinancialmodelingprepCompanyProfileParams,
 ) -> Arc<FinancialmodelingprepCompanyProfileCollector> {
-    Arc::new(FinancialmodelingprepCompanyProfileCollector::new(
-        pool.clone(),
-        client.clone(),
-        key_manager,
-    ))
+    Arc::new(
+        FinancialmodelingprepCompanyProfileCollector::new(
+            pool.clone(),
+            client.clone(),
+            key_manager,
+        )
+        .with_params(params),
+    )
 }
 
 fn create_action_polygon_grouped_daily(
     pool: &sqlx::Pool<sqlx::Postgres>,
     client: &Client,
     key_manager: Arc<Mutex<KeyManager>>,
+    params: PolygonGroupedDailyParams,
 ) -> Arc<PolygonGroupedDailyCollector> {
-    Arc::new(PolygonGroupedDailyCollector::new(
-        pool.clone(),
-        client.clone(),
-        key_manager,
-    ))
+    Arc::new(
+        PolygonGroupedDailyCollector::new(pool.clone(), client.clone(), key_manager)
+            .with_params(params),
+    )
 }
 
 fn create_action_polygon_grouped_daily_stager(
@@ -151,12 +267,44 @@ fn create_action_polygon_open_close(
     pool: &sqlx::Pool<sqlx::Postgres>,
     client: &Client,
     key_manager: Arc<Mutex<KeyManager>>,
+    params: PolygonOpenCloseParams,
 ) -> Arc<PolygonOpenCloseCollector> {
-    Arc::new(PolygonOpenCloseCollector::new(
-        pool.clone(),
-        client.clone(),
-        key_manager,
-    ))
+    Arc::new(
+        PolygonOpenCloseCollector::new(pool.clone(), client.clone(), key_manager)
+            .with_params(params),
+    )
+}
+
+fn create_action_sensor(
+    setting: &SensorSetting,
+    pool: &sqlx::Pool<sqlx::Postgres>,
+    client: &Client,
+) -> Arc<Sensor> {
+    let condition = match &setting.condition {
+        SensorConditionSetting::Sql(query) => SensorCondition::SqlPredicate {
+            pool: pool.clone(),
+            query: query.clone(),
+        },
+        SensorConditionSetting::File {
+            path,
+            max_age_seconds,
+        } => SensorCondition::File {
+            path: PathBuf::from(path),
+            max_age: max_age_seconds.map(Duration::from_secs),
+        },
+        SensorConditionSetting::HttpHead(url) => SensorCondition::HttpHead {
+            client: client.clone(),
+            url: url.clone(),
+        },
+    };
+    let sensor = Sensor::new(
+        condition,
+        Duration::from_secs(setting.poke_interval_seconds),
+    );
+    Arc::new(match setting.timeout_seconds {
+        Some(seconds) => sensor.with_timeout(Duration::from_secs(seconds)),
+        None => sensor,
+    })
 }
 
 /// Possible Actions
@@ -175,6 +323,8 @@ pub enum ActionType {
     FinmodCompanyProfileStage,
     FinmodMarketCapCollect,
     FinmodMarketCapStager,
+    /// waits for an external condition, e.g. `task_type: { Sensor: { condition: { sql: "select true" } } }`
+    Sensor(SensorSetting),
     Dummy,
 }
 
@@ -221,3 +371,42 @@ pub enum ActionType {
 //         true
 //     }
 // }
+
+#[cfg(test)]
+mod test {
+    use crate::actions::action::parse_params;
+    use crate::actions::collect::polygon_grouped_daily::PolygonGroupedDailyParams;
+    use config::{Config, File, FileFormat};
+
+    fn params_from_yaml(yaml: &str) -> config::Value {
+        Config::builder()
+            .add_source(File::from_str(yaml, FileFormat::Yaml))
+            .build()
+            .unwrap()
+            .get("params")
+            .unwrap()
+    }
+
+    #[test]
+    fn missing_params_take_defaults() {
+        let params: PolygonGroupedDailyParams = parse_params(None).unwrap();
+        assert_eq!(params, PolygonGroupedDailyParams::default());
+
+        let value = params_from_yaml("params: { history_months: 12 }");
+        let params: PolygonGroupedDailyParams = parse_params(Some(&value)).unwrap();
+        assert_eq!(params.history_months, 12);
+        assert_eq!(
+            params.wait_for_key,
+            PolygonGroupedDailyParams::default().wait_for_key
+        );
+    }
+
+    #[test]
+    fn unknown_or_mistyped_params_are_rejected() {
+        let unknown = params_from_yaml("params: { history_month: 12 }");
+        assert!(parse_params::<PolygonGroupedDailyParams>(Some(&unknown)).is_err());
+
+        let mistyped = params_from_yaml("params: { history_months: many }");
+        assert!(parse_params::<PolygonGroupedDailyParams>(Some(&mistyped)).is_err());
+    }
+}
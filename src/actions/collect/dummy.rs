@@ -1,3 +1,4 @@
+use crate::dag_schedule::context::TaskContext;
 use crate::dag_schedule::task::{Runnable, StatsMap, TaskError};
 
 use async_trait::async_trait;
@@ -23,7 +24,7 @@ impl Default for DummyCollector {
 #[async_trait]
 impl Runnable for DummyCollector {
     #[tracing::instrument(name = "Run DummyCollector", skip(self))]
-    async fn run(&self) -> Result<Option<StatsMap>, TaskError> {
+    async fn run(&self, _context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
         dummy_function(8).await;
         Ok(None)
     }
@@ -1,10 +1,12 @@
 use crate::api_keys::api_key::Status::{self};
 use crate::api_keys::api_key::{ApiKey, ApiKeyPlatform};
 use crate::api_keys::key_manager::KeyManager;
+use crate::dag_schedule::context::TaskContext;
 use crate::dag_schedule::task::TaskError::UnexpectedError;
 use crate::dag_schedule::task::{Runnable, StatsMap};
 use async_trait::async_trait;
 use chrono::NaiveDate;
+use tokio_util::sync::CancellationToken;
 
 use futures_util::TryFutureExt;
 use reqwest::Client;
@@ -49,6 +51,23 @@ pub struct FinancialmodelingprepCompanyProfileCollector {
     pool: PgPool,
     client: Client,
     key_manager: Arc<Mutex<KeyManager>>,
+    params: FinancialmodelingprepCompanyProfileParams,
+}
+
+/// Parameters of the collector, configured as `params` of the task
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FinancialmodelingprepCompanyProfileParams {
+    /// waits for a ready api key instead of stopping once all keys are exhausted
+    pub wait_for_key: bool,
+}
+
+impl Default for FinancialmodelingprepCompanyProfileParams {
+    fn default() -> Self {
+        FinancialmodelingprepCompanyProfileParams {
+            wait_for_key: WAIT_FOR_KEY,
+        }
+    }
 }
 
 impl FinancialmodelingprepCompanyProfileCollector {
@@ -58,8 +77,14 @@ impl FinancialmodelingprepCompanyProfileCollector {
             pool,
             client,
             key_manager,
+            params: FinancialmodelingprepCompanyProfileParams::default(),
         }
     }
+
+    pub fn with_params(mut self, params: FinancialmodelingprepCompanyProfileParams) -> Self {
+        self.params = params;
+        self
+    }
 }
 
 impl Display for FinancialmodelingprepCompanyProfileCollector {
@@ -71,14 +96,22 @@ impl Display for FinancialmodelingprepCompanyProfileCollector {
 #[async_trait]
 impl Runnable for FinancialmodelingprepCompanyProfileCollector {
     #[tracing::instrument(name = "Run FinancialmodelingprepCompanyProfileColletor", skip(self))]
-    async fn run(&self) -> Result<Option<StatsMap>, crate::dag_schedule::task::TaskError> {
+    async fn run(
+        &self,
+        context: TaskContext,
+    ) -> Result<Option<StatsMap>, crate::dag_schedule::task::TaskError> {
         load_and_store_missing_data(
             self.pool.clone(),
             self.client.clone(),
             self.key_manager.clone(),
+            &context.cancel,
+            &self.params,
         )
         .map_err(UnexpectedError)
         .await?;
+        if context.cancel.is_cancelled() {
+            return Err(crate::dag_schedule::task::TaskError::Cancelled);
+        }
         Ok(None)
     }
 }
@@ -151,8 +184,11 @@ pub async fn load_and_store_missing_data(
     connection_pool: PgPool,
     client: Client,
     key_manager: Arc<Mutex<KeyManager>>,
+    cancel: &CancellationToken,
+    params: &FinancialmodelingprepCompanyProfileParams,
 ) -> Result<(), anyhow::Error> {
-    load_and_store_missing_data_given_url(connection_pool, client, key_manager, URL).await
+    load_and_store_missing_data_given_url(connection_pool, client, key_manager, cancel, URL, params)
+        .await
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
@@ -160,19 +196,27 @@ async fn load_and_store_missing_data_given_url(
     connection_pool: sqlx::Pool<sqlx::Postgres>,
     client: Client,
     key_manager: Arc<Mutex<KeyManager>>,
+    cancel: &CancellationToken,
     url: &str,
+    params: &FinancialmodelingprepCompanyProfileParams,
 ) -> Result<(), anyhow::Error> {
     info!("Starting to load Financialmodelingprep Company Profile Collector.");
     let last_issue_symbol = "".to_string();
     let mut potential_issue_sybmol: Option<String> =
         get_next_issue_symbol(&connection_pool, &last_issue_symbol).await?;
     let mut general_api_key =
-        KeyManager::get_new_apikey_or_wait(key_manager.clone(), WAIT_FOR_KEY, PLATFORM).await;
+        KeyManager::get_new_apikey_or_wait(key_manager.clone(), params.wait_for_key, PLATFORM)
+            .await;
     let mut _successful_request_counter: u16 = 0; // Variable actually used, but clippy is buggy? with the shorthand += below. (clippy 0.1.79)
     while let (Some(issue_sybmol), Some(mut api_key)) = (
         potential_issue_sybmol.as_ref(),
         general_api_key.take_if(|_| potential_issue_sybmol.is_some()),
     ) {
+        if cancel.is_cancelled() {
+            info!("Cancelled, stop before symbol {}", issue_sybmol);
+            general_api_key = Some(api_key);
+            break;
+        }
         info!("Requesting symbol {}", issue_sybmol);
         let last_issue_symbol = issue_sybmol;
         let mut request = create_finprep_company_request(url, issue_sybmol, &mut api_key);
@@ -205,7 +249,7 @@ async fn load_and_store_missing_data_given_url(
         potential_issue_sybmol = get_next_issue_symbol(&connection_pool, last_issue_symbol).await?;
         general_api_key = KeyManager::exchange_apikey_or_wait_if_non_ready(
             key_manager.clone(),
-            WAIT_FOR_KEY,
+            params.wait_for_key,
             api_key,
             PLATFORM,
         )
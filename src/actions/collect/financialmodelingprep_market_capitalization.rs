@@ -1,7 +1,9 @@
 use crate::api_keys::api_key::{ApiKey, ApiKeyPlatform, Status};
 use crate::api_keys::key_manager::KeyManager;
+use crate::dag_schedule::context::TaskContext;
+use crate::dag_schedule::fan_out::FanOut;
 use crate::dag_schedule::task::TaskError::UnexpectedError;
-use crate::dag_schedule::task::{Runnable, StatsMap};
+use crate::dag_schedule::task::{Runnable, StatsMap, TaskError};
 use async_trait::async_trait;
 use chrono::{Days, Duration, NaiveDate, Utc};
 use futures_util::TryFutureExt;
@@ -12,6 +14,7 @@ use serde_with::serde_as;
 use sqlx::PgPool;
 use std::fmt::{Debug, Display};
 use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
 
 use tracing::{debug, info, warn};
 
@@ -49,6 +52,26 @@ pub struct FinancialmodelingprepMarketCapitalizationCollector {
     client: Client,
     // api_key: Option<Secret<String>>,
     key_manager: Arc<Mutex<KeyManager>>,
+    params: FinancialmodelingprepMarketCapitalizationParams,
+}
+
+/// Parameters of the collector, configured as `params` of the task
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct FinancialmodelingprepMarketCapitalizationParams {
+    /// waits for a ready api key instead of stopping once all keys are exhausted
+    pub wait_for_key: bool,
+    /// number of days requested at once
+    pub page_entry_limit: u32,
+}
+
+impl Default for FinancialmodelingprepMarketCapitalizationParams {
+    fn default() -> Self {
+        FinancialmodelingprepMarketCapitalizationParams {
+            wait_for_key: WAIT_FOR_KEY,
+            page_entry_limit: PAGE_ENTRY_LIMIT,
+        }
+    }
 }
 
 impl FinancialmodelingprepMarketCapitalizationCollector {
@@ -64,8 +87,14 @@ impl FinancialmodelingprepMarketCapitalizationCollector {
             client,
             // api_key,
             key_manager,
+            params: FinancialmodelingprepMarketCapitalizationParams::default(),
         }
     }
+
+    pub fn with_params(mut self, params: FinancialmodelingprepMarketCapitalizationParams) -> Self {
+        self.params = params;
+        self
+    }
 }
 
 impl Display for FinancialmodelingprepMarketCapitalizationCollector {
@@ -83,12 +112,17 @@ impl Runnable for FinancialmodelingprepMarketCapitalizationCollector {
         name = "Run FinancialmodelingprepMarketCapitalizationColletor",
         skip(self)
     )]
-    async fn run(&self) -> Result<Option<StatsMap>, crate::dag_schedule::task::TaskError> {
+    async fn run(
+        &self,
+        context: TaskContext,
+    ) -> Result<Option<StatsMap>, crate::dag_schedule::task::TaskError> {
         // if let Some(key) = &self.api_key {
         load_and_store_missing_data(
             self.pool.clone(),
             self.client.clone(),
             self.key_manager.clone(),
+            &context.cancel,
+            &self.params,
         )
         .map_err(UnexpectedError)
         .await?;
@@ -98,10 +132,63 @@ impl Runnable for FinancialmodelingprepMarketCapitalizationCollector {
         //         "FinancialmodelingprepMarketCapitalizationColletor key not provided",
         //     )));
         // }
+        if context.cancel.is_cancelled() {
+            return Err(crate::dag_schedule::task::TaskError::Cancelled);
+        }
         Ok(None)
     }
 }
 
+/// Collects every symbol as item of its own, see [`FanOutRunnable`](crate::dag_schedule::fan_out::FanOutRunnable)
+#[async_trait]
+impl FanOut for FinancialmodelingprepMarketCapitalizationCollector {
+    async fn items(&self, _context: &TaskContext) -> Result<Vec<String>, TaskError> {
+        let mut issue_symbols = vec![];
+        while let Some(issue_symbol) = get_next_uncollected_issue_symbol(&self.pool, &issue_symbols)
+            .await
+            .map_err(UnexpectedError)?
+        {
+            issue_symbols.push(issue_symbol);
+        }
+        Ok(issue_symbols)
+    }
+
+    async fn run_item(&self, item: &str, _context: &TaskContext) -> Result<(), TaskError> {
+        let Some(mut api_key) = KeyManager::get_new_apikey_or_wait(
+            self.key_manager.clone(),
+            self.params.wait_for_key,
+            PLATFORM,
+        )
+        .await
+        else {
+            return Err(UnexpectedError(anyhow::anyhow!(
+                "No api key ready to collect {}",
+                item
+            )));
+        };
+        let result = load_and_store_symbol(
+            &self.pool,
+            &self.client,
+            URL,
+            item,
+            &mut api_key,
+            &self.params,
+        )
+        .await;
+        self.key_manager
+            .lock()
+            .expect("msg")
+            .add_key_by_platform(api_key);
+        if !result.map_err(UnexpectedError)? {
+            return Err(UnexpectedError(anyhow::anyhow!(
+                "No api key ready to collect all days of {}",
+                item
+            )));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
 enum Responses {
@@ -158,8 +245,11 @@ pub async fn load_and_store_missing_data(
     connection_pool: PgPool,
     client: Client,
     key_manager: Arc<Mutex<KeyManager>>,
+    cancel: &CancellationToken,
+    params: &FinancialmodelingprepMarketCapitalizationParams,
 ) -> Result<(), anyhow::Error> {
-    load_and_store_missing_data_given_url(connection_pool, client, key_manager, URL).await
+    load_and_store_missing_data_given_url(connection_pool, client, key_manager, cancel, URL, params)
+        .await
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
@@ -167,7 +257,9 @@ async fn load_and_store_missing_data_given_url(
     connection_pool: sqlx::Pool<sqlx::Postgres>,
     client: Client,
     key_manager: Arc<Mutex<KeyManager>>,
+    cancel: &CancellationToken,
     url: &str,
+    params: &FinancialmodelingprepMarketCapitalizationParams,
 ) -> Result<(), anyhow::Error> {
     info!("Starting to load Financialmodelingprep Market Capitalization Collector.");
     let mut already_searched_symbols: Vec<String> = vec![];
@@ -176,69 +268,32 @@ async fn load_and_store_missing_data_given_url(
 
     info!("Next symbol: {:?}", potential_issue_sybmol);
     let mut general_api_key =
-        KeyManager::get_new_apikey_or_wait(key_manager.clone(), WAIT_FOR_KEY, PLATFORM).await;
-    let mut _successful_request_counter: u16 = 0;
+        KeyManager::get_new_apikey_or_wait(key_manager.clone(), params.wait_for_key, PLATFORM)
+            .await;
     while let (Some(issue_sybmol), Some(mut api_key)) = (
         potential_issue_sybmol.as_ref(),
         general_api_key.take_if(|_| potential_issue_sybmol.is_some()),
     ) {
-        info!("Searching start date for symbol {}", &issue_sybmol);
-        let mut start_request_date: NaiveDate =
-            search_start_date(&connection_pool, issue_sybmol).await?;
-        info!("Requesting symbol {}", &issue_sybmol);
-        already_searched_symbols.push(issue_sybmol.clone());
-        while start_request_date < Utc::now().date_naive() && api_key.get_status() == Status::Ready
-        {
-            let mut request = create_polygon_market_capitalization_request(
-                url,
-                issue_sybmol,
-                &start_request_date,
-                &mut api_key,
-            );
-            info!(
-                "Financialmodelingprep market capitalization request: {}",
-                request
-            );
-            let response = client
-                .get(request.expose_secret())
-                .send()
-                .await?
-                .text()
-                .await?;
-            debug!("Response: {}", response);
-            //TODO: Handle error
-            let parsed = crate::utils::action_helpers::parse_response::<Responses>(&response)?;
-            match parsed {
-                Responses::Data(data) => {
-                    store_data(data, &connection_pool).await?;
-                    _successful_request_counter += 1;
-                }
-                Responses::KeyExhausted(_) => {
-                    api_key.set_status(Status::Exhausted);
-                }
-                Responses::NotFound(_) => {
-                    info!("Stock symbol '{}' not found.", issue_sybmol);
-                    //Mark as not found if request range covers today
-                    if start_request_date <= Utc::now().date_naive()
-                        && Utc::now().date_naive()
-                            <= start_request_date
-                                .checked_add_days(Days::new((PAGE_ENTRY_LIMIT - 1).into()))
-                                .expect("Adding some days should always stay in range")
-                    {
-                        add_missing_issue_symbol(issue_sybmol, &connection_pool).await?;
-                    }
-                }
-            }
-
-            start_request_date = start_request_date
-                .checked_add_days(Days::new(1313))
-                .expect("Should not leave date range.");
+        if cancel.is_cancelled() {
+            info!("Cancelled, stop before symbol {}", issue_sybmol);
+            general_api_key = Some(api_key);
+            break;
         }
+        already_searched_symbols.push(issue_sybmol.clone());
+        load_and_store_symbol(
+            &connection_pool,
+            &client,
+            url,
+            issue_sybmol,
+            &mut api_key,
+            params,
+        )
+        .await?;
         potential_issue_sybmol =
             get_next_uncollected_issue_symbol(&connection_pool, &already_searched_symbols).await?;
         general_api_key = KeyManager::exchange_apikey_or_wait_if_non_ready(
             key_manager.clone(),
-            WAIT_FOR_KEY,
+            params.wait_for_key,
             api_key,
             PLATFORM,
         )
@@ -251,9 +306,72 @@ async fn load_and_store_missing_data_given_url(
     Ok(())
 }
 
+/// Collects the missing market capitalization of the symbol while the api key is ready.
+/// Returns whether the symbol is up to date, which it is not if the key got exhausted.
+async fn load_and_store_symbol(
+    connection_pool: &PgPool,
+    client: &Client,
+    url: &str,
+    issue_sybmol: &str,
+    api_key: &mut Box<dyn ApiKey>,
+    params: &FinancialmodelingprepMarketCapitalizationParams,
+) -> Result<bool, anyhow::Error> {
+    info!("Searching start date for symbol {}", &issue_sybmol);
+    let mut start_request_date: NaiveDate =
+        search_start_date(connection_pool, issue_sybmol).await?;
+    info!("Requesting symbol {}", &issue_sybmol);
+    while start_request_date < Utc::now().date_naive() && api_key.get_status() == Status::Ready {
+        let mut request = create_polygon_market_capitalization_request(
+            url,
+            issue_sybmol,
+            &start_request_date,
+            params.page_entry_limit,
+            api_key,
+        );
+        info!(
+            "Financialmodelingprep market capitalization request: {}",
+            request
+        );
+        let response = client
+            .get(request.expose_secret())
+            .send()
+            .await?
+            .text()
+            .await?;
+        debug!("Response: {}", response);
+        //TODO: Handle error
+        let parsed = crate::utils::action_helpers::parse_response::<Responses>(&response)?;
+        match parsed {
+            Responses::Data(data) => {
+                store_data(data, connection_pool).await?;
+            }
+            Responses::KeyExhausted(_) => {
+                api_key.set_status(Status::Exhausted);
+            }
+            Responses::NotFound(_) => {
+                info!("Stock symbol '{}' not found.", issue_sybmol);
+                //Mark as not found if request range covers today
+                if start_request_date <= Utc::now().date_naive()
+                    && Utc::now().date_naive()
+                        <= start_request_date
+                            .checked_add_days(Days::new((params.page_entry_limit - 1).into()))
+                            .expect("Adding some days should always stay in range")
+                {
+                    add_missing_issue_symbol(issue_sybmol, connection_pool).await?;
+                }
+            }
+        }
+
+        start_request_date = start_request_date
+            .checked_add_days(Days::new(params.page_entry_limit.into()))
+            .expect("Should not leave date range.");
+    }
+    Ok(start_request_date >= Utc::now().date_naive() && api_key.get_status() == Status::Ready)
+}
+
 async fn search_start_date(
     connection_pool: &PgPool,
-    issue_sybmol: &String,
+    issue_sybmol: &str,
 ) -> Result<NaiveDate, anyhow::Error> {
     // Search for existing date in time series
     let result = sqlx::query!(
@@ -440,15 +558,18 @@ fn create_polygon_market_capitalization_request<'a>(
     base_url: &'a str,
     issue_symbol: &str,
     start_date: &NaiveDate,
+    page_entry_limit: u32,
     api_key: &'a mut Box<dyn ApiKey>,
 ) -> FinancialmodelingprepMarketCapitalizationRequest<'a> {
     let end_date = start_date
-        .checked_add_days(Days::new((PAGE_ENTRY_LIMIT - 1).into()))
+        .checked_add_days(Days::new((page_entry_limit - 1).into()))
         .expect("Should not leave date range.");
 
     let base_request_url = base_url.to_string()
         + issue_symbol.to_string().as_str()
-        + "?limit=1313&from="
+        + "?limit="
+        + &page_entry_limit.to_string()
+        + "&from="
         + &start_date.to_string()
         + "&to="
         + &end_date.to_string()
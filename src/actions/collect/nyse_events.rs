@@ -8,7 +8,9 @@ use serde_with::{serde_as, NoneAsEmptyString};
 use std::fmt::Display;
 
 use crate::utils::action_helpers;
+use crate::utils::action_helpers::DateRange;
 
+use crate::dag_schedule::context::TaskContext;
 use crate::dag_schedule::task::{Runnable, StatsMap, TaskError};
 
 use crate::dag_schedule::task::TaskError::UnexpectedError;
@@ -21,11 +23,22 @@ const NYSE_EVENT_URL: &str = "https://www.nyse.com/api/nyseservice/v1/corpax/";
 pub struct NyseEventCollector {
     pool: PgPool,
     client: Client,
+    backfill: Option<DateRange>,
 }
 
 impl NyseEventCollector {
     pub fn new(pool: PgPool, client: Client) -> Self {
-        NyseEventCollector { pool, client }
+        NyseEventCollector {
+            pool,
+            client,
+            backfill: None,
+        }
+    }
+
+    /// Collects the action dates of the range again instead of the missing weeks
+    pub fn with_backfill(mut self, range: DateRange) -> Self {
+        self.backfill = Some(range);
+        self
     }
 }
 
@@ -38,10 +51,19 @@ impl Display for NyseEventCollector {
 #[async_trait]
 impl Runnable for NyseEventCollector {
     #[tracing::instrument(name = "Run NyseEventCollector", skip(self))]
-    async fn run(&self) -> Result<Option<StatsMap>, TaskError> {
-        load_and_store_missing_data(self.pool.clone(), self.client.clone())
-            .map_err(UnexpectedError)
-            .await?;
+    async fn run(&self, _context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
+        match &self.backfill {
+            Some(range) => {
+                backfill(self.pool.clone(), self.client.clone(), range)
+                    .map_err(UnexpectedError)
+                    .await?
+            }
+            None => {
+                load_and_store_missing_data(self.pool.clone(), self.client.clone())
+                    .map_err(UnexpectedError)
+                    .await?
+            }
+        }
         Ok(None)
     }
 }
@@ -128,6 +150,36 @@ pub async fn load_and_store_missing_data(
     .await
 }
 
+/// Loads the events of the action dates in the range again and replaces the stored events of these dates.
+/// The range has to end at the latest collected action date, so that the incremental collection continues as before.
+#[tracing::instrument(level = "debug", skip_all)]
+pub async fn backfill(
+    connection_pool: PgPool,
+    client: Client,
+    range: &DateRange,
+) -> Result<(), anyhow::Error> {
+    backfill_given_url(connection_pool, client, NYSE_EVENT_URL, range).await
+}
+
+#[tracing::instrument(level = "debug", skip_all)]
+async fn backfill_given_url(
+    connection_pool: PgPool,
+    client: Client,
+    url: &str,
+    range: &DateRange,
+) -> Result<(), anyhow::Error> {
+    info!(
+        "Starting to backfill NYSE events from {} to {}",
+        range.from, range.to
+    );
+    let watermark: Option<NaiveDate> =
+        sqlx::query_scalar("select max(action_date) from nyse_events")
+            .fetch_one(&connection_pool)
+            .await?;
+    range.check_within_watermark(watermark)?;
+    load_and_store_weeks(connection_pool, client, url, *range, true).await
+}
+
 #[tracing::instrument(level = "debug", skip_all)]
 async fn load_and_store_missing_data_given_url(
     connection_pool: PgPool,
@@ -136,12 +188,50 @@ async fn load_and_store_missing_data_given_url(
     upper_date_limit: NaiveDate,
 ) -> Result<(), anyhow::Error> {
     info!("Starting to load NYSE events");
-    let mut latest_date = latest_date_available(&connection_pool).await;
-    while latest_date <= upper_date_limit {
+    let dates = DateRange {
+        from: latest_date_available(&connection_pool).await,
+        to: upper_date_limit,
+    };
+    load_and_store_weeks(connection_pool, client, url, dates, false).await
+}
+
+/// Loads and stores the events week by week, starting at the first date of the range. Stored events
+/// of the loaded dates are replaced if `overwrite` is set.
+#[tracing::instrument(level = "debug", skip_all)]
+async fn load_and_store_weeks(
+    connection_pool: PgPool,
+    client: Client,
+    url: &str,
+    dates: DateRange,
+    overwrite: bool,
+) -> Result<(), anyhow::Error> {
+    let mut latest_date = dates.from;
+    while latest_date <= dates.to {
         debug!("Loading NYSE event data for week: {}", latest_date);
-        let week_data = load_missing_week(&client, &latest_date, url).await?;
+        let mut week_data = load_missing_week(&client, &latest_date, url).await?;
+        if overwrite {
+            // Events after the range are not deleted and must not be inserted next to the stored ones.
+            week_data.retain(|data| {
+                data.action_date
+                    .as_ref()
+                    .and_then(|action_date| NaiveDate::parse_from_str(action_date, "%Y-%m-%d").ok())
+                    .is_none_or(|action_date| action_date <= dates.to)
+            });
+        }
         let week_data = transpose_nyse_data_and_filter(week_data);
 
+        let mut transaction = connection_pool.begin().await?;
+        if overwrite {
+            let week_end = latest_date
+                .checked_add_days(Days::new(6))
+                .expect("Date should never leave the allowed range.")
+                .min(dates.to);
+            sqlx::query("DELETE FROM nyse_events WHERE action_date BETWEEN $1 AND $2")
+                .bind(latest_date)
+                .bind(week_end)
+                .execute(&mut *transaction)
+                .await?;
+        }
         sqlx::query!("INSERT INTO nyse_events
             (action_date, action_status, action_type, issue_symbol, issuer_name, updated_at, market_event)
             Select * from UNNEST ($1::date[], $2::text[], $3::text[], $4::text[], $5::text[], $6::text[], $7::text[]) on conflict do nothing",
@@ -152,8 +242,9 @@ async fn load_and_store_missing_data_given_url(
         &week_data.issuer_name[..],
         &week_data.updated_at[..],
         &week_data.market_event[..],
-    ).execute(&connection_pool)
+    ).execute(&mut *transaction)
             .await?;
+        transaction.commit().await?;
 
         latest_date = latest_date
             .checked_add_days(Days::new(7))
@@ -581,6 +672,63 @@ mod test {
         assert_eq!(saved.is_staged, false);
     }
 
+    #[sqlx::test]
+    async fn backfill_replaces_stored_events_of_range(pool: PgPool) {
+        sqlx::query(
+            r#"INSERT INTO nyse_events (action_date, action_status, action_type, issue_symbol, issuer_name, updated_at, market_event, is_staged) VALUES('2015-12-08', 'Pending before the Open', 'Suspend', 'SQNS', 'Sequans Communications S.A.', '2023-10-20T09:24:47.134141-04:00', 'replaced', true), ('2015-12-14', 'Pending before the Open', 'Suspend', 'SQNS', 'Sequans Communications S.A.', '2023-10-20T09:24:47.134141-04:00', 'kept', true);"#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        let server = MockServer::start();
+        let url = server.base_url();
+        let range = DateRange::new(
+            NaiveDate::from_ymd_opt(2015, 12, 7).unwrap(),
+            NaiveDate::from_ymd_opt(2015, 12, 13).unwrap(),
+        )
+        .unwrap();
+
+        let input_json = r#"{"count":2,"next":null,"previous":null,"results":[{"action_date":"2015-12-09","action_status":"Pending before the Open","action_type":"Suspend","issue_symbol":"SQNS","issuer_name":"Sequans Communications S.A.","updated_at":"2023-10-20T09:24:47.134141-04:00","market_event":"backfilled"},{"action_date":"2015-12-14","action_status":"Pending before the Open","action_type":"Suspend","issue_symbol":"SQNS","issuer_name":"Sequans Communications S.A.","updated_at":"2023-10-20T09:24:47.134141-04:00","market_event":"after range"}]}"#;
+        for page_size in ["1", "100"] {
+            server.mock(|when, then| {
+                when.method(GET)
+                    .query_param("action_date__gte", "2015-12-07")
+                    .query_param("action_date__lte", "2015-12-13")
+                    .query_param("page", "1")
+                    .query_param("page_size", page_size);
+                then.status(200)
+                    .header("content-type", "text/html")
+                    .body(input_json);
+            });
+        }
+
+        backfill_given_url(pool.clone(), get_test_client(), &url, &range)
+            .await
+            .unwrap();
+
+        let saved: Vec<(NaiveDate, String, bool)> = sqlx::query_as(
+            "SELECT action_date, market_event, is_staged FROM nyse_events ORDER BY action_date",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            saved,
+            vec![
+                (
+                    NaiveDate::from_ymd_opt(2015, 12, 9).unwrap(),
+                    "backfilled".to_string(),
+                    false
+                ),
+                (
+                    NaiveDate::from_ymd_opt(2015, 12, 14).unwrap(),
+                    "kept".to_string(),
+                    true
+                ),
+            ]
+        );
+    }
+
     #[test]
     fn parse_nyse_response_with_empty_strings() {
         let input_json = r#"{"count":1,"next":null,"previous":null,"results":[{"action_date":"","action_status":"","action_type":"Suspend","issue_symbol":"","issuer_name":"","updated_at":"2023-10-20T09:24:47.134141-04:00","market_event":"54a838d5-b1ae-427a-b7a3-629eb1a0de2c"}]}"#;
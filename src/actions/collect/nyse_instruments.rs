@@ -14,6 +14,7 @@ use serde_json::json;
 use sqlx::PgPool;
 use tracing::{debug, info};
 
+use crate::dag_schedule::context::TaskContext;
 use crate::dag_schedule::task::TaskError::UnexpectedError;
 use crate::dag_schedule::task::{Runnable, StatsMap, TaskError};
 
@@ -40,7 +41,7 @@ impl Display for NyseInstrumentCollector {
 #[async_trait]
 impl Runnable for NyseInstrumentCollector {
     #[tracing::instrument(name = "Run NyseInstrumentCollector", skip(self))]
-    async fn run(&self) -> Result<Option<StatsMap>, TaskError> {
+    async fn run(&self, _context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
         load_and_store_missing_data(self.pool.clone(), self.client.clone())
             .map_err(UnexpectedError)
             .await?;
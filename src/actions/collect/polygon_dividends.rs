@@ -27,11 +27,14 @@ use serde::{Deserialize, Serialize};
 
 use sqlx::PgPool;
 
+use crate::dag_schedule::context::TaskContext;
 use crate::dag_schedule::task::{Runnable, StatsMap, TaskError};
+use tokio_util::sync::CancellationToken;
 
 const URL: &str = "https://api.massive.com/stocks/v1/dividends?";
 const PLATFORM: &ApiKeyPlatform = &ApiKeyPlatform::Polygon;
 const WAIT_FOR_KEY: bool = true;
+const CUTOFF_DAYS: u64 = 30;
 
 #[derive(Debug)]
 struct PolygonDividendsRequest<'a> {
@@ -57,6 +60,26 @@ pub struct PolygonDividendsCollector {
     pool: PgPool,
     client: Client,
     key_manager: Arc<Mutex<KeyManager>>,
+    params: PolygonDividendsParams,
+}
+
+/// Parameters of the collector, configured as `params` of the task
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolygonDividendsParams {
+    /// waits for a ready api key instead of stopping once all keys are exhausted
+    pub wait_for_key: bool,
+    /// symbols without dividends are skipped for this many days
+    pub cutoff_days: u64,
+}
+
+impl Default for PolygonDividendsParams {
+    fn default() -> Self {
+        PolygonDividendsParams {
+            wait_for_key: WAIT_FOR_KEY,
+            cutoff_days: CUTOFF_DAYS,
+        }
+    }
 }
 
 impl PolygonDividendsCollector {
@@ -66,8 +89,14 @@ impl PolygonDividendsCollector {
             pool,
             client,
             key_manager,
+            params: PolygonDividendsParams::default(),
         }
     }
+
+    pub fn with_params(mut self, params: PolygonDividendsParams) -> Self {
+        self.params = params;
+        self
+    }
 }
 
 impl Display for PolygonDividendsCollector {
@@ -79,14 +108,19 @@ impl Display for PolygonDividendsCollector {
 #[async_trait]
 impl Runnable for PolygonDividendsCollector {
     #[tracing::instrument(level = "debug", skip_all)]
-    async fn run(&self) -> Result<Option<StatsMap>, TaskError> {
+    async fn run(&self, context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
         load_and_store_missing_data(
             self.pool.clone(),
             self.client.clone(),
             self.key_manager.clone(),
+            &context.cancel,
+            &self.params,
         )
         .map_err(TaskError::UnexpectedError)
         .await?;
+        if context.cancel.is_cancelled() {
+            return Err(TaskError::Cancelled);
+        }
         Ok(None)
     }
 }
@@ -96,8 +130,11 @@ pub async fn load_and_store_missing_data(
     connection_pool: PgPool,
     client: Client,
     key_manager: Arc<Mutex<KeyManager>>,
+    cancel: &CancellationToken,
+    params: &PolygonDividendsParams,
 ) -> Result<(), anyhow::Error> {
-    load_and_store_missing_data_given_url(connection_pool, client, key_manager, URL).await
+    load_and_store_missing_data_given_url(connection_pool, client, key_manager, cancel, URL, params)
+        .await
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
@@ -106,7 +143,9 @@ async fn load_and_store_missing_data_with_services(
     warden_service: &(dyn WardenServiceTrait + Send + Sync),
     client: Client,
     key_manager: Arc<Mutex<KeyManager>>,
+    cancel: &CancellationToken,
     url: &str,
+    params: &PolygonDividendsParams,
 ) -> Result<(), anyhow::Error> {
     let skippable_symbols = warden_service
         .get_missing_symbols(crate::database::warden_service::WardenType::MassiveDividends)
@@ -115,8 +154,13 @@ async fn load_and_store_missing_data_with_services(
         .get_next_issue_symbol_candidate("".to_string(), &skippable_symbols)
         .await;
     let mut general_api_key =
-        KeyManager::get_new_apikey_or_wait(key_manager.clone(), WAIT_FOR_KEY, PLATFORM).await;
+        KeyManager::get_new_apikey_or_wait(key_manager.clone(), params.wait_for_key, PLATFORM)
+            .await;
     while let (Some(issue_symbol), true) = (issue_symbol_candidate, general_api_key.is_some()) {
+        if cancel.is_cancelled() {
+            info!("Cancelled, stop before symbol {}", issue_symbol);
+            break;
+        }
         let mut api_key = general_api_key.unwrap();
         let mut request = create_polygon_dividends_request(url, &issue_symbol, &mut api_key);
         info!("Polygon dividends request: {}", request);
@@ -155,7 +199,7 @@ async fn load_and_store_missing_data_with_services(
             .await;
         general_api_key = KeyManager::exchange_apikey_or_wait_if_non_ready(
             key_manager.clone(),
-            WAIT_FOR_KEY,
+            params.wait_for_key,
             api_key,
             PLATFORM,
         )
@@ -170,16 +214,21 @@ async fn load_and_store_missing_data_given_url(
     connection_pool: sqlx::Pool<sqlx::Postgres>,
     client: Client,
     key_manager: Arc<Mutex<KeyManager>>,
+    cancel: &CancellationToken,
     url: &str,
+    params: &PolygonDividendsParams,
 ) -> Result<(), anyhow::Error> {
     let polygon_dividends_service = PolygonDividendsService::new(connection_pool.clone());
-    let warden_service = WardenService::new(connection_pool.clone());
+    let warden_service = WardenService::new(connection_pool.clone())
+        .with_massive_dividends_cutoff_days(params.cutoff_days);
     load_and_store_missing_data_with_services(
         &polygon_dividends_service,
         &warden_service,
         client,
         key_manager,
+        cancel,
         url,
+        params,
     )
     .await
 }
@@ -425,7 +474,9 @@ mod tests {
             &warden_mock,
             client,
             km.clone(),
+            &CancellationToken::new(),
             url,
+            &PolygonDividendsParams::default(),
         )
         .await;
         assert!(res.is_ok());
@@ -502,9 +553,59 @@ mod tests {
             &warden_mock,
             client,
             km.clone(),
+            &CancellationToken::new(),
+            url,
+            &PolygonDividendsParams::default(),
+        )
+        .await;
+        assert!(res.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_loop_stops_before_next_symbol_when_cancelled() {
+        let server = MockServer::start_async().await;
+        let request_mock = server
+            .mock_async(|when, then| {
+                when.method(GET);
+                then.status(200).body("{}");
+            })
+            .await;
+
+        let mut polygon_mock = MockPolygonDividendsServiceTrait::new();
+        polygon_mock
+            .expect_get_next_issue_symbol_candidate()
+            .times(1)
+            .return_once(|_, _| Box::pin(async { Some("SYM".to_string()) }));
+        polygon_mock.expect_save_all().times(0);
+
+        let mut warden_mock = MockWardenServiceTrait::new();
+        warden_mock
+            .expect_get_missing_symbols()
+            .times(1)
+            .return_once(|_| Box::pin(async { Ok(vec![]) }));
+
+        let km = Arc::new(Mutex::new(crate::api_keys::key_manager::KeyManager::new()));
+        {
+            let mut k = km.lock().unwrap();
+            k.add_key_by_platform(Box::new(PolygonKey::new("secret123".to_string())));
+        }
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let client = reqwest::Client::new();
+        let url = &format!("{}{}", server.url("/"), "stocks/v1/dividends?");
+
+        let res = load_and_store_missing_data_with_services(
+            &polygon_mock,
+            &warden_mock,
+            client,
+            km.clone(),
+            &cancel,
             url,
+            &PolygonDividendsParams::default(),
         )
         .await;
         assert!(res.is_ok());
+        request_mock.assert_hits_async(0).await;
     }
 }
@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use chrono::{Days, Months, NaiveDate, Utc};
-use futures_util::TryFutureExt;
 use secrecy::{ExposeSecret, Secret};
+use std::collections::BTreeSet;
 use std::fmt::{Debug, Display};
 use std::sync::{Arc, Mutex};
 
@@ -13,7 +13,10 @@ use tracing::{debug, info};
 
 use crate::api_keys::api_key::{ApiKey, ApiKeyPlatform, Status};
 use crate::api_keys::key_manager::KeyManager;
+use crate::dag_schedule::context::TaskContext;
 use crate::dag_schedule::task::{Runnable, StatsMap, TaskError};
+use crate::utils::action_helpers::DateRange;
+use tokio_util::sync::CancellationToken;
 
 const URL: &str = "https://api.polygon.io/v2/aggs/grouped/locale/us/market/stocks/";
 const PLATFORM: &ApiKeyPlatform = &ApiKeyPlatform::Polygon;
@@ -43,6 +46,27 @@ pub struct PolygonGroupedDailyCollector {
     pool: PgPool,
     client: Client,
     key_manager: Arc<Mutex<KeyManager>>,
+    params: PolygonGroupedDailyParams,
+    backfill: Option<DateRange>,
+}
+
+/// Parameters of the collector, configured as `params` of the task
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolygonGroupedDailyParams {
+    /// waits for a ready api key instead of stopping once all keys are exhausted
+    pub wait_for_key: bool,
+    /// number of months collected into the past if nothing was collected yet
+    pub history_months: u32,
+}
+
+impl Default for PolygonGroupedDailyParams {
+    fn default() -> Self {
+        PolygonGroupedDailyParams {
+            wait_for_key: WAIT_FOR_KEY,
+            history_months: 24,
+        }
+    }
 }
 
 impl PolygonGroupedDailyCollector {
@@ -52,8 +76,21 @@ impl PolygonGroupedDailyCollector {
             pool,
             client,
             key_manager,
+            params: PolygonGroupedDailyParams::default(),
+            backfill: None,
         }
     }
+
+    pub fn with_params(mut self, params: PolygonGroupedDailyParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Collects the business dates of the range again instead of the missing dates
+    pub fn with_backfill(mut self, range: DateRange) -> Self {
+        self.backfill = Some(range);
+        self
+    }
 }
 
 impl Display for PolygonGroupedDailyCollector {
@@ -65,24 +102,54 @@ impl Display for PolygonGroupedDailyCollector {
 #[async_trait]
 impl Runnable for PolygonGroupedDailyCollector {
     #[tracing::instrument(name = "Run PolygonGroupedDailyCollector", skip_all)]
-    async fn run(&self) -> Result<Option<StatsMap>, TaskError> {
+    async fn run(&self, context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
         // if let Some(key) = &self.api_key {
-        load_and_store_missing_data(
-            self.pool.clone(),
-            self.client.clone(),
-            self.key_manager.clone(),
-        )
-        .map_err(TaskError::UnexpectedError)
-        .await?;
+        let output = match &self.backfill {
+            Some(range) => self.backfill(&context.cancel, range).await,
+            None => {
+                self.load_and_store_missing_data_given_url(&context.cancel, URL)
+                    .await
+            }
+        }
+        .map_err(TaskError::UnexpectedError)?;
+        context.set_output(output);
         // } else {
         //     return Err(TaskError::UnexpectedError(Error::msg(
         //         "Api key not provided for PolygonGroupedDailyCollector",
         //     )));
         // }
+        if context.cancel.is_cancelled() {
+            return Err(TaskError::Cancelled);
+        }
         Ok(None)
     }
 }
 
+/// Output of the collector for the stager: business dates and symbols of the collected rows
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PolygonGroupedDailyOutput {
+    pub business_dates: BTreeSet<NaiveDate>,
+    pub symbols: BTreeSet<String>,
+}
+
+impl PolygonGroupedDailyOutput {
+    pub fn is_empty(&self) -> bool {
+        self.business_dates.is_empty()
+    }
+
+    pub fn merge(&mut self, other: &PolygonGroupedDailyOutput) {
+        self.business_dates
+            .extend(other.business_dates.iter().copied());
+        self.symbols.extend(other.symbols.iter().cloned());
+    }
+
+    fn add(&mut self, data: &TransposedPolygonOpenClose) {
+        self.business_dates
+            .extend(data.business_date.iter().copied());
+        self.symbols.extend(data.symbol.iter().cloned());
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PolygonGroupedDaily {
@@ -129,104 +196,151 @@ struct TransposedPolygonOpenClose {
     pub volume_weighted_average_price: Vec<Option<f64>>,
 }
 
-#[tracing::instrument(level = "debug", skip_all)]
-pub async fn load_and_store_missing_data(
-    connection_pool: PgPool,
-    client: Client,
-    key_manager: Arc<Mutex<KeyManager>>,
-) -> Result<(), anyhow::Error> {
-    load_and_store_missing_data_given_url(connection_pool, client, key_manager, URL).await
-}
-
-#[tracing::instrument(level = "debug", skip_all)]
-async fn load_and_store_missing_data_given_url(
-    connection_pool: sqlx::Pool<sqlx::Postgres>,
-    client: Client,
-    key_manager: Arc<Mutex<KeyManager>>,
-    url: &str,
-) -> Result<(), anyhow::Error> {
-    info!("Starting to load Polygon grouped daily.");
+impl PolygonGroupedDailyCollector {
+    /// Loads the business dates of the range again and replaces the stored rows of these dates.
+    /// The range has to end at the latest collected date, so that the incremental collection continues as before.
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn backfill(
+        &self,
+        cancel: &CancellationToken,
+        range: &DateRange,
+    ) -> Result<PolygonGroupedDailyOutput, anyhow::Error> {
+        info!(
+            "Starting to backfill Polygon grouped daily from {} to {}.",
+            range.from, range.to
+        );
+        range.check_within_watermark(latest_business_date(&self.pool).await?)?;
+        self.load_and_store_dates(cancel, URL, *range, true).await
+    }
 
-    let result = sqlx::query!(
-        "select max(business_date) as business_date
-        from polygon_grouped_daily"
-    )
-    .fetch_one(&connection_pool)
-    .await?
-    .business_date;
-
-    let mut general_api_key =
-        KeyManager::get_new_apikey_or_wait(key_manager.clone(), WAIT_FOR_KEY, PLATFORM).await;
-    let mut current_check_date = get_start_date(result);
-
-    while let Some(mut api_key) =
-        general_api_key.take_if(|_| current_check_date.lt(&Utc::now().date_naive()))
-    {
-        let mut request =
-            create_polygon_grouped_daily_request(url, &current_check_date, &mut api_key);
-        debug!("Polygon grouped daily request: {}", request);
-        let response = client
-            .get(request.expose_secret())
-            .send()
-            .await?
-            .text()
-            .await?;
-
-        let open_close =
-            crate::utils::action_helpers::parse_response::<PolygonGroupedDaily>(&response)?;
-
-        if let Some(results) = open_close.results {
-            let open_close = transpose_polygon_grouped_daily(results, current_check_date);
-
-            sqlx::query!(r#"INSERT INTO public.polygon_grouped_daily ("close", business_date, high, low, "open", symbol, order_amount, stock_traded, volume_weighted_average_price)
-                Select * from UNNEST ($1::float[], $2::date[], $3::float[], $4::float[], $5::float[], $6::text[], $7::float[], $8::float[], $9::float[]) on conflict do nothing"#,
-                &open_close.close[..],
-                &open_close.business_date[..],
-                &open_close.high[..],
-                &open_close.low[..],
-                &open_close.open[..],
-                &open_close.symbol[..],
-                &open_close.order_amount[..] as _,
-                &open_close.stock_traded[..],
-                &open_close.volume_weighted_average_price[..] as _,)
-            .execute(&connection_pool).await?;
-        }
-        if open_close.status != *"ERROR" {
-            current_check_date = current_check_date
-                .checked_add_days(Days::new(1))
-                .expect("Adding one day must always work, given the operating date context.");
-            // sleep(time::Duration::from_secs(13)).await;
-        } else {
-            info!(
-                "Failed with request {} and got response {}",
-                request, response
-            );
-            api_key.set_status(Status::Exhausted);
-        }
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn load_and_store_missing_data_given_url(
+        &self,
+        cancel: &CancellationToken,
+        url: &str,
+    ) -> Result<PolygonGroupedDailyOutput, anyhow::Error> {
+        info!("Starting to load Polygon grouped daily.");
+        let result = latest_business_date(&self.pool).await?;
+        let dates = DateRange {
+            from: get_start_date(result, self.params.history_months),
+            to: Utc::now()
+                .date_naive()
+                .pred_opt()
+                .expect("Yesterday must always exist, given the operating date context."),
+        };
+        self.load_and_store_dates(cancel, url, dates, false).await
+    }
 
-        general_api_key = KeyManager::exchange_apikey_or_wait_if_non_ready(
-            key_manager.clone(),
-            WAIT_FOR_KEY,
-            api_key,
+    /// Loads and stores the business dates of the range, stored rows of a loaded date are replaced if `overwrite` is set
+    #[tracing::instrument(level = "debug", skip_all)]
+    async fn load_and_store_dates(
+        &self,
+        cancel: &CancellationToken,
+        url: &str,
+        dates: DateRange,
+        overwrite: bool,
+    ) -> Result<PolygonGroupedDailyOutput, anyhow::Error> {
+        let mut output = PolygonGroupedDailyOutput::default();
+        let mut general_api_key = KeyManager::get_new_apikey_or_wait(
+            self.key_manager.clone(),
+            self.params.wait_for_key,
             PLATFORM,
         )
         .await;
+        let mut current_check_date = dates.from;
+
+        while let Some(mut api_key) = general_api_key.take_if(|_| current_check_date <= dates.to) {
+            if cancel.is_cancelled() {
+                info!("Cancelled, stop before date {}", current_check_date);
+                general_api_key = Some(api_key);
+                break;
+            }
+            let mut request =
+                create_polygon_grouped_daily_request(url, &current_check_date, &mut api_key);
+            debug!("Polygon grouped daily request: {}", request);
+            let response = self
+                .client
+                .get(request.expose_secret())
+                .send()
+                .await?
+                .text()
+                .await?;
+
+            let open_close =
+                crate::utils::action_helpers::parse_response::<PolygonGroupedDaily>(&response)?;
+
+            if let Some(results) = open_close.results {
+                let open_close = transpose_polygon_grouped_daily(results, current_check_date);
+
+                let mut transaction = self.pool.begin().await?;
+                if overwrite {
+                    sqlx::query("DELETE FROM polygon_grouped_daily WHERE business_date = $1")
+                        .bind(current_check_date)
+                        .execute(&mut *transaction)
+                        .await?;
+                }
+                sqlx::query!(r#"INSERT INTO public.polygon_grouped_daily ("close", business_date, high, low, "open", symbol, order_amount, stock_traded, volume_weighted_average_price)
+                Select * from UNNEST ($1::float[], $2::date[], $3::float[], $4::float[], $5::float[], $6::text[], $7::float[], $8::float[], $9::float[]) on conflict do nothing"#,
+                    &open_close.close[..],
+                    &open_close.business_date[..],
+                    &open_close.high[..],
+                    &open_close.low[..],
+                    &open_close.open[..],
+                    &open_close.symbol[..],
+                    &open_close.order_amount[..] as _,
+                    &open_close.stock_traded[..],
+                    &open_close.volume_weighted_average_price[..] as _,)
+                .execute(&mut *transaction).await?;
+                transaction.commit().await?;
+                output.add(&open_close);
+            }
+            if open_close.status != *"ERROR" {
+                current_check_date = current_check_date
+                    .checked_add_days(Days::new(1))
+                    .expect("Adding one day must always work, given the operating date context.");
+                // sleep(time::Duration::from_secs(13)).await;
+            } else {
+                info!(
+                    "Failed with request {} and got response {}",
+                    request, response
+                );
+                api_key.set_status(Status::Exhausted);
+            }
+
+            general_api_key = KeyManager::exchange_apikey_or_wait_if_non_ready(
+                self.key_manager.clone(),
+                self.params.wait_for_key,
+                api_key,
+                PLATFORM,
+            )
+            .await;
+        }
+        if let Some(api_key) = general_api_key {
+            let mut d = self.key_manager.lock().expect("msg");
+            d.add_key_by_platform(api_key);
+        }
+        Ok(output)
     }
-    if let Some(api_key) = general_api_key {
-        let mut d = key_manager.lock().expect("msg");
-        d.add_key_by_platform(api_key);
-    }
-    Ok(())
+}
+
+async fn latest_business_date(connection_pool: &PgPool) -> Result<Option<NaiveDate>, sqlx::Error> {
+    Ok(sqlx::query!(
+        "select max(business_date) as business_date
+        from polygon_grouped_daily"
+    )
+    .fetch_one(connection_pool)
+    .await?
+    .business_date)
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
-fn get_start_date(result: Option<NaiveDate>) -> NaiveDate {
+fn get_start_date(result: Option<NaiveDate>, history_months: u32) -> NaiveDate {
     if let Some(date) = result {
         return date
             .checked_add_days(Days::new(1))
             .expect("Adding one day must always work, given the operating date context.");
     }
-    earliest_date()
+    earliest_date(history_months)
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
@@ -263,11 +377,11 @@ fn transpose_polygon_grouped_daily(
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
-fn earliest_date() -> NaiveDate {
+fn earliest_date(history_months: u32) -> NaiveDate {
     Utc::now()
         .date_naive()
-        .checked_sub_months(Months::new(24))
-        .expect("Minus 2 years should never fail")
+        .checked_sub_months(Months::new(history_months))
+        .expect("Going back the configured months should never fail")
         .checked_add_days(Days::new(1))
         .expect("Adding 1 day should always work")
 }
@@ -288,4 +402,46 @@ fn create_polygon_grouped_daily_request<'a>(
 }
 
 #[cfg(test)]
-mod test {}
+mod test {
+    use crate::actions::collect::polygon_grouped_daily::{
+        transpose_polygon_grouped_daily, DailyValue, PolygonGroupedDailyOutput,
+    };
+    use chrono::NaiveDate;
+
+    #[test]
+    fn output_contains_dates_and_symbols_of_collected_rows() {
+        let daily_value = |symbol: &str| DailyValue {
+            symbol: symbol.to_string(),
+            close: 1.0,
+            high: 1.0,
+            low: 1.0,
+            order_amount: None,
+            open: 1.0,
+            unix_timestamp: 0,
+            stock_traded: 1.0,
+            volume_weighted_average_price: None,
+        };
+        let first_date = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        let second_date = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        let mut output = PolygonGroupedDailyOutput::default();
+        assert!(output.is_empty());
+
+        output.add(&transpose_polygon_grouped_daily(
+            vec![daily_value("AAPL"), daily_value("MSFT")],
+            first_date,
+        ));
+        output.add(&transpose_polygon_grouped_daily(
+            vec![daily_value("AAPL")],
+            second_date,
+        ));
+
+        assert_eq!(
+            output.business_dates.into_iter().collect::<Vec<_>>(),
+            vec![first_date, second_date]
+        );
+        assert_eq!(
+            output.symbols.into_iter().collect::<Vec<_>>(),
+            vec!["AAPL".to_string(), "MSFT".to_string()]
+        );
+    }
+}
@@ -22,7 +22,10 @@ use serde::{Deserialize, Serialize};
 
 use sqlx::PgPool;
 
+use crate::dag_schedule::context::TaskContext;
+use crate::dag_schedule::fan_out::FanOut;
 use crate::dag_schedule::task::{Runnable, StatsMap, TaskError};
+use tokio_util::sync::CancellationToken;
 
 const URL: &str = "https://api.polygon.io/v1/open-close/";
 const ERROR_MSG_VALUE_EXISTS: &str = "Value exists or error must have been caught before";
@@ -54,6 +57,26 @@ pub struct PolygonOpenCloseCollector {
     pool: PgPool,
     client: Client,
     key_manager: Arc<Mutex<KeyManager>>,
+    params: PolygonOpenCloseParams,
+}
+
+/// Parameters of the collector, configured as `params` of the task
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PolygonOpenCloseParams {
+    /// waits for a ready api key instead of stopping once all keys are exhausted
+    pub wait_for_key: bool,
+    /// a symbol without new data for the given days is marked as not available
+    pub idle_symbol_timeout_days: i64,
+}
+
+impl Default for PolygonOpenCloseParams {
+    fn default() -> Self {
+        PolygonOpenCloseParams {
+            wait_for_key: WAIT_FOR_KEY,
+            idle_symbol_timeout_days: IDLE_SYMBOL_TIMEOUT,
+        }
+    }
 }
 
 impl PolygonOpenCloseCollector {
@@ -63,8 +86,14 @@ impl PolygonOpenCloseCollector {
             pool,
             client,
             key_manager,
+            params: PolygonOpenCloseParams::default(),
         }
     }
+
+    pub fn with_params(mut self, params: PolygonOpenCloseParams) -> Self {
+        self.params = params;
+        self
+    }
 }
 
 impl Display for PolygonOpenCloseCollector {
@@ -76,18 +105,74 @@ impl Display for PolygonOpenCloseCollector {
 #[async_trait]
 impl Runnable for PolygonOpenCloseCollector {
     #[tracing::instrument(level = "debug", skip_all)]
-    async fn run(&self) -> Result<Option<StatsMap>, TaskError> {
+    async fn run(&self, context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
         load_and_store_missing_data(
             self.pool.clone(),
             self.client.clone(),
             self.key_manager.clone(),
+            &context.cancel,
+            &self.params,
         )
         .map_err(TaskError::UnexpectedError)
         .await?;
+        if context.cancel.is_cancelled() {
+            return Err(TaskError::Cancelled);
+        }
         Ok(None)
     }
 }
 
+/// Collects every symbol as item of its own, see [`FanOutRunnable`](crate::dag_schedule::fan_out::FanOutRunnable)
+#[async_trait]
+impl FanOut for PolygonOpenCloseCollector {
+    async fn items(&self, _context: &TaskContext) -> Result<Vec<String>, TaskError> {
+        let mut issue_symbols = vec![];
+        let mut issue_symbol_candidate = get_next_issue_symbol_candidate(&self.pool, None).await;
+        while let Some(issue_symbol) = issue_symbol_candidate {
+            issue_symbol_candidate =
+                get_next_issue_symbol_candidate(&self.pool, Some(issue_symbol.clone())).await;
+            issue_symbols.push(issue_symbol);
+        }
+        Ok(issue_symbols)
+    }
+
+    async fn run_item(&self, item: &str, _context: &TaskContext) -> Result<(), TaskError> {
+        let mut api_key = KeyManager::get_new_apikey_or_wait(
+            self.key_manager.clone(),
+            self.params.wait_for_key,
+            PLATFORM,
+        )
+        .await;
+        if api_key.is_none() {
+            return Err(TaskError::UnexpectedError(anyhow::anyhow!(
+                "No api key ready to collect {}",
+                item
+            )));
+        }
+        let result = load_and_store_symbol(
+            &self.pool,
+            &self.client,
+            &self.key_manager,
+            URL,
+            item,
+            &mut api_key,
+            &self.params,
+        )
+        .await;
+        if let Some(api_key) = api_key {
+            let mut d = self.key_manager.lock().expect("msg");
+            d.add_key_by_platform(api_key);
+        }
+        if !result.map_err(TaskError::UnexpectedError)? {
+            return Err(TaskError::UnexpectedError(anyhow::anyhow!(
+                "No api key ready to collect all days of {}",
+                item
+            )));
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PolygonOpenClose {
@@ -124,8 +209,11 @@ pub async fn load_and_store_missing_data(
     connection_pool: PgPool,
     client: Client,
     key_manager: Arc<Mutex<KeyManager>>,
+    cancel: &CancellationToken,
+    params: &PolygonOpenCloseParams,
 ) -> Result<(), anyhow::Error> {
-    load_and_store_missing_data_given_url(connection_pool, client, key_manager, URL).await
+    load_and_store_missing_data_given_url(connection_pool, client, key_manager, cancel, URL, params)
+        .await
 }
 
 #[tracing::instrument(level = "debug", skip_all)]
@@ -133,77 +221,32 @@ async fn load_and_store_missing_data_given_url(
     connection_pool: sqlx::Pool<sqlx::Postgres>,
     client: Client,
     key_manager: Arc<Mutex<KeyManager>>,
+    cancel: &CancellationToken,
     url: &str,
+    params: &PolygonOpenCloseParams,
 ) -> Result<(), anyhow::Error> {
     info!("Starting to load Polygon open close.");
 
     let mut issue_symbol_candidate: Option<String> =
         get_next_issue_symbol_candidate(&connection_pool, None).await;
     let mut general_api_key =
-        KeyManager::get_new_apikey_or_wait(key_manager.clone(), WAIT_FOR_KEY, PLATFORM).await;
-    while let (Some(issue_symbol), true) = (issue_symbol_candidate, general_api_key.is_some()) {
-        let mut current_check_date = earliest_date(&issue_symbol, &connection_pool).await;
-
-        while let Some(mut api_key) =
-            general_api_key.take_if(|_| current_check_date.lt(&Utc::now().date_naive()))
-        {
-            let mut request = create_polygon_open_close_request(
-                url,
-                &issue_symbol,
-                current_check_date,
-                &mut api_key,
-            );
-            debug!("Polygon open close request: {}", request);
-            let response = client
-                .get(request.expose_secret())
-                .send()
-                .await?
-                .text()
-                .await?;
-            let open_close = vec![parse_response::<PolygonOpenClose>(&response)?];
-            if open_close[0].status.eq("OK") {
-                let open_close_data = transpose_polygon_open_close(&open_close);
-                sqlx::query!(r#"INSERT INTO polygon_open_close
-                (after_hours, "close", business_date, high, low, "open", pre_market, symbol, volume)
-                Select * from UNNEST ($1::float[], $2::float[], $3::date[], $4::float[], $5::float[], $6::float[], $7::float[], $8::text[], $9::float[]) on conflict do nothing"#,
-                &open_close_data.after_hours[..] as _,
-                &open_close_data.close[..] as _,
-                &open_close_data.business_date[..],
-                &open_close_data.high[..] as _,
-                &open_close_data.low[..] as _,
-                &open_close_data.open[..] as _,
-                &open_close_data.pre_market[..] as _,
-                &open_close_data.symbol[..],
-                &open_close_data.volume[..] as _,)
-                .execute(&connection_pool)
-                .await?;
-            }
-            if open_close[0].status.ne("ERROR") {
-                current_check_date = current_check_date
-                    .checked_add_days(Days::new(1))
-                    .expect("Adding one day must always work, given the operating date context.");
-            } else {
-                info!(
-                    "Failed with request {} and got response {}",
-                    request, response
-                );
-                api_key.set_status(Status::Exhausted);
-            }
-            general_api_key = KeyManager::exchange_apikey_or_wait_if_non_ready(
-                key_manager.clone(),
-                WAIT_FOR_KEY,
-                api_key,
-                PLATFORM,
-            )
+        KeyManager::get_new_apikey_or_wait(key_manager.clone(), params.wait_for_key, PLATFORM)
             .await;
+    while let (Some(issue_symbol), true) = (issue_symbol_candidate, general_api_key.is_some()) {
+        if cancel.is_cancelled() {
+            info!("Cancelled, stop before symbol {}", issue_symbol);
+            break;
         }
-        // Mark symbols without new data as not available
-        if Utc::now().date_naive() - earliest_date(&issue_symbol, &connection_pool).await
-            > TimeDelta::days(IDLE_SYMBOL_TIMEOUT)
-        {
-            add_missing_issue_symbol(&issue_symbol, &connection_pool).await?;
-        }
-
+        load_and_store_symbol(
+            &connection_pool,
+            &client,
+            &key_manager,
+            url,
+            &issue_symbol,
+            &mut general_api_key,
+            params,
+        )
+        .await?;
         issue_symbol_candidate =
             get_next_issue_symbol_candidate(&connection_pool, Some(issue_symbol)).await;
     }
@@ -215,6 +258,78 @@ async fn load_and_store_missing_data_given_url(
     Ok(())
 }
 
+/// Collects the missing days of the symbol while an api key is ready, the key is exchanged once it is
+/// exhausted and none is left if no further key is ready. Returns whether the symbol is up to date.
+async fn load_and_store_symbol(
+    connection_pool: &PgPool,
+    client: &Client,
+    key_manager: &Arc<Mutex<KeyManager>>,
+    url: &str,
+    issue_symbol: &str,
+    general_api_key: &mut Option<Box<dyn ApiKey>>,
+    params: &PolygonOpenCloseParams,
+) -> Result<bool, anyhow::Error> {
+    let mut current_check_date = earliest_date(issue_symbol, connection_pool).await;
+
+    while let Some(mut api_key) =
+        general_api_key.take_if(|_| current_check_date.lt(&Utc::now().date_naive()))
+    {
+        let mut request =
+            create_polygon_open_close_request(url, issue_symbol, current_check_date, &mut api_key);
+        debug!("Polygon open close request: {}", request);
+        let response = client
+            .get(request.expose_secret())
+            .send()
+            .await?
+            .text()
+            .await?;
+        let open_close = vec![parse_response::<PolygonOpenClose>(&response)?];
+        if open_close[0].status.eq("OK") {
+            let open_close_data = transpose_polygon_open_close(&open_close);
+            sqlx::query!(r#"INSERT INTO polygon_open_close
+                (after_hours, "close", business_date, high, low, "open", pre_market, symbol, volume)
+                Select * from UNNEST ($1::float[], $2::float[], $3::date[], $4::float[], $5::float[], $6::float[], $7::float[], $8::text[], $9::float[]) on conflict do nothing"#,
+            &open_close_data.after_hours[..] as _,
+            &open_close_data.close[..] as _,
+            &open_close_data.business_date[..],
+            &open_close_data.high[..] as _,
+            &open_close_data.low[..] as _,
+            &open_close_data.open[..] as _,
+            &open_close_data.pre_market[..] as _,
+            &open_close_data.symbol[..],
+            &open_close_data.volume[..] as _,)
+            .execute(connection_pool)
+            .await?;
+        }
+        if open_close[0].status.ne("ERROR") {
+            current_check_date = current_check_date
+                .checked_add_days(Days::new(1))
+                .expect("Adding one day must always work, given the operating date context.");
+        } else {
+            info!(
+                "Failed with request {} and got response {}",
+                request, response
+            );
+            api_key.set_status(Status::Exhausted);
+        }
+        *general_api_key = KeyManager::exchange_apikey_or_wait_if_non_ready(
+            key_manager.clone(),
+            params.wait_for_key,
+            api_key,
+            PLATFORM,
+        )
+        .await;
+    }
+    // Mark symbols without new data as not available
+    if Utc::now().date_naive() - earliest_date(issue_symbol, connection_pool).await
+        > TimeDelta::days(params.idle_symbol_timeout_days)
+    {
+        add_missing_issue_symbol(issue_symbol, connection_pool).await?;
+    }
+
+    Ok(current_check_date >= Utc::now().date_naive())
+}
+
 async fn add_missing_issue_symbol(
     issue_symbol: &str,
     connection_pool: &PgPool,
@@ -313,7 +428,7 @@ fn transpose_polygon_open_close(instruments: &Vec<PolygonOpenClose>) -> Transpos
 
 #[tracing::instrument(level = "debug", skip_all)]
 async fn earliest_date(
-    issue_symbol: &String,
+    issue_symbol: &str,
     connection_pool: &sqlx::Pool<sqlx::Postgres>,
 ) -> NaiveDate {
     let a = sqlx::query!(
@@ -17,6 +17,7 @@ use zip::ZipArchive;
 
 use tokio_stream::StreamExt;
 
+use crate::dag_schedule::context::TaskContext;
 use crate::dag_schedule::task::TaskError::UnexpectedError;
 use crate::dag_schedule::task::{Runnable, StatsMap};
 use crate::utils;
@@ -88,7 +89,10 @@ impl Display for SecCompanyCollector {
 #[async_trait]
 impl Runnable for SecCompanyCollector {
     #[tracing::instrument(name = "Run SecCompanyCollector", skip(self))]
-    async fn run(&self) -> Result<Option<StatsMap>, crate::dag_schedule::task::TaskError> {
+    async fn run(
+        &self,
+        _context: TaskContext,
+    ) -> Result<Option<StatsMap>, crate::dag_schedule::task::TaskError> {
         load_and_store_missing_data(self.pool.clone(), self.client.clone())
             .await
             .map_err(UnexpectedError)?;
@@ -1,5 +1,6 @@
 pub mod action;
 pub mod collect;
 pub mod collector_sources;
+pub mod sensor;
 pub mod sp500_fields;
 pub mod stage;
@@ -0,0 +1,300 @@
+use crate::dag_schedule::context::TaskContext;
+use crate::dag_schedule::task::{error_chain, Runnable, StatsMap, TaskError};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tracing::{debug, warn};
+
+/// External condition a sensor waits for
+#[derive(Clone, Debug)]
+pub enum SensorCondition {
+    /// the query returns a single boolean, e.g. `select exists(select 1 from ... where ...)`
+    SqlPredicate { pool: PgPool, query: String },
+    /// the file exists, is not empty and, if a max age is given, was modified within it
+    File {
+        path: PathBuf,
+        max_age: Option<Duration>,
+    },
+    /// a HEAD request to the url returns a success status
+    HttpHead { client: Client, url: String },
+}
+
+impl SensorCondition {
+    pub async fn is_met(&self) -> Result<bool, TaskError> {
+        match self {
+            SensorCondition::SqlPredicate { pool, query } => sqlx::query_scalar::<_, bool>(query)
+                .fetch_one(pool)
+                .await
+                .map_err(TaskError::DatabaseError),
+            SensorCondition::File { path, max_age } => Ok(is_file_present(path, *max_age)),
+            SensorCondition::HttpHead { client, url } => client
+                .head(url)
+                .send()
+                .await
+                .map(|response| response.status().is_success())
+                .map_err(TaskError::ClientRequestError),
+        }
+    }
+}
+
+/// Same check as the download of the sec companies uses: empty files are considered missing
+fn is_file_present(path: &Path, max_age: Option<Duration>) -> bool {
+    match std::fs::metadata(path) {
+        Ok(metadata) if metadata.len() > 0 => match (max_age, metadata.modified()) {
+            (None, _) => true,
+            (Some(max_age), Ok(modified)) => {
+                let modification_date: DateTime<Utc> = modified.into();
+                (Utc::now() - modification_date)
+                    .to_std()
+                    .map(|age| age <= max_age)
+                    .unwrap_or(true)
+            }
+            (Some(_), Err(_)) => false,
+        },
+        _ => false,
+    }
+}
+
+/// Pokes its condition until it holds. Used as node of the DAG to gate downstream tasks on
+/// external data, e.g. rows being present in a table. Fails with [`TaskError::Timeout`] if the
+/// condition does not hold within the timeout. Errors while checking the condition, e.g. a refused
+/// connection, count as not met yet.
+#[derive(Clone, Debug)]
+pub struct Sensor {
+    condition: SensorCondition,
+    poke_interval: Duration,
+    timeout: Option<Duration>,
+}
+
+impl Sensor {
+    pub fn new(condition: SensorCondition, poke_interval: Duration) -> Self {
+        Sensor {
+            condition,
+            poke_interval,
+            timeout: None,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl Display for Sensor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Sensor struct.")
+    }
+}
+
+#[async_trait]
+impl Runnable for Sensor {
+    #[tracing::instrument(name = "Run Sensor", skip(self, context))]
+    async fn run(&self, context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
+        let started = Instant::now();
+        let mut pokes: usize = 0;
+        loop {
+            if context.cancel.is_cancelled() {
+                return Err(TaskError::Cancelled);
+            }
+            pokes += 1;
+            match self.condition.is_met().await {
+                Ok(true) => {
+                    let stats: StatsMap = Arc::new(Mutex::new(HashMap::new()));
+                    stats
+                        .lock()
+                        .await
+                        .insert("pokes".to_string(), Arc::new(pokes));
+                    return Ok(Some(stats));
+                }
+                Ok(false) => {}
+                Err(e) => warn!(
+                    "condition of sensor could not be checked: {}",
+                    error_chain(&e)
+                ),
+            }
+            if let Some(timeout) = self.timeout {
+                if started.elapsed() + self.poke_interval > timeout {
+                    return Err(TaskError::Timeout(timeout));
+                }
+            }
+            debug!(
+                "condition of sensor not met after {} pokes, next poke in {:?}",
+                pokes, self.poke_interval
+            );
+            tokio::select! {
+                _ = context.cancel.cancelled() => return Err(TaskError::Cancelled),
+                _ = tokio::time::sleep(self.poke_interval) => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::actions::sensor::{Sensor, SensorCondition};
+    use crate::dag_schedule::context::TaskContext;
+    use crate::dag_schedule::task::{custom_stats_to_json, Runnable, TaskError};
+    use crate::utils::test_helpers::get_test_client;
+    use chrono::{Days, Utc};
+    use filetime::FileTime;
+    use httpmock::Method::HEAD;
+    use httpmock::MockServer;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    #[tokio::test]
+    async fn file_condition_requires_non_empty_and_recent_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("grouped_daily.csv");
+        let condition = |max_age| SensorCondition::File {
+            path: path.clone(),
+            max_age,
+        };
+        let one_hour = Some(Duration::from_secs(3600));
+
+        assert!(!condition(None).is_met().await.unwrap());
+        std::fs::File::create(&path).unwrap();
+        assert!(!condition(None).is_met().await.unwrap());
+        std::fs::write(&path, b"AAPL").unwrap();
+        assert!(condition(one_hour).is_met().await.unwrap());
+
+        let time = Utc::now()
+            .checked_sub_days(Days::new(2))
+            .unwrap()
+            .timestamp();
+        filetime::set_file_mtime(&path, FileTime::from_unix_time(time, 0)).unwrap();
+        assert!(!condition(one_hour).is_met().await.unwrap());
+        assert!(condition(None).is_met().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn sensor_pokes_until_condition_is_met() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("grouped_daily.csv");
+        let sensor = Sensor::new(
+            SensorCondition::File {
+                path: path.clone(),
+                max_age: None,
+            },
+            Duration::from_millis(20),
+        )
+        .with_timeout(Duration::from_secs(3));
+
+        let handle = tokio::spawn(async move { sensor.run(TaskContext::default()).await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        std::fs::write(&path, b"AAPL").unwrap();
+
+        let stats = handle.await.unwrap().unwrap().unwrap();
+        let pokes = custom_stats_to_json(&stats).await["pokes"]
+            .as_u64()
+            .unwrap();
+        assert!(pokes > 1);
+    }
+
+    #[tokio::test]
+    async fn sensor_pokes_again_after_failed_check() {
+        // nothing listens on the port until the server starts, so the first pokes are refused
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let sensor = Sensor::new(
+            SensorCondition::HttpHead {
+                client: get_test_client(),
+                url: format!("http://{}/available", address),
+            },
+            Duration::from_millis(20),
+        )
+        .with_timeout(Duration::from_secs(3));
+
+        let handle = tokio::spawn(async move { sensor.run(TaskContext::default()).await });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let listener = TcpListener::bind(address).unwrap();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            assert!(stream.read(&mut request).unwrap() > 0);
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\nconnection: close\r\n\r\n")
+                .unwrap();
+        });
+
+        let stats = handle.await.unwrap().unwrap().unwrap();
+        let pokes = custom_stats_to_json(&stats).await["pokes"]
+            .as_u64()
+            .unwrap();
+        assert!(pokes > 1);
+    }
+
+    #[tokio::test]
+    async fn sensor_times_out_if_condition_is_not_met() {
+        let dir = TempDir::new().unwrap();
+        let sensor = Sensor::new(
+            SensorCondition::File {
+                path: dir.path().join("missing.csv"),
+                max_age: None,
+            },
+            Duration::from_millis(20),
+        )
+        .with_timeout(Duration::from_millis(100));
+
+        let result = sensor.run(TaskContext::default()).await;
+
+        assert!(matches!(result, Err(TaskError::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn sensor_stops_when_cancelled() {
+        let dir = TempDir::new().unwrap();
+        let sensor = Sensor::new(
+            SensorCondition::File {
+                path: dir.path().join("missing.csv"),
+                max_age: None,
+            },
+            Duration::from_secs(60),
+        );
+        let context = TaskContext::default();
+        let cancel = context.cancel.clone();
+
+        let handle = tokio::spawn(async move { sensor.run(context).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        cancel.cancel();
+
+        let result = tokio::time::timeout(Duration::from_secs(3), handle)
+            .await
+            .expect("sensor must stop after it was cancelled")
+            .unwrap();
+        assert!(matches!(result, Err(TaskError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn http_head_condition_checks_status() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(HEAD).path("/available");
+            then.status(200);
+        });
+        server.mock(|when, then| {
+            when.method(HEAD).path("/missing");
+            then.status(404);
+        });
+        let condition = |path: &str| SensorCondition::HttpHead {
+            client: get_test_client(),
+            url: server.url(path),
+        };
+
+        assert!(condition("/available").is_met().await.unwrap());
+        assert!(!condition("/missing").is_met().await.unwrap());
+    }
+}
@@ -4,6 +4,7 @@ use futures_util::TryFutureExt;
 use sqlx::PgPool;
 use std::fmt::Display;
 
+use crate::dag_schedule::context::TaskContext;
 use crate::dag_schedule::task::TaskError::UnexpectedError;
 use crate::dag_schedule::task::{Runnable, StatsMap, TaskError};
 
@@ -27,7 +28,7 @@ impl Display for FinancialmodelingprepCompanyProfileStager {
 #[async_trait]
 impl Runnable for FinancialmodelingprepCompanyProfileStager {
     #[tracing::instrument(name = "Run financialmodelingprep Company Profile Stager", skip(self))]
-    async fn run(&self) -> Result<Option<StatsMap>, TaskError> {
+    async fn run(&self, _context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
         stage_data(self.pool.clone())
             .map_err(UnexpectedError)
             .await?;
@@ -16,6 +16,7 @@ use std::pin::Pin;
 use tracing::error;
 use tracing::{debug, info};
 
+use crate::dag_schedule::context::TaskContext;
 use crate::dag_schedule::task::TaskError::UnexpectedError;
 use crate::dag_schedule::task::{Runnable, StatsMap, TaskError};
 
@@ -255,7 +256,7 @@ impl Runnable for FinancialmodelingprepMarketCapitalizationStager {
         name = "Run financialmodelingprep market capitalization stager",
         skip(self)
     )]
-    async fn run(&self) -> Result<Option<StatsMap>, TaskError> {
+    async fn run(&self, _context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
         stage_data(&self.pool).map_err(UnexpectedError).await?;
         Ok(None)
     }
@@ -6,6 +6,7 @@ use std::fmt::Display;
 
 use tracing::info;
 
+use crate::dag_schedule::context::TaskContext;
 use crate::dag_schedule::task::TaskError::UnexpectedError;
 use crate::dag_schedule::task::{Runnable, StatsMap, TaskError};
 
@@ -69,7 +70,7 @@ impl Display for NyseInstrumentStager {
 #[async_trait]
 impl Runnable for NyseInstrumentStager {
     #[tracing::instrument(name = "Run NyseInstrumentStager", skip(self))]
-    async fn run(&self) -> Result<Option<StatsMap>, TaskError> {
+    async fn run(&self, _context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
         stage_data(self.pool.clone())
             .await
             .map_err(UnexpectedError)?;
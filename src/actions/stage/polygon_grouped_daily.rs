@@ -14,6 +14,8 @@ use std::fmt::Display;
 use std::pin::Pin;
 use tracing::{debug, error, info};
 
+use crate::actions::collect::polygon_grouped_daily::PolygonGroupedDailyOutput;
+use crate::dag_schedule::context::TaskContext;
 use crate::dag_schedule::task::TaskError::UnexpectedError;
 use crate::dag_schedule::task::{Runnable, StatsMap, TaskError};
 
@@ -254,28 +256,46 @@ impl Display for PolygonGroupedDailyStager {
 #[async_trait]
 impl Runnable for PolygonGroupedDailyStager {
     #[tracing::instrument(name = "Run Polygon Grouped Daily Stager", skip(self))]
-    async fn run(&self) -> Result<Option<StatsMap>, TaskError> {
+    async fn run(&self, context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
         info!("Start polygon grouped daily stager.");
-        stage_data(&self.pool).map_err(UnexpectedError).await?;
+        let outputs = context.upstream_outputs::<PolygonGroupedDailyOutput>();
+        let collected = (!outputs.is_empty()).then(|| {
+            let mut collected = PolygonGroupedDailyOutput::default();
+            outputs.iter().for_each(|output| collected.merge(output));
+            collected
+        });
+        stage_data(&self.pool, collected.as_ref())
+            .map_err(UnexpectedError)
+            .await?;
         Ok(None)
     }
 }
 
+/// Stages the unstaged grouped daily data. If the output of the collector is given, only the rows
+/// of its business dates and symbols are staged, otherwise all unstaged rows.
 #[tracing::instrument(level = "debug", skip_all)]
-pub async fn stage_data(connection_pool: &PgPool) -> Result<(), anyhow::Error> {
-    // Mark already existing data as staged (comes maybe from other sources)
-    debug!("Mark already existing data as staged");
-    mark_staged(connection_pool).await?;
-
+pub async fn stage_data(
+    connection_pool: &PgPool,
+    collected: Option<&PolygonGroupedDailyOutput>,
+) -> Result<(), anyhow::Error> {
     let partitions: HashSet<u32> = get_existing_partitions(connection_pool).await?;
     debug!("Partitions found: {:?}", partitions);
 
-    let input_data = get_stageable_data(connection_pool);
+    let input_data = match collected {
+        Some(collected) => {
+            debug!(
+                "Stage {} collected business dates",
+                collected.business_dates.len()
+            );
+            get_collected_stageable_data(connection_pool, collected)
+        }
+        None => get_stageable_data(connection_pool),
+    };
     stage_data_stream(connection_pool, input_data, partitions).await?;
-    debug!("Mark new staged data");
-    mark_staged(connection_pool).await?;
 
     stage_updatable_data(connection_pool).await?;
+    // Mark the staged data and data which already existed (comes maybe from other sources)
+    debug!("Mark staged data");
     mark_staged(connection_pool).await?;
     Ok(())
 }
@@ -307,7 +327,7 @@ async fn stage_data_stream<'a>(
         >,
     >,
     mut existing_partitions: HashSet<u32>,
-) -> Result<(), anyhow::Error> {
+) -> Result<HashSet<u32>, anyhow::Error> {
     debug!("Start staging data.");
     while let Some(batch_grouped_daily) = input_data.as_mut().chunks(10000).next().await {
         let batch_market_data: Vec<MarketData> = batch_grouped_daily
@@ -334,7 +354,7 @@ async fn stage_data_stream<'a>(
         add_data(connection_pool, market_data_transposed).await?;
     }
     debug!("End staging data.");
-    Ok(())
+    Ok(existing_partitions)
 }
 
 async fn create_partitions(
@@ -383,6 +403,8 @@ async fn create_partition(
         .await
 }
 
+/// Inserts the market data, the values of already existing rows are replaced, e.g. after a backfill
+/// corrected the collected data.
 async fn add_data(
     connection_pool: &PgPool,
     data: MarketDataTransposed,
@@ -390,7 +412,13 @@ async fn add_data(
     sqlx::query!(
         r##"INSERT INTO market_data
         (symbol, business_date, stock_price, "open", "close", stock_traded, order_amount, after_hours, pre_market, market_capitalization, year_month)
-        Select * from UNNEST($1::text[], $2::date[], $3::float[], $4::float[], $5::float[], $6::float[], $7::float[], $8::float[], $9::float[], $10::float[], $11::int[]) on conflict do nothing;"##,
+        Select * from UNNEST($1::text[], $2::date[], $3::float[], $4::float[], $5::float[], $6::float[], $7::float[], $8::float[], $9::float[], $10::float[], $11::int[])
+        on conflict (symbol, business_date, year_month) do update set
+            stock_price = excluded.stock_price,
+            "open" = coalesce(excluded."open", market_data."open"),
+            "close" = coalesce(excluded."close", market_data."close"),
+            stock_traded = coalesce(excluded.stock_traded, market_data.stock_traded),
+            order_amount = coalesce(excluded.order_amount, market_data.order_amount);"##,
         &data.symbol,
         &data.business_date,
         data.stock_price as _,
@@ -406,6 +434,7 @@ async fn add_data(
     Ok(())
 }
 
+#[derive(sqlx::FromRow)]
 struct PolygonGroupedDailyTable {
     symbol: String,
     close: BigDecimal,
@@ -431,6 +460,32 @@ fn get_stageable_data<'a>(
     polygon_grouped_daily_stream
 }
 
+/// unstaged rows of the business dates and symbols written by the collector
+#[tracing::instrument(level = "debug", skip_all)]
+fn get_collected_stageable_data<'a>(
+    connection_pool: &'a PgPool,
+    collected: &PolygonGroupedDailyOutput,
+) -> Pin<
+    Box<
+        dyn futures_util::Stream<Item = Result<PolygonGroupedDailyTable, sqlx::Error>>
+            + 'a
+            + std::marker::Send,
+    >,
+> {
+    let business_dates: Vec<NaiveDate> = collected.business_dates.iter().copied().collect();
+    let symbols: Vec<String> = collected.symbols.iter().cloned().collect();
+    sqlx::query_as::<_, PolygonGroupedDailyTable>(
+        r##"select pgd.symbol, pgd."open", pgd."close", pgd.business_date, pgd.order_amount, pgd.stock_traded
+        from polygon_grouped_daily pgd
+        where pgd.is_staged = false
+        and pgd.business_date = any($1)
+        and pgd.symbol = any($2)"##,
+    )
+    .bind(business_dates)
+    .bind(symbols)
+    .fetch(connection_pool)
+}
+
 #[tracing::instrument(level = "debug", skip_all)]
 async fn get_existing_partitions(connection_pool: &PgPool) -> Result<HashSet<u32>, anyhow::Error> {
     let oid = get_table_oid(connection_pool).await?;
@@ -518,13 +573,16 @@ async fn mark_staged(connection_pool: &PgPool) -> Result<(), anyhow::Error> {
 
 #[cfg(test)]
 mod test {
-    use chrono::Utc;
+    use chrono::{NaiveDate, Utc};
     use num_bigint::{BigInt, Sign::Plus, ToBigInt};
     use sqlx::{types::BigDecimal, Pool, Postgres};
+    use std::collections::BTreeSet;
 
+    use crate::actions::collect::polygon_grouped_daily::PolygonGroupedDailyOutput;
     use crate::actions::stage::polygon_grouped_daily::{
         add_data, create_partition, get_existing_partition_ranges_for_oid, get_table_oid,
-        mark_staged, stage_updatable_data, MarketData, MarketDataBuilder, MarketDataTransposed,
+        mark_staged, stage_data, stage_updatable_data, MarketData, MarketDataBuilder,
+        MarketDataTransposed,
     };
 
     #[sqlx::test()]
@@ -600,6 +658,88 @@ mod test {
         Ok(())
     }
 
+    #[sqlx::test(fixtures(
+        "../../../tests/resources/collectors/staging/polygon_grouped_daily_staging/polygon_grouped_daily_data_source.sql"
+    ))]
+    async fn given_collected_data_when_staging_then_only_collected_rows_staged(
+        pool: Pool<Postgres>,
+    ) -> Result<(), anyhow::Error> {
+        // the collector of this run only wrote the last day, the other days are left over
+        let collected = PolygonGroupedDailyOutput {
+            business_dates: BTreeSet::from([NaiveDate::from_ymd_opt(2022, 3, 9).unwrap()]),
+            symbols: BTreeSet::from(["A".to_string()]),
+        };
+
+        stage_data(&pool, Some(&collected)).await?;
+
+        let staged: i64 = sqlx::query_scalar("select count(*) from market_data where symbol = 'A'")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(staged, 1);
+        let unstaged: i64 = sqlx::query_scalar(
+            "select count(*) from polygon_grouped_daily where is_staged = false",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(unstaged, 3);
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(
+        "../../../tests/resources/collectors/staging/polygon_grouped_daily_staging/polygon_grouped_daily_data_source.sql"
+    ))]
+    async fn given_no_collected_data_when_staging_then_all_unstaged_rows_staged(
+        pool: Pool<Postgres>,
+    ) -> Result<(), anyhow::Error> {
+        stage_data(&pool, None).await?;
+
+        let staged: i64 = sqlx::query_scalar("select count(*) from market_data where symbol = 'A'")
+            .fetch_one(&pool)
+            .await?;
+        assert_eq!(staged, 4);
+        let unstaged: i64 = sqlx::query_scalar(
+            "select count(*) from polygon_grouped_daily where is_staged = false",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(unstaged, 0);
+        Ok(())
+    }
+
+    #[sqlx::test(fixtures(
+        "../../../tests/resources/collectors/staging/polygon_grouped_daily_staging/polygon_grouped_daily_data_source.sql"
+    ))]
+    async fn given_corrected_raw_row_when_staging_then_market_data_corrected(
+        pool: Pool<Postgres>,
+    ) -> Result<(), anyhow::Error> {
+        stage_data(&pool, None).await?;
+        // a backfill replaced the row with corrected values
+        sqlx::query(
+            r#"update polygon_grouped_daily set "close" = 140.5, is_staged = false
+            where symbol = 'A' and business_date = '2022-03-09'"#,
+        )
+        .execute(&pool)
+        .await?;
+
+        stage_data(&pool, None).await?;
+
+        let (close, stock_price): (f64, f64) = sqlx::query_as(
+            r#"select "close", stock_price from market_data
+            where symbol = 'A' and business_date = '2022-03-09'"#,
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(close, 140.5);
+        assert_eq!(stock_price, 140.5);
+        let unstaged: i64 = sqlx::query_scalar(
+            "select count(*) from polygon_grouped_daily where is_staged = false",
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert_eq!(unstaged, 0);
+        Ok(())
+    }
+
     #[test]
     fn get_missing_zero_values_given_none_then_none() {
         assert_eq!(
@@ -5,6 +5,7 @@ use sqlx::PgPool;
 use std::fmt::Display;
 use tracing::info;
 
+use crate::dag_schedule::context::TaskContext;
 use crate::dag_schedule::task::TaskError::UnexpectedError;
 use crate::dag_schedule::task::{Runnable, StatsMap, TaskError};
 
@@ -28,7 +29,7 @@ impl Display for SecCompanyStager {
 #[async_trait]
 impl Runnable for SecCompanyStager {
     #[tracing::instrument(name = "Run SecCompanyStager", skip(self))]
-    async fn run(&self) -> Result<Option<StatsMap>, TaskError> {
+    async fn run(&self, _context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
         stage_data(self.pool.clone())
             .map_err(UnexpectedError)
             .await?;
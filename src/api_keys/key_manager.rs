@@ -8,6 +8,7 @@ use config::Map;
 use priority_queue::PriorityQueue;
 
 use super::api_key::{ApiKey, ApiKeyPlatform, Status};
+use crate::utils::notification::{Notification, Notifier};
 
 type KeyOrTimeoutResult = Result<(Option<Box<dyn ApiKey>>, Option<DateTime<Utc>>), KeyErrors>;
 type KeyStore = Map<ApiKeyPlatform, PriorityQueue<Box<dyn ApiKey>, Reverse<DateTime<Utc>>>>;
@@ -15,11 +16,21 @@ type KeyStore = Map<ApiKeyPlatform, PriorityQueue<Box<dyn ApiKey>, Reverse<DateT
 #[derive(Debug)]
 pub struct KeyManager {
     keys: KeyStore,
+    /// notified if no key is ready, once per call which waits for a key or gives up
+    notifier: Option<Notifier>,
 }
 
 impl KeyManager {
     pub fn new() -> Self {
-        KeyManager { keys: Map::new() }
+        KeyManager {
+            keys: Map::new(),
+            notifier: None,
+        }
+    }
+
+    pub fn with_notifier(mut self, notifier: Notifier) -> Self {
+        self.notifier = Some(notifier);
+        self
     }
 
     pub async fn exchange_apikey_or_wait_if_non_ready(
@@ -58,11 +69,16 @@ impl KeyManager {
             let mut d = key_manager.lock().expect("msg");
             d.get_key_and_timeout(platform)
         };
+        let mut notified = false;
         while let Ok(f) = g {
             match f {
                 (Some(_), Some(_)) => return None, // Cannot occur
                 // Queue is empty
                 (None, None) => {
+                    if !notified {
+                        KeyManager::notify_exhausted(&key_manager, platform, None).await;
+                        notified = true;
+                    }
                     if wait {
                         tokio::time::sleep(Duration::minutes(1).to_std().unwrap()).await;
                     } else {
@@ -70,6 +86,11 @@ impl KeyManager {
                     }
                 }
                 (None, Some(refresh_time)) => {
+                    if !notified {
+                        KeyManager::notify_exhausted(&key_manager, platform, Some(refresh_time))
+                            .await;
+                        notified = true;
+                    }
                     if wait {
                         let time_difference = refresh_time - Utc::now();
                         if let Ok(sleep_duration) = time_difference.to_std() {
@@ -89,6 +110,22 @@ impl KeyManager {
         None // Key never added to queue
     }
 
+    async fn notify_exhausted(
+        key_manager: &Arc<Mutex<KeyManager>>,
+        platform: &ApiKeyPlatform,
+        next_ready_at: Option<DateTime<Utc>>,
+    ) {
+        let notifier = key_manager.lock().expect("msg").notifier.clone();
+        if let Some(notifier) = notifier {
+            notifier
+                .notify(&Notification::KeysExhausted {
+                    platform: platform.to_string(),
+                    next_ready_at,
+                })
+                .await;
+        }
+    }
+
     pub fn add_key_by_platform(&mut self, key: Box<dyn ApiKey>) {
         let platform = key.get_platform();
         let key_value_pair = self.keys.get_mut(&platform);
@@ -151,6 +188,12 @@ mod test {
     };
 
     use super::KeyManager;
+    use crate::utils::notification::{NotificationEvent, NotificationSink, Notifier, SinkFormat};
+    use crate::utils::test_helpers::get_test_client;
+    use httpmock::Method::POST;
+    use httpmock::MockServer;
+    use secrecy::Secret;
+    use std::sync::{Arc, Mutex};
 
     // Tested
     // Create object and add key throws no errors
@@ -318,4 +361,71 @@ mod test {
             .unwrap();
         assert_eq!(key1.get_usage_counter(), 1);
     }
+
+    #[tokio::test]
+    async fn exhausted_keys_are_notified_if_not_waiting() {
+        let server = MockServer::start();
+        let webhook = server.mock(|when, then| {
+            when.method(POST).path("/webhook").json_body_partial(
+                r#"{"event": "keys_exhausted", "platform": "Financialmodelingprep"}"#,
+            );
+            then.status(200);
+        });
+        let notifier = Notifier::new(get_test_client()).with_sink(NotificationSink::new(
+            Secret::new(server.url("/webhook")),
+            SinkFormat::Json,
+            vec![NotificationEvent::KeysExhausted],
+        ));
+        let mut km = KeyManager::new().with_notifier(notifier);
+        let mut key = FinancialmodelingprepKey::new_with_time("key1".to_string(), Utc::now());
+        key.set_status(Status::Exhausted);
+        km.add_key_by_platform(Box::new(key));
+
+        let key = KeyManager::get_new_apikey_or_wait(
+            Arc::new(Mutex::new(km)),
+            false,
+            &ApiKeyPlatform::Financialmodelingprep,
+        )
+        .await;
+
+        assert!(key.is_none());
+        webhook.assert();
+    }
+
+    #[tokio::test]
+    async fn exhausted_keys_are_notified_once_when_waiting_starts() {
+        let server = MockServer::start();
+        let webhook = server.mock(|when, then| {
+            when.method(POST).path("/webhook").json_body_partial(
+                r#"{"event": "keys_exhausted", "platform": "Financialmodelingprep"}"#,
+            );
+            then.status(200);
+        });
+        let notifier = Notifier::new(get_test_client()).with_sink(NotificationSink::new(
+            Secret::new(server.url("/webhook")),
+            SinkFormat::Json,
+            vec![NotificationEvent::KeysExhausted],
+        ));
+        let mut km = KeyManager::new().with_notifier(notifier);
+        let mut key = FinancialmodelingprepKey::new_with_time("key1".to_string(), Utc::now());
+        key.set_status(Status::Exhausted);
+        km.add_key_by_platform(Box::new(key));
+
+        let waiting = tokio::spawn(KeyManager::get_new_apikey_or_wait(
+            Arc::new(Mutex::new(km)),
+            true,
+            &ApiKeyPlatform::Financialmodelingprep,
+        ));
+        for _ in 0..50 {
+            if webhook.hits() > 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        assert!(!waiting.is_finished());
+        waiting.abort();
+        webhook.assert_hits(1);
+    }
 }
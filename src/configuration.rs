@@ -1,14 +1,18 @@
+use chrono::{NaiveDate, NaiveTime};
 use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 use serde_aux::field_attributes::deserialize_number_from_string;
 use serde_with::serde_as;
 use serde_with::DefaultOnError;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
+use std::collections::HashMap;
 use tracing::error;
 
 use crate::actions::action::ActionType;
 use crate::actions::collector_sources::CollectorSource;
 use crate::actions::sp500_fields;
+use crate::dag_schedule::task::TriggerRule;
+use crate::utils::notification::{NotificationEvent, SinkFormat};
 
 #[derive(Deserialize)]
 pub struct Settings {
@@ -30,18 +34,158 @@ pub struct DatabaseSettings {
 #[serde_as]
 #[derive(Deserialize)]
 pub struct ApplicationSettings {
+    /// dependencies of the default pipeline
+    #[serde(default)]
     pub task_dependencies: Vec<TaskDependency>,
+    /// further pipelines by name, run side by side with the default pipeline
+    #[serde(default)]
+    pub pipelines: HashMap<String, PipelineSetting>,
+    /// named groups of tasks, a group can be listed as dependency instead of all its members
+    #[serde(default)]
+    pub task_groups: HashMap<TaskName, Vec<TaskName>>,
     pub tasks: Vec<TaskSetting>,
     pub http_client: HttpClientSettings,
+    /// keeps the application running and re-executes tasks according to their cadence
+    #[serde(default)]
+    pub daemon: bool,
+    /// only executes tasks which did not finish in the last run and their descendants
+    #[serde(default)]
+    pub resume_last_run: bool,
+    /// only builds and checks the schedule and logs its execution waves, no task is executed
+    #[serde(default)]
+    pub dry_run: bool,
+    /// directory to which the dag of the schedule is exported as DOT and Mermaid file
+    pub dag_export_dir: Option<String>,
+    /// format in which the report of each pipeline is logged at the end of a run
+    #[serde(default)]
+    pub run_report_format: RunReportFormat,
+    /// maximum number of tasks running at the same time, unlimited if not set
+    pub max_parallel_tasks: Option<usize>,
+    /// maximum number of tasks running at the same time per named resource
+    #[serde(default)]
+    pub resource_pools: HashMap<String, usize>,
+    /// distributes the execution of the tasks over worker processes sharing the database
+    pub distributed: Option<DistributedSetting>,
+    /// tasks the daemon executes as soon as new rows were inserted into a raw data table
+    #[serde(default)]
+    pub event_triggers: Vec<EventTriggerSetting>,
+    /// only runs the given collector over a date range again instead of the pipelines
+    pub backfill: Option<BackfillSetting>,
+    /// webhooks notified about failed tasks, completed runs and exhausted api keys
+    #[serde(default)]
+    pub notifications: Vec<NotificationSetting>,
     #[serde_as(deserialize_as = "DefaultOnError")]
     #[serde(default)]
     pub secrets: SecretKeys,
 }
 
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunReportFormat {
+    #[default]
+    Table,
+    Json,
+}
+
+/// Webhook receiving notifications, e.g. a Slack incoming webhook
+#[derive(Deserialize, Clone, Debug)]
+pub struct NotificationSetting {
+    pub url: Secret<String>,
+    #[serde(default)]
+    pub format: SinkFormat,
+    /// all events if not set
+    #[serde(default = "default_notification_events")]
+    pub events: Vec<NotificationEvent>,
+}
+
+fn default_notification_events() -> Vec<NotificationEvent> {
+    vec![
+        NotificationEvent::TaskFailed,
+        NotificationEvent::ScheduleCompleted,
+        NotificationEvent::KeysExhausted,
+    ]
+}
+
+/// Collects the dates from `from` to `to` (inclusive) again with the collector of the task, stored rows
+/// of these dates are replaced
+#[derive(Deserialize, Clone, Debug)]
+pub struct BackfillSetting {
+    pub task: TaskName,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+/// Triggers a task, usually a stager, when new rows arrive in a table
+#[derive(Deserialize, Clone, Debug)]
+pub struct EventTriggerSetting {
+    pub table: String,
+    pub task: TaskName,
+    /// the task is triggered once no further rows arrived within the given seconds
+    #[serde(default = "default_debounce_seconds")]
+    pub debounce_seconds: u64,
+}
+
+fn default_debounce_seconds() -> u64 {
+    30
+}
+
+/// Defines the role of the process if tasks are executed by workers claiming them from a queue
+#[derive(Deserialize, Clone, Debug)]
+pub struct DistributedSetting {
+    pub role: DistributedRole,
+    /// how often the scheduler checks for results and idle workers check for queued tasks
+    #[serde(default = "default_poll_interval_milliseconds")]
+    pub poll_interval_milliseconds: u64,
+    #[serde(default = "default_heartbeat_interval_seconds")]
+    pub heartbeat_interval_seconds: u64,
+    /// a task is queued again if its worker did not send a heartbeat within the given seconds
+    #[serde(default = "default_stale_after_seconds")]
+    pub stale_after_seconds: u64,
+    /// number of tasks a worker executes at the same time
+    #[serde(default = "default_worker_slots")]
+    pub worker_slots: usize,
+}
+
+fn default_poll_interval_milliseconds() -> u64 {
+    1000
+}
+
+fn default_heartbeat_interval_seconds() -> u64 {
+    10
+}
+
+fn default_stale_after_seconds() -> u64 {
+    60
+}
+
+fn default_worker_slots() -> usize {
+    1
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DistributedRole {
+    /// runs the schedules and queues ready tasks instead of executing them
+    Scheduler,
+    /// executes queued tasks
+    Worker,
+}
+
+/// A pipeline has its own DAG built from the task settings and is run by its own schedule
+#[derive(Deserialize, Clone)]
+pub struct PipelineSetting {
+    pub task_dependencies: Vec<TaskDependency>,
+    /// cadence of the tasks of the pipeline without own cadence (daemon only)
+    pub cadence: Option<CadenceSetting>,
+}
+
 #[derive(Deserialize, Clone)]
 pub struct TaskDependency {
     pub name: TaskName,
     pub dependencies: Vec<TaskName>,
+    /// trigger rule per dependency, dependencies without rule are only successful if they succeeded
+    #[serde(default)]
+    pub trigger_rules: HashMap<TaskName, TriggerRule>,
 }
 
 pub type TaskName = String;
@@ -57,6 +201,128 @@ pub struct TaskSetting {
     pub include_sources: Vec<CollectorSource>,
     #[serde(default = "default_exclude_source")]
     pub exclude_sources: Vec<CollectorSource>,
+    #[serde(default)]
+    pub execution_mode: ExecutionModeSetting,
+    pub cadence: Option<CadenceSetting>,
+    /// resource pools of which the task needs a free slot to be started
+    #[serde(default)]
+    pub resources: Vec<String>,
+    /// cancels a single execution of the task (including retries) after the given seconds
+    pub timeout_seconds: Option<u64>,
+    /// skips the task while its last success lies within the given hours, also across processes,
+    /// its dependents run as if it succeeded
+    pub freshness_hours: Option<u64>,
+    /// tasks with higher priority are started first if several tasks are ready at once
+    #[serde(default)]
+    pub priority: i32,
+    /// retries failed executions with retryable errors, no retries if not set
+    pub retry: Option<RetrySetting>,
+    /// time of day (UTC) before which the task is not started
+    pub not_before: Option<NaiveTime>,
+    /// time of day (UTC) at which the task is cancelled if it did not finish
+    pub deadline: Option<NaiveTime>,
+    /// parameters of the action, checked against the task type on start
+    pub params: Option<config::Value>,
+    /// processes the symbols of the collector as items of their own, a failing symbol only fails its item
+    pub fan_out: Option<FanOutSetting>,
+}
+
+/// Limits of the items of a fan-out task
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct FanOutSetting {
+    #[serde(default = "default_max_parallel_items")]
+    pub max_parallel_items: usize,
+    /// the task fails once more items failed
+    #[serde(default)]
+    pub max_failed_items: usize,
+}
+
+fn default_max_parallel_items() -> usize {
+    1
+}
+
+/// Defines how often and after which back off a failed execution of a task is retried
+#[derive(Deserialize, Clone, Debug)]
+pub struct RetrySetting {
+    pub max_retries: u32,
+    #[serde(default)]
+    pub back_off: BackOffSetting,
+    /// back off of constant back off, lower limit of linear and exponential back off
+    #[serde(default)]
+    pub min_back_off_milliseconds: u64,
+    /// upper limit of linear and exponential back off, the minimum if not set
+    pub max_back_off_milliseconds: Option<u64>,
+    /// base of exponential back off
+    #[serde(default = "default_back_off_base")]
+    pub base: u32,
+    /// maximum random time added to each back off
+    #[serde(default)]
+    pub jitter_milliseconds: u64,
+}
+
+fn default_back_off_base() -> u32 {
+    2
+}
+
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BackOffSetting {
+    #[default]
+    Constant,
+    Linear,
+    Exponential,
+}
+
+/// Defines the condition a sensor task waits for and how often it is checked
+#[derive(Deserialize, Clone, Debug)]
+pub struct SensorSetting {
+    pub condition: SensorConditionSetting,
+    #[serde(default = "default_poke_interval_seconds")]
+    pub poke_interval_seconds: u64,
+    /// fails the sensor if the condition is not met within the given seconds, waits forever if not set
+    pub timeout_seconds: Option<u64>,
+}
+
+fn default_poke_interval_seconds() -> u64 {
+    60
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorConditionSetting {
+    /// query returning a single boolean
+    Sql(String),
+    /// non-empty file, modified within the max age if set
+    File {
+        path: String,
+        max_age_seconds: Option<u64>,
+    },
+    /// url answering a HEAD request with a success status
+    HttpHead(String),
+}
+
+/// Defines how often the action of a task is repeated each time the task is started
+#[derive(Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionModeSetting {
+    #[default]
+    Once,
+    Continuously,
+    RepeatLimited {
+        count: u32,
+    },
+    RepeatForDuration {
+        seconds: u64,
+    },
+}
+
+/// Defines when a task is started again in daemon mode, either by a cron expression
+/// (with seconds, e.g. "0 30 22 * * Mon-Fri") or by a fixed interval
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CadenceSetting {
+    Cron(String),
+    IntervalSeconds(u64),
 }
 
 #[derive(Deserialize, Clone)]
@@ -0,0 +1,61 @@
+use chrono::{DateTime, Utc};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Defines when a recurring task is due again after it was started.
+#[derive(Clone, Debug)]
+pub enum Cadence {
+    Cron(Box<cron::Schedule>),
+    Interval(Duration),
+}
+
+impl Cadence {
+    /// Parses a cron expression with seconds, e.g. "0 30 22 * * Mon-Fri"
+    pub fn from_cron(expression: &str) -> Result<Self, cron::error::Error> {
+        cron::Schedule::from_str(expression).map(|schedule| Cadence::Cron(Box::new(schedule)))
+    }
+
+    /// Returns the next point in time after `after` at which the task is due
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            Cadence::Cron(schedule) => schedule.after(&after).next(),
+            Cadence::Interval(interval) => chrono::Duration::from_std(*interval)
+                .ok()
+                .and_then(|interval| after.checked_add_signed(interval)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dag_schedule::cadence::Cadence;
+    use chrono::{TimeZone, Utc};
+    use std::time::Duration;
+
+    #[test]
+    fn cron_cadence_returns_next_matching_time() {
+        let cadence = Cadence::from_cron("0 30 22 * * Mon-Fri").unwrap();
+        // Friday evening after the trigger time
+        let after = Utc.with_ymd_and_hms(2024, 3, 1, 23, 0, 0).unwrap();
+
+        let next = cadence.next_after(after).unwrap();
+
+        // next trigger is on monday
+        assert_eq!(next, Utc.with_ymd_and_hms(2024, 3, 4, 22, 30, 0).unwrap());
+    }
+
+    #[test]
+    fn interval_cadence_adds_interval() {
+        let cadence = Cadence::Interval(Duration::from_secs(3600));
+        let after = Utc.with_ymd_and_hms(2024, 3, 1, 23, 0, 0).unwrap();
+
+        let next = cadence.next_after(after).unwrap();
+
+        assert_eq!(next, Utc.with_ymd_and_hms(2024, 3, 2, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn invalid_cron_expression_is_rejected() {
+        assert!(Cadence::from_cron("every day").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+/// Limits how many tasks run at the same time, globally and per named resource pool.
+/// A task needs a free slot in the global limit and in every pool it uses to be started.
+#[derive(Clone, Debug, Default)]
+pub struct ConcurrencyLimits {
+    max_parallel_tasks: Option<usize>,
+    pool_sizes: HashMap<String, usize>,
+    used_pool_slots: HashMap<String, usize>,
+    /// resources held by the currently running tasks, by task name
+    running_tasks: HashMap<String, Vec<String>>,
+}
+
+impl ConcurrencyLimits {
+    /// Limits below one are raised to one, so that every task can be started eventually
+    pub fn new(max_parallel_tasks: Option<usize>, pool_sizes: HashMap<String, usize>) -> Self {
+        ConcurrencyLimits {
+            max_parallel_tasks: max_parallel_tasks.map(|max| max.max(1)),
+            pool_sizes: pool_sizes
+                .into_iter()
+                .map(|(pool, size)| (pool, size.max(1)))
+                .collect(),
+            used_pool_slots: HashMap::new(),
+            running_tasks: HashMap::new(),
+        }
+    }
+
+    pub fn has_pool(&self, pool: &str) -> bool {
+        self.pool_sizes.contains_key(pool)
+    }
+
+    /// Takes a slot for the task if the global limit and all its pools have free slots
+    pub fn try_acquire(&mut self, task_name: &str, resources: &[String]) -> bool {
+        let global_slot_free = self
+            .max_parallel_tasks
+            .is_none_or(|max| self.running_tasks.len() < max);
+        let pool_slots_free = resources.iter().all(|pool| {
+            self.pool_sizes.get(pool).is_none_or(|size| {
+                self.used_pool_slots.get(pool).copied().unwrap_or_default() < *size
+            })
+        });
+        if !global_slot_free || !pool_slots_free {
+            return false;
+        }
+        for pool in resources {
+            *self.used_pool_slots.entry(pool.clone()).or_default() += 1;
+        }
+        self.running_tasks
+            .insert(task_name.to_string(), resources.to_vec());
+        true
+    }
+
+    /// Frees the slots of the task, does nothing if the task holds no slot
+    pub fn release(&mut self, task_name: &str) {
+        let Some(resources) = self.running_tasks.remove(task_name) else {
+            return;
+        };
+        for pool in resources {
+            if let Some(used) = self.used_pool_slots.get_mut(&pool) {
+                *used = used.saturating_sub(1);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dag_schedule::concurrency::ConcurrencyLimits;
+    use std::collections::HashMap;
+
+    #[test]
+    fn global_limit_is_respected() {
+        let mut limits = ConcurrencyLimits::new(Some(2), HashMap::new());
+
+        assert!(limits.try_acquire("a", &[]));
+        assert!(limits.try_acquire("b", &[]));
+        assert!(!limits.try_acquire("c", &[]));
+
+        limits.release("a");
+        assert!(limits.try_acquire("c", &[]));
+    }
+
+    #[test]
+    fn pool_limit_only_blocks_tasks_using_the_pool() {
+        let mut limits =
+            ConcurrencyLimits::new(None, HashMap::from([("polygon_api".to_string(), 1)]));
+        let polygon = vec!["polygon_api".to_string()];
+
+        assert!(limits.try_acquire("grouped_daily", &polygon));
+        assert!(!limits.try_acquire("open_close", &polygon));
+        assert!(limits.try_acquire("sec_companies", &[]));
+
+        limits.release("grouped_daily");
+        assert!(limits.try_acquire("open_close", &polygon));
+    }
+
+    #[test]
+    fn limits_below_one_are_raised_to_one() {
+        let mut limits = ConcurrencyLimits::new(Some(0), HashMap::from([("db".to_string(), 0)]));
+
+        assert!(limits.try_acquire("a", &["db".to_string()]));
+        assert!(!limits.try_acquire("b", &[]));
+    }
+}
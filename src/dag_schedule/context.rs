@@ -0,0 +1,92 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+
+/// Output a task hands to its outgoing tasks, e.g. the keys of the rows it inserted
+pub type TaskOutput = Arc<dyn Any + Send + Sync>;
+
+/// Passed to every execution of a runnable. Gives access to the outputs of the upstream tasks
+/// which finished in the current run and takes the output of the runnable itself.
+#[derive(Clone, Debug, Default)]
+pub struct TaskContext {
+    /// cancelled once the task shall stop, long running actions should check it regularly
+    pub cancel: CancellationToken,
+    upstream_outputs: HashMap<String, TaskOutput>,
+    output: Arc<Mutex<Option<TaskOutput>>>,
+}
+
+impl TaskContext {
+    pub fn new(cancel: CancellationToken, upstream_outputs: HashMap<String, TaskOutput>) -> Self {
+        TaskContext {
+            cancel,
+            upstream_outputs,
+            output: Default::default(),
+        }
+    }
+
+    /// output of the given upstream task, none if the task did not provide an output of type `T`
+    pub fn upstream_output<T: Any + Send + Sync>(&self, task_name: &str) -> Option<Arc<T>> {
+        self.upstream_outputs
+            .get(task_name)
+            .and_then(|output| output.clone().downcast::<T>().ok())
+    }
+
+    /// outputs of type `T` of all upstream tasks, ordered by task name
+    pub fn upstream_outputs<T: Any + Send + Sync>(&self) -> Vec<Arc<T>> {
+        let mut outputs: Vec<(&String, Arc<T>)> = self
+            .upstream_outputs
+            .iter()
+            .filter_map(|(name, output)| output.clone().downcast::<T>().ok().map(|o| (name, o)))
+            .collect();
+        outputs.sort_by(|a, b| a.0.cmp(b.0));
+        outputs.into_iter().map(|(_, output)| output).collect()
+    }
+
+    /// sets the output handed to the outgoing tasks, replaces the output of a previous execution
+    pub fn set_output<T: Any + Send + Sync>(&self, output: T) {
+        *self.output.lock().expect("output lock poisoned") = Some(Arc::new(output));
+    }
+
+    /// output set by the runnable, if any
+    pub fn output(&self) -> Option<TaskOutput> {
+        self.output.lock().expect("output lock poisoned").clone()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dag_schedule::context::{TaskContext, TaskOutput};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use tokio_util::sync::CancellationToken;
+
+    #[test]
+    fn upstream_outputs_are_returned_by_type() {
+        let upstream_outputs: HashMap<String, TaskOutput> = HashMap::from([
+            ("b".to_string(), Arc::new(2_u32) as TaskOutput),
+            ("a".to_string(), Arc::new(1_u32) as TaskOutput),
+            ("c".to_string(), Arc::new("text".to_string()) as TaskOutput),
+        ]);
+        let context = TaskContext::new(CancellationToken::new(), upstream_outputs);
+
+        assert_eq!(context.upstream_output::<u32>("b"), Some(Arc::new(2)));
+        assert_eq!(context.upstream_output::<String>("b"), None);
+        assert_eq!(context.upstream_output::<u32>("unknown"), None);
+        assert_eq!(
+            context.upstream_outputs::<u32>(),
+            vec![Arc::new(1), Arc::new(2)]
+        );
+    }
+
+    #[test]
+    fn output_is_shared_between_clones() {
+        let context = TaskContext::default();
+        let execution_context = context.clone();
+
+        execution_context.set_output(vec!["AAPL".to_string()]);
+
+        let output = context.output().unwrap().downcast::<Vec<String>>().unwrap();
+        assert_eq!(*output, vec!["AAPL".to_string()]);
+    }
+}
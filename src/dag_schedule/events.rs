@@ -0,0 +1,214 @@
+use crate::dag_schedule::schedule::ScheduleError;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info};
+
+/// Maps events, e.g. new rows in a raw data table, to the tasks they trigger. A burst of events
+/// triggers a task only once, as soon as no further event arrived within the debounce of the task.
+#[derive(Clone, Debug, Default)]
+pub struct EventTriggers {
+    triggers: HashMap<String, Vec<(String, Duration)>>,
+}
+
+impl EventTriggers {
+    pub fn new() -> Self {
+        EventTriggers::default()
+    }
+
+    pub fn with_trigger(mut self, event: String, task_name: String, debounce: Duration) -> Self {
+        self.triggers
+            .entry(event)
+            .or_default()
+            .push((task_name, debounce));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triggers.is_empty()
+    }
+
+    /// Triggers of tasks which are not configured are returned as problems, sorted by event
+    pub fn check_tasks(&self, configured_tasks: &HashSet<&str>) -> Vec<ScheduleError> {
+        let mut problems: Vec<ScheduleError> = self
+            .triggers
+            .iter()
+            .flat_map(|(event, triggers)| {
+                triggers
+                    .iter()
+                    .filter(|(task_name, _)| !configured_tasks.contains(task_name.as_str()))
+                    .map(|(task_name, _)| ScheduleError::UnknownTriggeredTask {
+                        event: event.clone(),
+                        task: task_name.clone(),
+                    })
+            })
+            .collect();
+        problems.sort_by_key(|problem| problem.to_string());
+        problems
+    }
+
+    /// Sends the names of the triggered tasks to the schedules containing them, each schedule is
+    /// given with the names of its tasks. Returns when cancelled or when no more events are sent.
+    pub async fn run(
+        &self,
+        mut events: mpsc::Receiver<String>,
+        schedules: Vec<(mpsc::Sender<String>, HashSet<String>)>,
+        cancel: CancellationToken,
+    ) {
+        // triggered tasks and when their debounce passes
+        let mut pending: HashMap<String, Instant> = HashMap::new();
+        loop {
+            let next_due = pending.values().min().copied();
+            tokio::select! {
+                event = events.recv() => {
+                    let Some(event) = event else {
+                        return;
+                    };
+                    let Some(triggers) = self.triggers.get(&event) else {
+                        debug!("No task is triggered by event {}", event);
+                        continue;
+                    };
+                    for (task_name, debounce) in triggers {
+                        pending.insert(task_name.clone(), Instant::now() + *debounce);
+                    }
+                }
+                _ = sleep_until(next_due) => {
+                    let now = Instant::now();
+                    let due: Vec<String> = pending
+                        .iter()
+                        .filter(|(_, due_at)| **due_at <= now)
+                        .map(|(task_name, _)| task_name.clone())
+                        .collect();
+                    for task_name in due {
+                        pending.remove(&task_name);
+                        info!("Trigger task {} after new data arrived", task_name);
+                        for (schedule, task_names) in &schedules {
+                            if !task_names.contains(&task_name) {
+                                continue;
+                            }
+                            // a schedule which stopped does not receive triggers anymore
+                            let _ = schedule.send(task_name.clone()).await;
+                        }
+                    }
+                }
+                _ = cancel.cancelled() => return,
+            }
+        }
+    }
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dag_schedule::events::EventTriggers;
+    use crate::dag_schedule::schedule::ScheduleError;
+    use std::collections::HashSet;
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use tokio_util::sync::CancellationToken;
+
+    #[test]
+    fn triggers_of_unknown_tasks_are_problems() {
+        let triggers = EventTriggers::new()
+            .with_trigger(
+                "sec_companies".to_string(),
+                "SecCompaniesStage".to_string(),
+                Duration::ZERO,
+            )
+            .with_trigger(
+                "financialmodelingprep_market_cap".to_string(),
+                "FinmodMarketCapStagr".to_string(),
+                Duration::ZERO,
+            );
+
+        let problems = triggers.check_tasks(&HashSet::from(["SecCompaniesStage"]));
+
+        assert!(matches!(
+            problems.as_slice(),
+            [ScheduleError::UnknownTriggeredTask { event, task }]
+                if event == "financialmodelingprep_market_cap" && task == "FinmodMarketCapStagr"
+        ));
+    }
+
+    #[tokio::test]
+    async fn burst_of_events_triggers_task_once() {
+        let triggers = EventTriggers::new().with_trigger(
+            "sec_companies".to_string(),
+            "SecCompaniesStage".to_string(),
+            Duration::from_millis(100),
+        );
+        let (event_sender, events) = mpsc::channel(10);
+        let (schedule, mut triggered) = mpsc::channel(10);
+        let cancel = CancellationToken::new();
+        let run_cancel = cancel.clone();
+        let schedules = vec![(schedule, HashSet::from(["SecCompaniesStage".to_string()]))];
+        let handle = tokio::spawn(async move { triggers.run(events, schedules, run_cancel).await });
+
+        for _ in 0..3 {
+            event_sender
+                .send("sec_companies".to_string())
+                .await
+                .unwrap();
+            event_sender.send("nyse_events".to_string()).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+
+        let task_name = tokio::time::timeout(Duration::from_secs(3), triggered.recv())
+            .await
+            .expect("task must be triggered after the debounce")
+            .unwrap();
+        assert_eq!(task_name, "SecCompaniesStage");
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(triggered.try_recv().is_err());
+
+        cancel.cancel();
+        handle.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn task_is_only_sent_to_schedules_containing_it() {
+        let triggers = EventTriggers::new().with_trigger(
+            "sec_companies".to_string(),
+            "SecCompaniesStage".to_string(),
+            Duration::ZERO,
+        );
+        let (event_sender, events) = mpsc::channel(10);
+        let (stager_schedule, mut stager_triggered) = mpsc::channel(10);
+        let (other_schedule, mut other_triggered) = mpsc::channel(10);
+        let schedules = vec![
+            (
+                stager_schedule,
+                HashSet::from(["SecCompaniesStage".to_string()]),
+            ),
+            (
+                other_schedule,
+                HashSet::from(["NyseEventsCollect".to_string()]),
+            ),
+        ];
+        let cancel = CancellationToken::new();
+        let run_cancel = cancel.clone();
+        let handle = tokio::spawn(async move { triggers.run(events, schedules, run_cancel).await });
+
+        event_sender
+            .send("sec_companies".to_string())
+            .await
+            .unwrap();
+
+        let task_name = tokio::time::timeout(Duration::from_secs(3), stager_triggered.recv())
+            .await
+            .expect("task must be triggered")
+            .unwrap();
+        assert_eq!(task_name, "SecCompaniesStage");
+        cancel.cancel();
+        handle.await.unwrap();
+        assert!(other_triggered.try_recv().is_err());
+    }
+}
@@ -0,0 +1,336 @@
+use crate::dag_schedule::schedule::TaskGroups;
+use crate::dag_schedule::task::{ExecutionState, TriggerRule};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Snapshot of the tasks of a schedule and their dependencies, used to render the DAG
+#[derive(Debug, Default, PartialEq)]
+pub struct DagGraph {
+    /// names and states of the tasks, sorted by name
+    pub tasks: Vec<(String, ExecutionState)>,
+    /// (dependency, dependent task, trigger rule of the dependent task), sorted
+    pub dependencies: Vec<(String, String, TriggerRule)>,
+}
+
+impl DagGraph {
+    /// Groups the tasks into waves: a task belongs to the first wave after all its dependencies.
+    /// Tasks within a wave are sorted by name. Tasks on a cycle are not part of any wave.
+    pub fn execution_waves(&self) -> Vec<Vec<String>> {
+        let mut remaining_dependencies: HashMap<&str, usize> = self
+            .tasks
+            .iter()
+            .map(|(name, _)| (name.as_str(), 0))
+            .collect();
+        for (_, task, _) in self.dependencies.iter() {
+            *remaining_dependencies.entry(task.as_str()).or_default() += 1;
+        }
+
+        let mut waves = vec![];
+        loop {
+            let mut wave: Vec<String> = remaining_dependencies
+                .iter()
+                .filter(|(_, count)| **count == 0)
+                .map(|(name, _)| name.to_string())
+                .collect();
+            if wave.is_empty() {
+                return waves;
+            }
+            wave.sort();
+            for name in wave.iter() {
+                remaining_dependencies.remove(name.as_str());
+                for (_, task, _) in self.dependencies.iter().filter(|(d, _, _)| d == name) {
+                    if let Some(count) = remaining_dependencies.get_mut(task.as_str()) {
+                        *count -= 1;
+                    }
+                }
+            }
+            waves.push(wave);
+        }
+    }
+
+    /// Replaces the members of each group by a single node named after the group. The node is in
+    /// the first of the states failed, cancelled, running, pending and skipped any member is in,
+    /// finished only if all members finished. Dependencies within a group are dropped.
+    pub fn collapse_groups(&self, groups: &TaskGroups) -> DagGraph {
+        let mut group_names: Vec<&String> = groups.keys().collect();
+        group_names.sort();
+        let mut group_of: HashMap<&str, &str> = HashMap::new();
+        for group in group_names {
+            for member in groups[group].iter() {
+                group_of.entry(member.as_str()).or_insert(group.as_str());
+            }
+        }
+        let node = |name: &String| {
+            group_of
+                .get(name.as_str())
+                .map(|group| group.to_string())
+                .unwrap_or_else(|| name.clone())
+        };
+
+        let mut member_states: HashMap<String, Vec<ExecutionState>> = HashMap::new();
+        for (name, state) in self.tasks.iter() {
+            member_states.entry(node(name)).or_default().push(*state);
+        }
+        let mut tasks: Vec<(String, ExecutionState)> = member_states
+            .into_iter()
+            .map(|(name, states)| (name, collapsed_state(&states)))
+            .collect();
+        tasks.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut dependencies: Vec<(String, String, TriggerRule)> = self
+            .dependencies
+            .iter()
+            .map(|(dependency, task, rule)| (node(dependency), node(task), *rule))
+            .filter(|(dependency, task, _)| dependency != task)
+            .collect();
+        dependencies.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        dependencies.dedup_by(|a, b| a.0 == b.0 && a.1 == b.1);
+        DagGraph {
+            tasks,
+            dependencies,
+        }
+    }
+
+    /// Renders the DAG as Graphviz DOT, e.g. `dot -Tsvg dependencies.dot > output.svg`
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::new();
+        let _ = writeln!(dot, "digraph schedule {{");
+        let _ = writeln!(dot, " fontname=\"Helvetica,Arial,sans-serif\"");
+        let _ = writeln!(dot, " node [fontname=\"Helvetica,Arial,sans-serif\"]");
+        let _ = writeln!(dot, " rankdir=\"LR\";");
+        for (name, state) in self.tasks.iter() {
+            match state_color(*state) {
+                Some(color) => {
+                    let _ = writeln!(
+                        dot,
+                        " \"{}\" [label=\"{}\\n{}\", style=filled, fillcolor={}];",
+                        name,
+                        name,
+                        state.as_str(),
+                        color
+                    );
+                }
+                None => {
+                    let _ = writeln!(dot, " \"{}\";", name);
+                }
+            }
+        }
+        let _ = writeln!(dot);
+        for (dependency, task, rule) in self.dependencies.iter() {
+            match rule_label(*rule) {
+                Some(label) => {
+                    let _ = writeln!(
+                        dot,
+                        " \"{}\" -> \"{}\" [label=\"{}\", style=dashed];",
+                        dependency, task, label
+                    );
+                }
+                None => {
+                    let _ = writeln!(dot, " \"{}\" -> \"{}\";", dependency, task);
+                }
+            }
+        }
+        let _ = writeln!(dot, "}}");
+        dot
+    }
+
+    /// Renders the DAG as Mermaid flowchart, e.g. to embed it in markdown documentation
+    pub fn to_mermaid(&self) -> String {
+        let mut mermaid = String::new();
+        let _ = writeln!(mermaid, "flowchart LR");
+        for (name, state) in self.tasks.iter() {
+            match state {
+                ExecutionState::Pending => {
+                    let _ = writeln!(mermaid, "    {}[\"{}\"]", mermaid_id(name), name);
+                }
+                state => {
+                    let _ = writeln!(
+                        mermaid,
+                        "    {}[\"{}<br/>{}\"]:::{}",
+                        mermaid_id(name),
+                        name,
+                        state.as_str(),
+                        state.as_str()
+                    );
+                }
+            }
+        }
+        for (dependency, task, rule) in self.dependencies.iter() {
+            match rule_label(*rule) {
+                Some(label) => {
+                    let _ = writeln!(
+                        mermaid,
+                        "    {} -.->|{}| {}",
+                        mermaid_id(dependency),
+                        label,
+                        mermaid_id(task)
+                    );
+                }
+                None => {
+                    let _ = writeln!(
+                        mermaid,
+                        "    {} --> {}",
+                        mermaid_id(dependency),
+                        mermaid_id(task)
+                    );
+                }
+            }
+        }
+        let mut used_states: Vec<ExecutionState> = self
+            .tasks
+            .iter()
+            .map(|(_, state)| *state)
+            .filter(|state| state_color(*state).is_some())
+            .collect();
+        used_states.sort_by_key(|state| state.as_str());
+        used_states.dedup();
+        for state in used_states {
+            if let Some(color) = state_color(state) {
+                let _ = writeln!(mermaid, "    classDef {} fill:{}", state.as_str(), color);
+            }
+        }
+        mermaid
+    }
+}
+
+fn collapsed_state(states: &[ExecutionState]) -> ExecutionState {
+    [
+        ExecutionState::Failed,
+        ExecutionState::Cancelled,
+        ExecutionState::Running,
+        ExecutionState::Pending,
+        ExecutionState::Skipped,
+    ]
+    .into_iter()
+    .find(|state| states.contains(state))
+    .unwrap_or(ExecutionState::Finished)
+}
+
+/// colors of the final states, pending tasks are not colored
+fn state_color(state: ExecutionState) -> Option<&'static str> {
+    match state {
+        ExecutionState::Pending => None,
+        ExecutionState::Running => Some("lightblue"),
+        ExecutionState::Finished => Some("chartreuse"),
+        ExecutionState::Failed => Some("tomato"),
+        ExecutionState::Cancelled => Some("orange"),
+        ExecutionState::Skipped => Some("lightgrey"),
+    }
+}
+
+/// only trigger rules differing from the default are shown
+fn rule_label(rule: TriggerRule) -> Option<&'static str> {
+    match rule {
+        TriggerRule::OnSuccess => None,
+        TriggerRule::OnFailure => Some("on failure"),
+        TriggerRule::Always => Some("always"),
+    }
+}
+
+/// mermaid node ids must not contain special characters, the name is used as label
+fn mermaid_id(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dag_schedule::export::DagGraph;
+    use crate::dag_schedule::task::{ExecutionState, TriggerRule};
+    use std::collections::HashMap;
+
+    fn create_graph() -> DagGraph {
+        DagGraph {
+            tasks: vec![
+                ("Collect".to_string(), ExecutionState::Finished),
+                ("Notify".to_string(), ExecutionState::Pending),
+                ("Other".to_string(), ExecutionState::Pending),
+                ("Stage".to_string(), ExecutionState::Failed),
+            ],
+            dependencies: vec![
+                (
+                    "Collect".to_string(),
+                    "Stage".to_string(),
+                    TriggerRule::OnSuccess,
+                ),
+                (
+                    "Stage".to_string(),
+                    "Notify".to_string(),
+                    TriggerRule::Always,
+                ),
+            ],
+        }
+    }
+
+    #[test]
+    fn execution_waves_follow_dependencies() {
+        assert_eq!(
+            create_graph().execution_waves(),
+            vec![
+                vec!["Collect".to_string(), "Other".to_string()],
+                vec!["Stage".to_string()],
+                vec!["Notify".to_string()],
+            ]
+        );
+    }
+
+    #[test]
+    fn dot_contains_states_and_trigger_rules() {
+        let dot = create_graph().to_dot();
+
+        assert!(dot.starts_with("digraph schedule {"));
+        assert!(dot.contains(
+            " \"Collect\" [label=\"Collect\\nfinished\", style=filled, fillcolor=chartreuse];"
+        ));
+        assert!(dot.contains(" \"Other\";"));
+        assert!(dot.contains(" \"Collect\" -> \"Stage\";"));
+        assert!(dot.contains(" \"Stage\" -> \"Notify\" [label=\"always\", style=dashed];"));
+    }
+
+    #[test]
+    fn mermaid_contains_states_and_trigger_rules() {
+        let mermaid = create_graph().to_mermaid();
+
+        assert_eq!(
+            mermaid,
+            "flowchart LR
+    Collect[\"Collect<br/>finished\"]:::finished
+    Notify[\"Notify\"]
+    Other[\"Other\"]
+    Stage[\"Stage<br/>failed\"]:::failed
+    Collect --> Stage
+    Stage -.->|always| Notify
+    classDef failed fill:tomato
+    classDef finished fill:chartreuse
+"
+        );
+    }
+
+    #[test]
+    fn collapsed_groups_replace_their_members() {
+        let groups = HashMap::from([(
+            "Polygon".to_string(),
+            vec!["Collect".to_string(), "Stage".to_string()],
+        )]);
+
+        let collapsed = create_graph().collapse_groups(&groups);
+
+        assert_eq!(
+            collapsed.tasks,
+            vec![
+                ("Notify".to_string(), ExecutionState::Pending),
+                ("Other".to_string(), ExecutionState::Pending),
+                ("Polygon".to_string(), ExecutionState::Failed),
+            ]
+        );
+        assert_eq!(
+            collapsed.dependencies,
+            vec![(
+                "Polygon".to_string(),
+                "Notify".to_string(),
+                TriggerRule::Always
+            )]
+        );
+    }
+}
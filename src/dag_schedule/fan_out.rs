@@ -0,0 +1,374 @@
+use crate::dag_schedule::context::TaskContext;
+use crate::dag_schedule::task::{error_chain, Runnable, StatsMap, TaskError};
+use async_trait::async_trait;
+use futures_util::{stream, StreamExt};
+use serde::Serialize;
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+/// Action which is expanded at runtime into items, e.g. one per symbol, processed independently
+/// of each other. A failing item does not stop the other items.
+#[async_trait]
+pub trait FanOut: Send + Sync + Debug {
+    /// Lists the items to process, called on every execution of the task
+    async fn items(&self, context: &TaskContext) -> Result<Vec<String>, TaskError>;
+
+    /// Processes a single item
+    async fn run_item(&self, item: &str, context: &TaskContext) -> Result<(), TaskError>;
+}
+
+/// Limits of the items of a fan-out task
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FanOutOptions {
+    /// number of items processed at the same time
+    pub max_parallel_items: usize,
+    /// the task fails once more items failed
+    pub max_failed_items: usize,
+}
+
+impl Default for FanOutOptions {
+    fn default() -> Self {
+        FanOutOptions {
+            max_parallel_items: 1,
+            max_failed_items: 0,
+        }
+    }
+}
+
+/// Item which failed with the given error including all its causes
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ItemFailure {
+    pub item: String,
+    pub error: String,
+}
+
+/// Outcome of all items of an execution, set as output of the task and reported per task in the
+/// run report. Items are sorted.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct FanOutResult {
+    pub succeeded: Vec<String>,
+    pub failed: Vec<ItemFailure>,
+    /// items not started or interrupted because the task was cancelled
+    pub skipped: Vec<String>,
+}
+
+/// Runs the items of a [`FanOut`] with bounded parallelism and tracks the outcome of every item.
+/// The number of items and the failed items are reported as custom stats. A retry of the task
+/// only runs the items which did not succeed in the previous attempt.
+#[derive(Debug)]
+pub struct FanOutRunnable {
+    fan_out: Arc<dyn FanOut>,
+    options: FanOutOptions,
+}
+
+impl FanOutRunnable {
+    /// A parallelism below one is raised to one
+    pub fn new(fan_out: Arc<dyn FanOut>, options: FanOutOptions) -> Self {
+        FanOutRunnable {
+            fan_out,
+            options: FanOutOptions {
+                max_parallel_items: options.max_parallel_items.max(1),
+                ..options
+            },
+        }
+    }
+
+    /// Result of the previous attempt of the same execution if too many of its items failed, so
+    /// that this attempt is a retry. Repeated executions after a successful attempt run all items.
+    fn failed_attempt(&self, context: &TaskContext) -> Option<Arc<FanOutResult>> {
+        context
+            .output()
+            .and_then(|output| output.downcast::<FanOutResult>().ok())
+            .filter(|result| {
+                result.skipped.is_empty() && result.failed.len() > self.options.max_failed_items
+            })
+    }
+}
+
+#[async_trait]
+impl Runnable for FanOutRunnable {
+    async fn run(&self, context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
+        let mut items = self.fan_out.items(&context).await?;
+        let previous = self.failed_attempt(&context);
+        match &previous {
+            Some(previous) => {
+                items.retain(|item| !previous.succeeded.contains(item));
+                info!(
+                    "Retry {} items, {} items succeeded in a previous attempt",
+                    items.len(),
+                    previous.succeeded.len()
+                );
+            }
+            None => info!("Fan out into {} items", items.len()),
+        }
+        let outcomes: Vec<(String, Option<Result<(), TaskError>>)> = stream::iter(items)
+            .map(|item| {
+                let context = &context;
+                async move {
+                    // running items are interrupted as well, they are started again by the next run
+                    tokio::select! {
+                        biased;
+                        _ = context.cancel.cancelled() => (item, None),
+                        result = self.fan_out.run_item(&item, context) => (item, Some(result)),
+                    }
+                }
+            })
+            .buffer_unordered(self.options.max_parallel_items)
+            .collect()
+            .await;
+
+        let mut result = FanOutResult {
+            succeeded: previous
+                .map(|previous| previous.succeeded.clone())
+                .unwrap_or_default(),
+            ..Default::default()
+        };
+        let total = result.succeeded.len() + outcomes.len();
+        let mut retryable = false;
+        for (item, outcome) in outcomes {
+            match outcome {
+                Some(Ok(())) => result.succeeded.push(item),
+                Some(Err(e)) => {
+                    let error = error_chain(&e);
+                    warn!("Item {} failed: {}", item, error);
+                    retryable |= e.is_retryable();
+                    result.failed.push(ItemFailure { item, error });
+                }
+                None => result.skipped.push(item),
+            }
+        }
+        result.succeeded.sort();
+        result.failed.sort_by(|a, b| a.item.cmp(&b.item));
+        result.skipped.sort();
+        let stats = fan_out_stats(total, &result);
+        let failed: Vec<String> = result.failed.iter().map(|f| f.item.clone()).collect();
+        let cancelled = !result.skipped.is_empty();
+        context.set_output(result);
+
+        if cancelled {
+            return Err(TaskError::Cancelled);
+        }
+        if failed.len() > self.options.max_failed_items {
+            return Err(TaskError::ItemsFailed {
+                failed,
+                total,
+                retryable,
+            });
+        }
+        Ok(Some(stats))
+    }
+}
+
+fn fan_out_stats(total: usize, result: &FanOutResult) -> StatsMap {
+    let failed_items = serde_json::Value::Array(
+        result
+            .failed
+            .iter()
+            .map(|f| serde_json::json!({"item": f.item, "error": f.error}))
+            .collect(),
+    );
+    let stats: HashMap<String, Arc<dyn Any + Send + Sync>> = HashMap::from([
+        (
+            "items".to_string(),
+            Arc::new(total) as Arc<dyn Any + Send + Sync>,
+        ),
+        (
+            "succeeded_items".to_string(),
+            Arc::new(result.succeeded.len()),
+        ),
+        ("failed_items".to_string(), Arc::new(failed_items)),
+    ]);
+    Arc::new(Mutex::new(stats))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dag_schedule::context::TaskContext;
+    use crate::dag_schedule::fan_out::{
+        FanOut, FanOutOptions, FanOutResult, FanOutRunnable, ItemFailure,
+    };
+    use crate::dag_schedule::task::{custom_stats_to_json, Runnable, TaskError};
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// Fails the items starting with "bad", runs the items starting with "slow" for an hour,
+    /// tracks how many items run at the same time and which items were started
+    #[derive(Debug, Default)]
+    struct Symbols {
+        items: Vec<String>,
+        running: AtomicUsize,
+        max_running: AtomicUsize,
+        started: Mutex<Vec<String>>,
+    }
+
+    impl Symbols {
+        fn new(items: &[&str]) -> Self {
+            Symbols {
+                items: items.iter().map(|item| item.to_string()).collect(),
+                ..Default::default()
+            }
+        }
+    }
+
+    #[async_trait]
+    impl FanOut for Symbols {
+        async fn items(&self, _context: &TaskContext) -> Result<Vec<String>, TaskError> {
+            Ok(self.items.clone())
+        }
+
+        async fn run_item(&self, item: &str, _context: &TaskContext) -> Result<(), TaskError> {
+            self.started.lock().unwrap().push(item.to_string());
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+            if item.starts_with("slow") {
+                tokio::time::sleep(Duration::from_secs(3600)).await;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            if item.starts_with("bad") {
+                return Err(TaskError::UnexpectedError(anyhow::anyhow!(
+                    "No data for {}",
+                    item
+                )));
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn items_run_with_bounded_parallelism_and_failures_are_tracked() {
+        let symbols = Arc::new(Symbols::new(&["MSFT", "bad_A", "AAPL", "IBM", "bad_B"]));
+        let runnable = FanOutRunnable::new(
+            symbols.clone(),
+            FanOutOptions {
+                max_parallel_items: 2,
+                max_failed_items: 2,
+            },
+        );
+        let context = TaskContext::default();
+
+        let stats = runnable.run(context.clone()).await.unwrap().unwrap();
+
+        assert_eq!(symbols.max_running.load(Ordering::SeqCst), 2);
+        let output = context
+            .output()
+            .unwrap()
+            .downcast::<FanOutResult>()
+            .unwrap();
+        assert_eq!(output.succeeded, vec!["AAPL", "IBM", "MSFT"]);
+        assert_eq!(
+            output.failed,
+            vec![
+                ItemFailure {
+                    item: "bad_A".to_string(),
+                    error: "Something went wrong: No data for bad_A".to_string()
+                },
+                ItemFailure {
+                    item: "bad_B".to_string(),
+                    error: "Something went wrong: No data for bad_B".to_string()
+                },
+            ]
+        );
+        let stats = custom_stats_to_json(&stats).await;
+        assert_eq!(stats["items"], json!(5));
+        assert_eq!(stats["succeeded_items"], json!(3));
+        assert_eq!(stats["failed_items"][1]["item"], json!("bad_B"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn too_many_failed_items_fail_the_task() {
+        let runnable = FanOutRunnable::new(
+            Arc::new(Symbols::new(&["AAPL", "bad_A", "bad_B"])),
+            FanOutOptions::default(),
+        );
+        let context = TaskContext::default();
+
+        let error = runnable.run(context.clone()).await.unwrap_err();
+
+        assert_eq!(error.to_string(), "2 of 3 items failed: bad_A, bad_B");
+        assert!(error.is_retryable());
+        let output = context
+            .output()
+            .unwrap()
+            .downcast::<FanOutResult>()
+            .unwrap();
+        assert_eq!(output.succeeded, vec!["AAPL"]);
+    }
+
+    #[tokio::test]
+    async fn cancelled_task_skips_remaining_items() {
+        let runnable = FanOutRunnable::new(
+            Arc::new(Symbols::new(&["AAPL", "IBM"])),
+            FanOutOptions::default(),
+        );
+        let context = TaskContext::default();
+        context.cancel.cancel();
+
+        let error = runnable.run(context.clone()).await.unwrap_err();
+
+        assert!(matches!(error, TaskError::Cancelled));
+        let output = context
+            .output()
+            .unwrap()
+            .downcast::<FanOutResult>()
+            .unwrap();
+        assert_eq!(output.skipped, vec!["AAPL", "IBM"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retry_only_runs_the_items_which_did_not_succeed() {
+        let symbols = Arc::new(Symbols::new(&["AAPL", "bad_A", "IBM"]));
+        let runnable = FanOutRunnable::new(symbols.clone(), FanOutOptions::default());
+        let context = TaskContext::default();
+
+        runnable.run(context.clone()).await.unwrap_err();
+        let error = runnable.run(context.clone()).await.unwrap_err();
+
+        assert_eq!(error.to_string(), "1 of 3 items failed: bad_A");
+        let mut started = symbols.started.lock().unwrap().clone();
+        started.sort();
+        assert_eq!(started, vec!["AAPL", "IBM", "bad_A", "bad_A"]);
+        let output = context
+            .output()
+            .unwrap()
+            .downcast::<FanOutResult>()
+            .unwrap();
+        assert_eq!(output.succeeded, vec!["AAPL", "IBM"]);
+        assert_eq!(output.failed[0].item, "bad_A");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancelled_task_interrupts_running_items() {
+        let runnable = FanOutRunnable::new(
+            Arc::new(Symbols::new(&["AAPL", "slow_A"])),
+            FanOutOptions {
+                max_parallel_items: 2,
+                max_failed_items: 0,
+            },
+        );
+        let context = TaskContext::default();
+        let cancel = context.cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            cancel.cancel();
+        });
+
+        let error = runnable.run(context.clone()).await.unwrap_err();
+
+        assert!(matches!(error, TaskError::Cancelled));
+        let output = context
+            .output()
+            .unwrap()
+            .downcast::<FanOutResult>()
+            .unwrap();
+        assert_eq!(output.succeeded, vec!["AAPL"]);
+        assert_eq!(output.skipped, vec!["slow_A"]);
+    }
+}
@@ -1,2 +1,14 @@
+pub mod cadence;
+pub mod concurrency;
+pub mod context;
+pub mod events;
+pub mod export;
+pub mod fan_out;
+pub mod report;
 pub mod schedule;
+pub mod scheduler;
 pub mod task;
+#[cfg(test)]
+pub mod testing;
+pub mod window;
+pub mod worker;
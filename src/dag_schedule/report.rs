@@ -0,0 +1,231 @@
+use crate::dag_schedule::fan_out::FanOutResult;
+use crate::dag_schedule::schedule::TaskResult;
+use crate::dag_schedule::task::{custom_stats_to_json, ExecutionState};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Display;
+use uuid::Uuid;
+
+/// Result of a single run of a schedule, returned by [`crate::dag_schedule::schedule::Schedule::run_schedule`]
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RunReport {
+    pub pipeline: String,
+    pub run_id: Uuid,
+    /// tasks sorted by name
+    pub tasks: Vec<TaskReport>,
+}
+
+/// Final state of a task in a run including its stats
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TaskReport {
+    pub name: String,
+    pub state: ExecutionState,
+    /// empty if the task was not executed, e.g. because it was skipped
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: DateTime<Utc>,
+    pub runtime_milliseconds: u64,
+    /// number of executions including retries
+    pub attempts: u32,
+    /// error of the last execution including all its causes
+    pub error: Option<String>,
+    pub custom_stats: Option<serde_json::Value>,
+    /// outcome of every item, only set for fan-out tasks
+    pub items: Option<FanOutResult>,
+}
+
+impl TaskReport {
+    pub async fn new(name: &str, result: &TaskResult) -> Self {
+        let custom_stats = match &result.stats.custom_stats {
+            Some(stats) => Some(custom_stats_to_json(stats).await),
+            None => None,
+        };
+        TaskReport {
+            name: name.to_string(),
+            state: result.state,
+            started_at: result.stats.started_at,
+            finished_at: result.finished_at,
+            runtime_milliseconds: u64::try_from(result.stats.runtime.as_millis())
+                .unwrap_or(u64::MAX),
+            attempts: result.stats.attempts,
+            error: result.stats.error.clone(),
+            custom_stats,
+            items: result.stats.items.as_deref().cloned(),
+        }
+    }
+}
+
+impl RunReport {
+    pub async fn new(pipeline: &str, run_id: Uuid, results: &HashMap<String, TaskResult>) -> Self {
+        let mut tasks = Vec::with_capacity(results.len());
+        for (name, result) in results {
+            tasks.push(TaskReport::new(name, result).await);
+        }
+        tasks.sort_by(|a, b| a.name.cmp(&b.name));
+        RunReport {
+            pipeline: pipeline.to_string(),
+            run_id,
+            tasks,
+        }
+    }
+
+    pub fn failed_tasks(&self) -> Vec<&str> {
+        self.tasks
+            .iter()
+            .filter(|task| task.state == ExecutionState::Failed)
+            .map(|task| task.name.as_str())
+            .collect()
+    }
+
+    /// Cancelled tasks do not count as failure, they were stopped on purpose
+    pub fn has_failures(&self) -> bool {
+        !self.failed_tasks().is_empty()
+    }
+}
+
+/// Formats the report as table, one row per task
+impl Display for RunReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let header = ["TASK", "STATE", "RUNTIME", "ATTEMPTS", "ERROR", "STATS"];
+        let rows: Vec<[String; 6]> = self
+            .tasks
+            .iter()
+            .map(|task| {
+                [
+                    task.name.clone(),
+                    task.state.as_str().to_string(),
+                    format!("{}ms", task.runtime_milliseconds),
+                    task.attempts.to_string(),
+                    task.error.clone().unwrap_or_default(),
+                    task.custom_stats
+                        .as_ref()
+                        .map(|stats| stats.to_string())
+                        .unwrap_or_default(),
+                ]
+            })
+            .collect();
+        let mut widths = header.map(str::len);
+        for row in rows.iter() {
+            for (width, cell) in widths.iter_mut().zip(row.iter()) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        writeln!(f, "Run {} of pipeline {}", self.run_id, self.pipeline)?;
+        let header = header.map(str::to_string);
+        for row in std::iter::once(&header).chain(rows.iter()) {
+            let line = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+                .collect::<Vec<_>>()
+                .join("  ");
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dag_schedule::fan_out::{FanOutResult, ItemFailure};
+    use crate::dag_schedule::report::RunReport;
+    use crate::dag_schedule::schedule::TaskResult;
+    use crate::dag_schedule::task::{ExecutionState, ExecutionStats, StatsMap};
+    use chrono::Utc;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::Mutex;
+    use uuid::Uuid;
+
+    fn result(state: ExecutionState, stats: ExecutionStats) -> TaskResult {
+        TaskResult {
+            state,
+            finished_at: Utc::now(),
+            stats,
+        }
+    }
+
+    #[tokio::test]
+    async fn report_contains_all_tasks_and_detects_failures() {
+        let custom_stats: StatsMap = Arc::new(Mutex::new(HashMap::new()));
+        custom_stats
+            .lock()
+            .await
+            .insert("rows".to_string(), Arc::new(42_usize));
+        let results = HashMap::from([
+            (
+                "collect".to_string(),
+                result(
+                    ExecutionState::Finished,
+                    ExecutionStats {
+                        started_at: Some(Utc::now()),
+                        runtime: Duration::from_millis(1500),
+                        attempts: 2,
+                        custom_stats: Some(custom_stats),
+                        ..Default::default()
+                    },
+                ),
+            ),
+            (
+                "stage".to_string(),
+                result(
+                    ExecutionState::Failed,
+                    ExecutionStats {
+                        is_error: true,
+                        attempts: 1,
+                        error: Some("Database interaction failed".to_string()),
+                        items: Some(Arc::new(FanOutResult {
+                            succeeded: vec!["AAPL".to_string()],
+                            failed: vec![ItemFailure {
+                                item: "MSFT".to_string(),
+                                error: "Database interaction failed".to_string(),
+                            }],
+                            skipped: vec![],
+                        })),
+                        ..Default::default()
+                    },
+                ),
+            ),
+            (
+                "export".to_string(),
+                result(ExecutionState::Cancelled, ExecutionStats::default()),
+            ),
+        ]);
+
+        let report = RunReport::new("daily", Uuid::new_v4(), &results).await;
+
+        let names: Vec<&str> = report.tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["collect", "export", "stage"]);
+        assert_eq!(report.failed_tasks(), vec!["stage"]);
+        assert!(report.has_failures());
+        assert_eq!(report.tasks[0].runtime_milliseconds, 1500);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["tasks"][0]["custom_stats"]["rows"], 42);
+        assert_eq!(json["tasks"][2]["state"], "failed");
+        assert_eq!(json["tasks"][2]["items"]["succeeded"][0], "AAPL");
+        assert_eq!(json["tasks"][2]["items"]["failed"][0]["item"], "MSFT");
+        assert_eq!(json["tasks"][0]["items"], serde_json::Value::Null);
+
+        let table = report.to_string();
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("TASK"));
+        assert!(lines[2].starts_with("collect  finished   1500ms"));
+        assert!(lines[4].contains("Database interaction failed"));
+    }
+
+    #[tokio::test]
+    async fn cancelled_tasks_are_no_failures() {
+        let results = HashMap::from([(
+            "collect".to_string(),
+            result(ExecutionState::Cancelled, ExecutionStats::default()),
+        )]);
+
+        let report = RunReport::new("daily", Uuid::new_v4(), &results).await;
+
+        assert!(!report.has_failures());
+    }
+}
@@ -1,29 +1,115 @@
+use crate::dag_schedule::cadence::Cadence;
+use crate::dag_schedule::concurrency::ConcurrencyLimits;
+use crate::dag_schedule::export::DagGraph;
+use crate::dag_schedule::report::{RunReport, TaskReport};
+use crate::dag_schedule::scheduler::{TaskLocks, DEFAULT_PIPELINE};
 use crate::dag_schedule::task::{
-    CycleCheck, ExecutionMode, ExecutionStats, RetryOptions, Runnable, Task, TaskError, TaskRef,
-    Tools,
+    custom_stats_to_json, error_chain, CycleCheck, ExecutionMode, ExecutionState, ExecutionStats,
+    RetryOptions, Runnable, Task, TaskRef, Tools, Trigger, TriggerRule,
 };
-use std::collections::HashMap;
+use crate::dag_schedule::window::TimeWindow;
+use crate::database::run_history_service::{RunHistoryServiceTrait, TaskRunEntry};
+use crate::database::task_control_service::{TaskControl, TaskControlServiceTrait};
+use crate::utils::notification::{Notification, Notifier};
+use chrono::{DateTime, Utc};
+use priority_queue::PriorityQueue;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::sync::Arc;
-use tokio::sync::mpsc;
-use tracing::{debug, error, Instrument};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn, Instrument};
 use uuid::Uuid;
 
-// todo clean up, exchange unwraps and panic with proper error handling
-// todo introduce schedule error
+// todo clean up, exchange unwraps with proper error handling
 // todo remove prints and use tracing
 // todo think about attributes of schedule and how to design api (see also next todo)
-// todo was renamed to schedule: think about if which functions really need to be used on it and , furthermore introduce a Scheduler which can can create/run multiple schedules by type
+// todo was renamed to schedule: think about which functions really need to be used on it
 pub struct Schedule {
+    name: String,
     source_tasks: Vec<TaskRef>,
     tasks: HashMap<Uuid, TaskRef>,
-    _results: HashMap<Uuid, anyhow::Result<ExecutionStats, TaskError>>,
+    results: HashMap<String, TaskResult>,
+    run_id: Uuid,
+    run_started_at: DateTime<Utc>,
+    run_history: Option<Arc<dyn RunHistoryServiceTrait>>,
+    updates_freshness: bool,
+    concurrency: ConcurrencyLimits,
+    cancel: CancellationToken,
     num_reachable_tasks: usize,
     num_tasks: usize,
+    checked: bool,
+    task_groups: TaskGroups,
+    task_locks: Option<TaskLocks>,
+    task_events: Option<mpsc::Receiver<String>>,
+    /// set for runs of the daemon which only execute the tasks triggered by events and their
+    /// descendants, these partial runs are not recorded in the run history
+    only_triggered: bool,
+    notifier: Notifier,
+    task_controls: Option<Arc<dyn TaskControlServiceTrait>>,
+    /// tasks whose control was already loaded in the current run
+    loaded_controls: HashSet<Uuid>,
     // trigger_receiver: Option<mpsc::Receiver<(bool, Vec<TaskRef>)>>,
 }
 
+/// Final state of a task in the last run of the schedule
+#[derive(Clone, Debug)]
+pub struct TaskResult {
+    pub state: ExecutionState,
+    pub finished_at: DateTime<Utc>,
+    pub stats: ExecutionStats,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum ScheduleError {
+    #[error("No task without dependencies exists to start the schedule")]
+    NoSourceTasks,
+    #[error("Cycle detected: {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+    #[error("Tasks cannot be reached from any task without dependencies: {}", .0.join(", "))]
+    UnreachableTasks(Vec<String>),
+    #[error("Task {task} depends on unknown task {dependency}")]
+    UnknownDependency { task: String, dependency: String },
+    #[error("Task {0} has no task setting")]
+    MissingTaskSetting(String),
+    #[error(
+        "Trigger rule of task {task} refers to {dependency}, which is not one of its dependencies"
+    )]
+    UnknownTriggerRuleDependency { task: String, dependency: String },
+    #[error("Task group {0} has the same name as a task")]
+    AmbiguousTaskGroup(String),
+    #[error("Task {task} uses unknown resource pool {resource}")]
+    UnknownResource { task: String, resource: String },
+    #[error("Event {event} triggers unknown task {task}")]
+    UnknownTriggeredTask { event: String, task: String },
+    #[error("Schedule must be checked successfully before running it")]
+    NotChecked,
+    #[error("Schedule has {} problems: {}", .0.len(), .0.iter().map(|p| p.to_string()).collect::<Vec<_>>().join("; "))]
+    Multiple(Vec<ScheduleError>),
+}
+
+impl ScheduleError {
+    /// combines all problems into a single error, returns Ok if there is none
+    pub fn from_problems(mut problems: Vec<ScheduleError>) -> Result<(), ScheduleError> {
+        match problems.len() {
+            0 => Ok(()),
+            1 => Err(problems.remove(0)),
+            _ => Err(ScheduleError::Multiple(problems)),
+        }
+    }
+
+    /// splits the error into its single problems
+    pub fn into_problems(self) -> Vec<ScheduleError> {
+        match self {
+            ScheduleError::Multiple(problems) => problems,
+            problem => vec![problem],
+        }
+    }
+}
+
 pub type TaskSpecRef = Arc<TaskSpec>;
 
 #[derive(Debug)]
@@ -32,6 +118,19 @@ pub struct TaskSpec {
     pub name: String,
     pub retry_options: RetryOptions,
     pub execution_mode: ExecutionMode,
+    pub cadence: Option<Cadence>,
+    /// trigger rules by name of the dependency, dependencies without rule use the default rule
+    pub trigger_rules: HashMap<String, TriggerRule>,
+    /// resource pools of which the task needs a free slot to be started
+    pub resources: Vec<String>,
+    /// maximum runtime of a single execution including retries
+    pub timeout: Option<Duration>,
+    /// tasks with higher priority are started first if several tasks are ready at once
+    pub priority: i32,
+    /// daily time window in which the task is started and has to finish
+    pub window: TimeWindow,
+    /// the task is not executed while its last success lies within the given duration
+    pub freshness: Option<Duration>,
     pub tools: Tools,
     pub runnable: Arc<dyn Runnable>,
 }
@@ -56,11 +155,61 @@ impl TaskSpec {
             name,
             retry_options,
             execution_mode,
+            cadence: None,
+            trigger_rules: HashMap::new(),
+            resources: Vec::new(),
+            timeout: None,
+            priority: 0,
+            window: TimeWindow::default(),
+            freshness: None,
             tools,
             runnable,
         }
     }
 
+    /// sets the rules deciding on which final states of its dependencies the task is executed
+    pub fn with_trigger_rules(mut self, trigger_rules: HashMap<String, TriggerRule>) -> TaskSpec {
+        self.trigger_rules = trigger_rules;
+        self
+    }
+
+    /// sets the cadence at which the task is re-executed when the schedule runs as daemon
+    pub fn with_cadence(mut self, cadence: Option<Cadence>) -> TaskSpec {
+        self.cadence = cadence;
+        self
+    }
+
+    /// sets the resource pools limiting how many tasks using them can run at the same time
+    pub fn with_resources(mut self, resources: Vec<String>) -> TaskSpec {
+        self.resources = resources;
+        self
+    }
+
+    /// sets the maximum runtime of a single execution of the task
+    pub fn with_timeout(mut self, timeout: Option<Duration>) -> TaskSpec {
+        self.timeout = timeout;
+        self
+    }
+
+    /// sets the priority deciding which of several ready tasks is started first
+    pub fn with_priority(mut self, priority: i32) -> TaskSpec {
+        self.priority = priority;
+        self
+    }
+
+    /// sets the daily time window before which the task is not started and after which it is cancelled
+    pub fn with_window(mut self, window: TimeWindow) -> TaskSpec {
+        self.window = window;
+        self
+    }
+
+    /// skips the task while its last success lies within the given duration, the outgoing tasks
+    /// continue as if it succeeded
+    pub fn with_freshness(mut self, freshness: Option<Duration>) -> TaskSpec {
+        self.freshness = freshness;
+        self
+    }
+
     pub fn get_uuid(&self) -> Uuid {
         self.id
     }
@@ -82,6 +231,9 @@ impl fmt::Display for TaskSpec {
 
 pub type TaskDependenciesSpecs = HashMap<TaskSpecRef, Vec<TaskSpecRef>>;
 
+/// Named groups of tasks by group name, e.g. all tasks of one data provider
+pub type TaskGroups = HashMap<String, Vec<String>>;
+
 impl Default for Schedule {
     fn default() -> Self {
         Schedule::new()
@@ -92,56 +244,207 @@ impl Schedule {
     #[allow(clippy::mutable_key_type)]
     pub fn new() -> Self {
         Schedule {
+            name: DEFAULT_PIPELINE.to_string(),
             source_tasks: Default::default(),
             tasks: Default::default(),
-            _results: Default::default(),
+            results: Default::default(),
+            run_id: Uuid::nil(),
+            run_started_at: Utc::now(),
+            run_history: None,
+            updates_freshness: true,
+            concurrency: Default::default(),
+            cancel: CancellationToken::new(),
             num_reachable_tasks: 0,
             num_tasks: 0,
+            checked: false,
+            task_groups: Default::default(),
+            task_locks: None,
+            task_events: None,
+            only_triggered: false,
+            notifier: Notifier::default(),
+            task_controls: None,
+            loaded_controls: Default::default(),
             // trigger_receiver: None,
         }
     }
 
-    #[allow(clippy::mutable_key_type)]
-    #[tracing::instrument(level = "debug", skip_all)]
-    async fn create_schedule(
-        &mut self,
-        task_dependencies_specs: TaskDependenciesSpecs,
-    ) -> HashMap<Uuid, TaskRef> {
-        let tasks_map = self.create_tasks_from_specs(&task_dependencies_specs).await;
-        self.add_outgoing_tasks_to_tasks_in_map(&task_dependencies_specs, &tasks_map)
-            .await;
-        tasks_map
+    /// name of the pipeline the schedule belongs to, used to tell its runs apart in the run history
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+
+    /// Locks shared with the schedules of other pipelines, a task waits with its execution
+    /// until it finished in all other schedules
+    pub fn with_task_locks(mut self, task_locks: TaskLocks) -> Self {
+        self.task_locks = Some(task_locks);
+        self
+    }
+
+    /// persists the results of every run of the schedule
+    pub fn with_run_history(mut self, run_history: Arc<dyn RunHistoryServiceTrait>) -> Self {
+        self.run_history = Some(run_history);
+        self
+    }
+
+    /// Successful tasks become fresh in the run history unless disabled, e.g. for a backfill
+    /// which only reloads past data
+    pub fn with_freshness_updates(mut self, updates_freshness: bool) -> Self {
+        self.updates_freshness = updates_freshness;
+        self
+    }
+
+    /// limits the number of tasks running at the same time
+    pub fn with_concurrency_limits(mut self, concurrency: ConcurrencyLimits) -> Self {
+        self.concurrency = concurrency;
+        self
     }
 
-    // todo proper error handling and check preconditions before running (e.g tasks must be scheduled)
+    /// Cancelling the token cancels all running tasks and prevents starting further tasks.
+    /// Must be set before the tasks are scheduled.
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// names of tasks the daemon executes as soon as they are received, see [`Schedule::run_daemon`]
+    pub fn with_task_events(mut self, task_events: mpsc::Receiver<String>) -> Self {
+        self.task_events = Some(task_events);
+        self
+    }
+
+    /// notified about failed tasks and completed runs
+    pub fn with_notifier(mut self, notifier: Notifier) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    /// Controls set by operators, loaded once per run before a task is started. A forced run is
+    /// consumed when the task is dispatched for execution, a paused task keeps it.
+    pub fn with_task_controls(mut self, task_controls: Arc<dyn TaskControlServiceTrait>) -> Self {
+        self.task_controls = Some(task_controls);
+        self
+    }
+
+    /// groups whose members are reported as a single node in the run summary
+    pub fn with_task_groups(mut self, task_groups: TaskGroups) -> Self {
+        self.task_groups = task_groups;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// id of the current or last run of the schedule
+    pub fn run_id(&self) -> Uuid {
+        self.run_id
+    }
+
+    /// results of all tasks which reached a final state in the current or last run, by task name
+    pub fn results(&self) -> &HashMap<String, TaskResult> {
+        &self.results
+    }
+
+    /// snapshot of the scheduled tasks and their dependencies without executing any task,
+    /// tasks without a result in the current or last run are pending
+    pub async fn graph(&self) -> DagGraph {
+        let mut graph = DagGraph::default();
+        for task in self.tasks.values() {
+            let locked_task = task.lock().await;
+            let state = self
+                .results
+                .get(&locked_task.name)
+                .map(|result| result.state)
+                .unwrap_or(ExecutionState::Pending);
+            graph.tasks.push((locked_task.name.clone(), state));
+            for outgoing_task in locked_task.outgoing_tasks.iter() {
+                let locked_outgoing = outgoing_task.lock().await;
+                let rule = locked_outgoing
+                    .trigger_rules
+                    .get(&locked_task.name)
+                    .copied()
+                    .unwrap_or_default();
+                graph.dependencies.push((
+                    locked_task.name.clone(),
+                    locked_outgoing.name.clone(),
+                    rule,
+                ));
+            }
+        }
+        graph.tasks.sort_by(|a, b| a.0.cmp(&b.0));
+        graph
+            .dependencies
+            .sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+        graph
+    }
+
+    /// checks the scheduled tasks and returns all problems found which prevent running the schedule
     #[tracing::instrument(skip(self))]
-    pub async fn run_checks(&mut self) {
-        self.check_if_source_tasks_exists().await;
-        self.check_for_cycles_from_sources().await;
+    pub async fn run_checks(&mut self) -> Result<(), ScheduleError> {
+        let mut problems = vec![];
+        if let Err(e) = self.check_if_source_tasks_exists().await {
+            problems.push(e);
+        }
+        problems.extend(self.check_resources().await);
+        problems.extend(self.check_for_cycles_from_sources().await);
         // must run last because dependent on cycle check
-        self.check_if_all_tasks_are_reachable().await;
+        if let Err(e) = self.check_if_all_tasks_are_reachable().await {
+            problems.push(e);
+        }
+        self.checked = problems.is_empty();
+        ScheduleError::from_problems(problems)
     }
+
     #[tracing::instrument(level = "debug", skip(self))]
-    async fn check_if_source_tasks_exists(&self) {
+    async fn check_resources(&self) -> Vec<ScheduleError> {
+        let mut problems = vec![];
+        for task in self.tasks.values() {
+            let locked_task = task.lock().await;
+            for resource in locked_task.resources.iter() {
+                if !self.concurrency.has_pool(resource) {
+                    problems.push(ScheduleError::UnknownResource {
+                        task: locked_task.name.clone(),
+                        resource: resource.clone(),
+                    });
+                }
+            }
+        }
+        problems.sort_by_key(|p| p.to_string());
+        problems
+    }
+
+    #[tracing::instrument(level = "debug", skip(self))]
+    async fn check_if_source_tasks_exists(&self) -> Result<(), ScheduleError> {
         if self.source_tasks.is_empty() {
-            panic!("Not all tasks are reachable")
+            return Err(ScheduleError::NoSourceTasks);
         }
+        Ok(())
     }
+
     #[tracing::instrument(level = "debug", skip(self))]
-    async fn check_if_all_tasks_are_reachable(&self) {
-        if self.num_reachable_tasks != self.num_tasks {
-            panic!(
-                "Not all tasks are reachable. Only {} of {} can be reached",
-                self.num_reachable_tasks, self.num_tasks
-            )
+    async fn check_if_all_tasks_are_reachable(&self) -> Result<(), ScheduleError> {
+        if self.num_reachable_tasks == self.num_tasks {
+            return Ok(());
         }
+        let mut unreachable_tasks = vec![];
+        for task in self.tasks.values() {
+            let locked_task = task.lock().await;
+            if locked_task.cycle_check != CycleCheck::Finished {
+                unreachable_tasks.push(locked_task.name.clone());
+            }
+        }
+        unreachable_tasks.sort();
+        Err(ScheduleError::UnreachableTasks(unreachable_tasks))
     }
 
     /// uses an iterative dfs starting with the source tasks to identify cycles
     /// Visited counts the allowed visits for a node in case that the
-    /// node should be executed multiple times repeated
+    /// node should be executed multiple times repeated.
+    /// Returns a problem for each detected cycle, containing the names of the tasks in the cycle
     #[tracing::instrument(level = "debug", skip(self))]
-    async fn check_for_cycles_from_sources(&mut self) {
+    async fn check_for_cycles_from_sources(&mut self) -> Vec<ScheduleError> {
+        let mut cycles = vec![];
         let mut task_stack = self.source_tasks.clone();
         while let Some(t) = task_stack.last().cloned() {
             let mut l_t = t.lock().await;
@@ -152,17 +455,26 @@ impl Schedule {
                     };
                     let outs = l_t.outgoing_tasks.clone();
                     for out_t in outs {
-                        match out_t.lock().await.cycle_check {
+                        // a task depending on itself cannot be locked a second time
+                        let out_cycle_check = if Arc::ptr_eq(&out_t, &t) {
+                            CycleCheck::Visited { max_allowed: 0 }
+                        } else {
+                            out_t.lock().await.cycle_check
+                        };
+                        match out_cycle_check {
                             CycleCheck::Unknown => {
                                 task_stack.push(out_t.clone());
                             }
                             CycleCheck::Visited { max_allowed } => {
                                 // todo use repeat in task creation and add the tasks to its own adj list then introduce the check
                                 if max_allowed == 0 {
-                                    error!("cycle detected");
-                                    panic!("cycle detected")
+                                    let cycle =
+                                        Self::cycle_path(&task_stack, &t, &l_t.name, &out_t).await;
+                                    error!("cycle detected: {}", cycle.join(" -> "));
+                                    cycles.push(ScheduleError::Cycle(cycle));
+                                    continue;
                                 }
-                                t.lock().await.cycle_check = CycleCheck::Visited {
+                                l_t.cycle_check = CycleCheck::Visited {
                                     max_allowed: max_allowed.saturating_sub(1),
                                 }
                             }
@@ -180,6 +492,42 @@ impl Schedule {
                 }
             }
         }
+        cycles
+    }
+
+    /// The visited tasks on the dfs stack form the current path, the cycle is the part of the path
+    /// starting at the task which was visited again. The current task is already locked,
+    /// so its name is passed separately.
+    async fn cycle_path(
+        task_stack: &[TaskRef],
+        current_task: &TaskRef,
+        current_task_name: &str,
+        revisited_task: &TaskRef,
+    ) -> Vec<String> {
+        let mut path: Vec<(TaskRef, String)> = vec![];
+        for task in task_stack {
+            let name = if Arc::ptr_eq(task, current_task) {
+                current_task_name.to_string()
+            } else {
+                let locked_task = task.lock().await;
+                if !matches!(locked_task.cycle_check, CycleCheck::Visited { .. }) {
+                    continue;
+                }
+                locked_task.name.clone()
+            };
+            // a task can be on the stack multiple times, only its latest position is on the path
+            path.retain(|(t, _)| !Arc::ptr_eq(t, task));
+            path.push((task.clone(), name));
+        }
+        let start = path
+            .iter()
+            .position(|(t, _)| Arc::ptr_eq(t, revisited_task))
+            .unwrap_or_default();
+        let mut cycle: Vec<String> = path.into_iter().skip(start).map(|(_, n)| n).collect();
+        if let Some(first) = cycle.first().cloned() {
+            cycle.push(first);
+        }
+        cycle
     }
 
     /// creates TaskRef from TaskDependenciesSpecs,
@@ -194,8 +542,11 @@ impl Schedule {
         let mut tasks_map: HashMap<Uuid, TaskRef> = HashMap::new();
         for (task_spec, dependencies) in specs {
             let task = Task::new_from_spec(task_spec.clone());
+            task.lock().await.cancel = self.cancel.clone();
             if !dependencies.is_empty() {
-                task.lock().await.num_ingoing_tasks = Some(dependencies.len());
+                let mut locked_task = task.lock().await;
+                locked_task.num_ingoing_tasks = Some(dependencies.len());
+                locked_task.num_dependencies = Some(dependencies.len());
             } else {
                 self.source_tasks.push(task.clone())
             }
@@ -214,84 +565,584 @@ impl Schedule {
         &self,
         specs: &TaskDependenciesSpecs,
         tasks_map: &HashMap<Uuid, TaskRef>,
-    ) {
+    ) -> Result<(), ScheduleError> {
+        let mut problems = vec![];
         for (task_spec, dependencies) in specs {
             if let Some(outgoing_task) = tasks_map.get(&task_spec.id) {
                 for dep_task_spec in dependencies {
                     if let Some(task) = tasks_map.get(&dep_task_spec.id) {
                         let mut locked_task = task.lock().await;
                         locked_task.outgoing_tasks.push(outgoing_task.clone());
+                    } else {
+                        problems.push(ScheduleError::UnknownDependency {
+                            task: task_spec.name.clone(),
+                            dependency: dep_task_spec.name.clone(),
+                        });
                     }
                 }
             }
         }
+        ScheduleError::from_problems(problems)
     }
 
+    /// creates the tasks and their dependencies from the specs,
+    /// fails if a dependency is not part of the specs itself
     #[allow(clippy::mutable_key_type)]
     #[tracing::instrument(level = "debug", skip(self))]
-    pub async fn schedule_tasks(&mut self, task_dependencies_specs: TaskDependenciesSpecs) {
-        self.tasks = self.create_schedule(task_dependencies_specs).await;
+    pub async fn schedule_tasks(
+        &mut self,
+        task_dependencies_specs: TaskDependenciesSpecs,
+    ) -> Result<(), ScheduleError> {
+        self.checked = false;
+        self.tasks = self.create_tasks_from_specs(&task_dependencies_specs).await;
+        self.add_outgoing_tasks_to_tasks_in_map(&task_dependencies_specs, &self.tasks)
+            .await
     }
 
-    #[tracing::instrument(skip(self))]
-    pub async fn run_schedule(&mut self) {
+    /// Runs all tasks of the schedule once, the report contains the final state of every task
+    #[tracing::instrument(skip(self), fields(pipeline = %self.name))]
+    pub async fn run_schedule(&mut self) -> Result<RunReport, ScheduleError> {
+        if !self.checked {
+            return Err(ScheduleError::NotChecked);
+        }
+        self.start_run().await;
         let (trigger_sender, mut trigger_receiver) = mpsc::channel(100);
+        // triggers of skipped tasks are handled directly without sending them through the channel
+        let mut skipped_triggers: VecDeque<Trigger> = VecDeque::new();
+        // tasks without remaining dependencies waiting for a free slot
+        let mut ready_tasks = ReadyQueue::default();
 
-        self.start_source_tasks(trigger_sender.clone()).await;
+        self.queue_source_tasks(&mut ready_tasks).await?;
+        self.dispatch_ready_tasks(&mut ready_tasks, trigger_sender.clone())
+            .await;
 
         // handle received finished triggers from tasks
-        for _ in 0..self.num_reachable_tasks {
-            if let Some(msg) = trigger_receiver.recv().await {
-                let (_result, tasks) = msg;
-                debug!("number received next tasks: {}", tasks.len());
-                self.start_outgoing_tasks(&tasks, trigger_sender.clone())
+        let mut num_finished_tasks = 0;
+        while num_finished_tasks < self.num_reachable_tasks {
+            let trigger = match (
+                skipped_triggers.pop_front(),
+                ready_tasks.next_window_opening(),
+            ) {
+                (Some(trigger), _) => Some(trigger),
+                (None, None) => trigger_receiver.recv().await,
+                // wake up when the window of a delayed task opens, release all if cancelled
+                (None, Some(opens_at)) => {
+                    let until_open = (opens_at - Utc::now()).to_std().unwrap_or_default();
+                    tokio::select! {
+                        trigger = trigger_receiver.recv() => trigger,
+                        _ = tokio::time::sleep(until_open) => {
+                            ready_tasks.release_delayed(Utc::now());
+                            self.dispatch_ready_tasks(&mut ready_tasks, trigger_sender.clone())
+                                .await;
+                            continue;
+                        }
+                        _ = self.cancel.cancelled() => {
+                            ready_tasks.release_delayed(DateTime::<Utc>::MAX_UTC);
+                            self.dispatch_ready_tasks(&mut ready_tasks, trigger_sender.clone())
+                                .await;
+                            continue;
+                        }
+                    }
+                }
+            };
+            num_finished_tasks += 1;
+            if let Some(trigger) = trigger {
+                debug!(
+                    "task {} reached state {:?}, number received next tasks: {}",
+                    trigger.task_name,
+                    trigger.state,
+                    trigger.next_tasks.len()
+                );
+                self.record_task_result(&trigger).await;
+                self.concurrency.release(&trigger.task_name);
+                self.queue_outgoing_tasks(&trigger, &mut ready_tasks, &mut skipped_triggers)
                     .await;
+                self.dispatch_ready_tasks(&mut ready_tasks, trigger_sender.clone())
+                    .await;
+            }
+        }
+        self.log_run_summary().await;
+        self.finish_run().await;
+        let report = RunReport::new(&self.name, self.run_id, &self.results).await;
+        self.notifier
+            .notify(&Notification::ScheduleCompleted {
+                report: report.clone(),
+            })
+            .await;
+        Ok(report)
+    }
+
+    /// run history the current run is recorded in, a resumed run continues the last recorded run
+    fn recorded_run_history(&self) -> Option<&Arc<dyn RunHistoryServiceTrait>> {
+        self.run_history.as_ref().filter(|_| !self.only_triggered)
+    }
+
+    async fn start_run(&mut self) {
+        self.run_id = Uuid::new_v4();
+        self.run_started_at = Utc::now();
+        self.results.clear();
+        self.loaded_controls.clear();
+        info!(
+            "Start schedule run {} of pipeline {}",
+            self.run_id, self.name
+        );
+        if let Some(run_history) = self.recorded_run_history() {
+            if let Err(e) = run_history
+                .start_schedule_run(self.run_id, &self.name, self.run_started_at)
+                .await
+            {
+                warn!("Failed to persist start of schedule run: {:#}", e);
+            }
+        }
+        self.load_last_successes().await;
+    }
+
+    /// Takes the last successes of the tasks with freshness from the run history,
+    /// so that successes of previous processes count as well
+    async fn load_last_successes(&self) {
+        let Some(run_history) = &self.run_history else {
+            return;
+        };
+        let mut task_names = Vec::new();
+        for task in self.tasks.values() {
+            let task = task.lock().await;
+            if task.freshness.is_some() {
+                task_names.push(task.name.clone());
+            }
+        }
+        if task_names.is_empty() {
+            return;
+        }
+        match run_history.get_last_successes(&task_names).await {
+            Ok(last_successes) => {
+                for task in self.tasks.values() {
+                    let mut task = task.lock().await;
+                    if let Some(succeeded_at) = last_successes.get(&task.name) {
+                        task.last_succeeded_at = task.last_succeeded_at.max(Some(*succeeded_at));
+                    }
+                }
+            }
+            Err(e) => warn!("Failed to load last successes of tasks: {:#}", e),
+        }
+    }
+
+    async fn finish_run(&self) {
+        if let Some(run_history) = self.recorded_run_history() {
+            if let Err(e) = run_history
+                .finish_schedule_run(self.run_id, Utc::now())
+                .await
+            {
+                warn!("Failed to persist end of schedule run: {:#}", e);
+            }
+        }
+    }
+
+    /// Resumes a previous run: tasks which finished in that run are not executed again,
+    /// unless one of their ancestors has to be executed. All other tasks run as usual.
+    #[tracing::instrument(skip_all)]
+    pub async fn resume(&mut self, finished_tasks: &HashSet<String>) {
+        // all tasks which did not finish and their descendants have to be executed again
+        let mut unfinished: Vec<TaskRef> = Vec::new();
+        for task in self.tasks.values() {
+            if !finished_tasks.contains(&task.lock().await.name) {
+                unfinished.push(task.clone());
+            }
+        }
+        let to_execute = Self::with_descendants(unfinished).await;
+
+        for (id, task) in self.tasks.iter() {
+            let mut locked_task = task.lock().await;
+            locked_task.finished_in_resumed_run = !to_execute.contains(id);
+            if locked_task.finished_in_resumed_run {
+                info!(
+                    "Task {} already finished, it is not executed again",
+                    locked_task.name
+                );
+            }
+        }
+    }
+
+    /// ids of the given tasks and all tasks depending on them
+    async fn with_descendants(tasks: Vec<TaskRef>) -> HashSet<Uuid> {
+        let mut ids: HashSet<Uuid> = HashSet::new();
+        let mut stack = tasks;
+        while let Some(task) = stack.pop() {
+            let locked_task = task.lock().await;
+            if ids.insert(locked_task.id) {
+                stack.extend(locked_task.outgoing_tasks.iter().cloned());
+            }
+        }
+        ids
+    }
+
+    /// Makes the triggered tasks due regardless of their cadence. If `only_triggered` is set,
+    /// all other tasks except the descendants of the triggered tasks are not executed in the next run.
+    async fn trigger_tasks(&mut self, task_names: &HashSet<String>, only_triggered: bool) {
+        self.only_triggered = only_triggered;
+        let mut triggered: Vec<TaskRef> = Vec::new();
+        for task in self.tasks.values() {
+            let mut locked_task = task.lock().await;
+            if task_names.contains(&locked_task.name) {
+                locked_task.next_due = None;
+                triggered.push(task.clone());
             }
         }
+        if !only_triggered {
+            return;
+        }
+        let to_execute = Self::with_descendants(triggered).await;
+        for (id, task) in self.tasks.iter() {
+            task.lock().await.not_triggered = !to_execute.contains(id);
+        }
+    }
+
+    /// keeps the final state of the task, persists it in the run history and notifies about failures
+    async fn record_task_result(&mut self, trigger: &Trigger) {
+        let result = TaskResult {
+            state: trigger.state,
+            finished_at: Utc::now(),
+            stats: trigger.stats.clone(),
+        };
+        if let Some(run_history) = self.recorded_run_history() {
+            let custom_stats = match &result.stats.custom_stats {
+                Some(stats) => Some(custom_stats_to_json(stats).await),
+                None => None,
+            };
+            let entry = TaskRunEntry {
+                run_id: self.run_id,
+                task_name: trigger.task_name.clone(),
+                started_at: result.stats.started_at,
+                finished_at: result.finished_at,
+                state: result.state,
+                attempts: result.stats.attempts,
+                error: result.stats.error.clone(),
+                custom_stats,
+            };
+            if let Err(e) = run_history
+                .save_task_run(&entry, self.updates_freshness)
+                .await
+            {
+                warn!(
+                    "Failed to persist result of task {}: {:#}",
+                    trigger.task_name, e
+                );
+            }
+        }
+        if result.state == ExecutionState::Failed {
+            self.notifier
+                .notify(&Notification::TaskFailed {
+                    pipeline: self.name.clone(),
+                    run_id: self.run_id,
+                    task: TaskReport::new(&trigger.task_name, &result).await,
+                })
+                .await;
+        }
+        self.results.insert(trigger.task_name.clone(), result);
+    }
+
+    /// logs the final state of all tasks of the last run, members of a task group are collapsed
+    /// into the group
+    async fn log_run_summary(&self) {
+        let mut summary: HashMap<ExecutionState, Vec<String>> = HashMap::new();
+        for (name, state) in self.graph().await.collapse_groups(&self.task_groups).tasks {
+            summary.entry(state).or_default().push(name);
+        }
+        for (state, names) in summary {
+            info!("{:?} tasks ({}): {}", state, names.len(), names.join(", "));
+        }
     }
-    #[tracing::instrument(skip(self, trigger_sender))]
-    async fn start_source_tasks(&self, trigger_sender: mpsc::Sender<(bool, Vec<TaskRef>)>) {
+
+    /// Keeps the schedule alive and re-runs it whenever the cadence of a task is due.
+    /// The whole schedule is executed once on start, afterwards only due tasks are executed,
+    /// tasks which are not due just pass the trigger to their outgoing tasks.
+    /// Task events, e.g. new raw data for a stager, re-run the schedule too: only the received
+    /// tasks and their descendants are executed, unless the cadence of a task is due at the same time.
+    /// Such partial runs are not recorded in the run history, events of unknown tasks are ignored.
+    /// Returns if no task has a cadence and no task events are received, if a stop signal was received
+    /// or if the schedule was cancelled.
+    #[tracing::instrument(skip_all, fields(pipeline = %self.name))]
+    pub async fn run_daemon(
+        &mut self,
+        mut stop: broadcast::Receiver<()>,
+    ) -> Result<(), ScheduleError> {
+        let mut task_names: HashSet<String> = HashSet::new();
+        for task in self.tasks.values() {
+            task_names.insert(task.lock().await.name.clone());
+        }
+        loop {
+            self.run_schedule().await?;
+            if self.cancel.is_cancelled() {
+                info!("Schedule was cancelled, stop daemon");
+                return Ok(());
+            }
+            let next_trigger = self.next_trigger_time().await;
+            let mut triggered: HashSet<String> = HashSet::new();
+            let cadence_due = loop {
+                if next_trigger.is_none() && self.task_events.is_none() {
+                    info!("No recurring tasks scheduled, stop daemon");
+                    return Ok(());
+                }
+                if let Some(next_trigger) = next_trigger {
+                    info!("Next schedule run at {}", next_trigger);
+                }
+                let wait = next_trigger.map(|t| (t - Utc::now()).to_std().unwrap_or_default());
+                tokio::select! {
+                    _ = sleep_or_wait_forever(wait) => break true,
+                    task_name = receive_task_event(&mut self.task_events) => match task_name {
+                        Some(task_name) if task_names.contains(&task_name) => {
+                            triggered.insert(task_name);
+                            break false;
+                        }
+                        // no run without any task to execute
+                        Some(task_name) => {
+                            debug!("Task {} of event is not part of the schedule", task_name)
+                        }
+                        None => self.task_events = None,
+                    },
+                    _ = stop.recv() => {
+                        info!("Received stop signal, stop daemon");
+                        return Ok(());
+                    }
+                    _ = self.cancel.cancelled() => {
+                        info!("Schedule was cancelled, stop daemon");
+                        return Ok(());
+                    }
+                }
+            };
+            if let Some(task_events) = self.task_events.as_mut() {
+                while let Ok(task_name) = task_events.try_recv() {
+                    if task_names.contains(&task_name) {
+                        triggered.insert(task_name);
+                    }
+                }
+            }
+            self.reset_tasks().await;
+            self.only_triggered = false;
+            if !triggered.is_empty() {
+                info!(
+                    "Run tasks triggered by events: {}",
+                    triggered.iter().cloned().collect::<Vec<_>>().join(", ")
+                );
+                self.trigger_tasks(&triggered, !cadence_due).await;
+            }
+        }
+    }
+
+    /// earliest point in time at which a task with cadence is due again
+    async fn next_trigger_time(&self) -> Option<DateTime<Utc>> {
+        let mut next_trigger: Option<DateTime<Utc>> = None;
+        for task in self.tasks.values() {
+            if let Some(next_due) = task.lock().await.next_due {
+                next_trigger = Some(next_trigger.map_or(next_due, |t| t.min(next_due)));
+            }
+        }
+        next_trigger
+    }
+
+    async fn reset_tasks(&self) {
+        for task in self.tasks.values() {
+            task.lock().await.reset();
+        }
+    }
+
+    #[tracing::instrument(skip_all)]
+    async fn queue_source_tasks(&self, ready_tasks: &mut ReadyQueue) -> Result<(), ScheduleError> {
         if self.source_tasks.is_empty() {
             error!("No source tasks defined");
-            panic!("No source tasks defined")
+            return Err(ScheduleError::NoSourceTasks);
         }
         for task in self.source_tasks.iter() {
-            let trigger_sender = trigger_sender.clone();
-            let task = task.clone();
-            let span = tracing::Span::current();
-            tokio::spawn(async move {
-                let mut task = task.lock().await;
-                task.run(trigger_sender).instrument(span).await.unwrap();
-            });
+            let locked_task = task.lock().await;
+            ready_tasks.push(locked_task.id, locked_task.priority);
         }
+        Ok(())
     }
 
-    #[tracing::instrument(skip(self, trigger_sender))]
-    async fn start_outgoing_tasks(
+    /// Registers the finished task and its output at its outgoing tasks and queues all tasks
+    /// without remaining dependencies. Tasks with unsatisfied trigger rules are skipped,
+    /// their triggers are added to the skipped triggers to cascade through the schedule.
+    #[tracing::instrument(skip_all)]
+    async fn queue_outgoing_tasks(
         &self,
-        tasks: &Vec<TaskRef>,
-        trigger_sender: mpsc::Sender<(bool, Vec<TaskRef>)>,
+        trigger: &Trigger,
+        ready_tasks: &mut ReadyQueue,
+        skipped_triggers: &mut VecDeque<Trigger>,
     ) {
-        for task in tasks {
-            // different scope for locking task and updating remaining incoming tasks
+        for task in &trigger.next_tasks {
+            let mut locked_task = task.lock().await;
+            if let Some(output) = &trigger.output {
+                locked_task
+                    .upstream_outputs
+                    .insert(trigger.task_name.clone(), output.clone());
+            }
+            if !locked_task
+                .register_finished_dependency(&trigger.task_name, trigger.dependency_state())
             {
+                continue;
+            }
+            if !locked_task.dependencies_satisfied {
+                info!("Skip task {}", locked_task.name);
+                skipped_triggers.push_back(locked_task.skip());
+                continue;
+            }
+            ready_tasks.push(locked_task.id, locked_task.priority);
+        }
+    }
+
+    /// Starts the ready tasks by priority as long as the concurrency limits have a free slot,
+    /// the other tasks keep waiting in the queue until running tasks finish
+    #[tracing::instrument(skip_all)]
+    async fn dispatch_ready_tasks(
+        &mut self,
+        ready_tasks: &mut ReadyQueue,
+        trigger_sender: mpsc::Sender<Trigger>,
+    ) {
+        let mut waiting_tasks = Vec::new();
+        while let Some((id, rank)) = ready_tasks.pop() {
+            let Some(task) = self.tasks.get(&id).cloned() else {
+                continue;
+            };
+            // a task waiting for its window or a slot keeps the control loaded on its first dispatch
+            if let Some(task_controls) = &self.task_controls {
+                if self.loaded_controls.insert(id) {
+                    let name = task.lock().await.name.clone();
+                    let control = load_task_control(task_controls.as_ref(), &name).await;
+                    task.lock().await.control = control;
+                }
+            }
+            let (name, resources, forced) = {
                 let mut locked_task = task.lock().await;
-                locked_task.num_ingoing_tasks =
-                    locked_task.num_ingoing_tasks.map(|i| i.saturating_sub(1));
+                let now = Utc::now();
+                if let Some(opens_at) = locked_task.window_opens_at(now) {
+                    info!(
+                        "Task {} waits for its time window opening at {}",
+                        locked_task.name, opens_at
+                    );
+                    ready_tasks.delay(id, rank, opens_at);
+                    continue;
+                }
+                // taken from the start of the run, a task dispatched late still misses its deadline
+                if locked_task.deadline_at.is_none() {
+                    locked_task.deadline_at =
+                        locked_task.window.deadline_of_run(self.run_started_at);
+                }
+                (
+                    locked_task.name.clone(),
+                    locked_task.resources.clone(),
+                    locked_task.control.force_run && !locked_task.control.is_paused(now),
+                )
+            };
+            if !self.concurrency.try_acquire(&name, &resources) {
+                debug!("Task {} waits for a free slot", name);
+                waiting_tasks.push((id, rank));
+                continue;
             }
-            if let Some(0) = task.lock().await.num_ingoing_tasks {
-                let task = task.clone();
-                let trigger_sender = trigger_sender.clone();
-                let span = tracing::Span::current();
-                tokio::spawn(async move {
-                    task.lock()
-                        .await
-                        .run(trigger_sender)
-                        .instrument(span)
-                        .await
-                        .unwrap();
-                });
+            if let Some(task_controls) = self.task_controls.as_ref().filter(|_| forced) {
+                if let Err(e) = task_controls.clear_force_run(&name).await {
+                    warn!("Failed to clear forced run of task {}: {:#}", name, e);
+                }
             }
+            let trigger_sender = trigger_sender.clone();
+            let span = tracing::Span::current();
+            let task_lock = self.task_locks.as_ref().map(|locks| locks.lock_of(&name));
+            tokio::spawn(async move {
+                // a task which is part of several pipelines waits until it finished in the others
+                let _running = match task_lock {
+                    Some(lock) => Some(match lock.clone().try_lock_owned() {
+                        Ok(guard) => guard,
+                        Err(_) => {
+                            info!("Task {} waits until it finished in another pipeline", name);
+                            lock.lock_owned().await
+                        }
+                    }),
+                    None => None,
+                };
+                let mut task = task.lock().await;
+                if let Err(e) = task.run(trigger_sender).instrument(span).await {
+                    error!("Task {} failed: {}", task.name, error_chain(&e));
+                }
+            });
+        }
+        for (id, rank) in waiting_tasks {
+            ready_tasks.requeue(id, rank);
+        }
+    }
+}
+
+async fn sleep_or_wait_forever(duration: Option<Duration>) {
+    match duration {
+        Some(duration) => tokio::time::sleep(duration).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// control of the task set by an operator, a task whose control cannot be loaded runs as configured
+async fn load_task_control(
+    task_controls: &dyn TaskControlServiceTrait,
+    task_name: &str,
+) -> TaskControl {
+    match task_controls.get_control(task_name).await {
+        Ok(control) => control.unwrap_or_default(),
+        Err(e) => {
+            warn!("Failed to load control of task {}: {:#}", task_name, e);
+            TaskControl::default()
+        }
+    }
+}
+
+/// next received task event, waits forever without task events
+async fn receive_task_event(task_events: &mut Option<mpsc::Receiver<String>>) -> Option<String> {
+    match task_events {
+        Some(task_events) => task_events.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// rank of a ready task: higher priorities first, equal priorities in the order they became ready
+type ReadyRank = (i32, Reverse<u64>);
+
+/// Tasks without remaining dependencies waiting to be started
+#[derive(Default)]
+struct ReadyQueue {
+    queue: PriorityQueue<Uuid, ReadyRank>,
+    sequence: u64,
+    /// tasks waiting for their time window to open
+    delayed: Vec<(DateTime<Utc>, Uuid, ReadyRank)>,
+}
+
+impl ReadyQueue {
+    fn push(&mut self, id: Uuid, priority: i32) {
+        self.queue.push(id, (priority, Reverse(self.sequence)));
+        self.sequence += 1;
+    }
+
+    /// queues a task again with the rank it had before, e.g. if it could not be started
+    fn requeue(&mut self, id: Uuid, rank: ReadyRank) {
+        self.queue.push(id, rank);
+    }
+
+    fn pop(&mut self) -> Option<(Uuid, ReadyRank)> {
+        self.queue.pop()
+    }
+
+    /// holds the task back until its time window opens
+    fn delay(&mut self, id: Uuid, rank: ReadyRank, opens_at: DateTime<Utc>) {
+        self.delayed.push((opens_at, id, rank));
+    }
+
+    fn next_window_opening(&self) -> Option<DateTime<Utc>> {
+        self.delayed.iter().map(|(opens_at, _, _)| *opens_at).min()
+    }
+
+    /// queues all delayed tasks whose window opened until the given time
+    fn release_delayed(&mut self, until: DateTime<Utc>) {
+        let (opened, delayed) = self
+            .delayed
+            .drain(..)
+            .partition(|(opens_at, _, _)| *opens_at <= until);
+        self.delayed = delayed;
+        for (_, id, rank) in opened {
+            self.requeue(id, rank);
         }
     }
 }
@@ -311,17 +1162,36 @@ impl Schedule {
 
 #[cfg(test)]
 mod test {
-    use crate::dag_schedule::schedule::{Schedule, TaskDependenciesSpecs, TaskSpec, TaskSpecRef};
-    use crate::dag_schedule::task::{ExecutionMode, RetryOptions, Runnable, StatsMap};
+    use crate::dag_schedule::cadence::Cadence;
+    use crate::dag_schedule::concurrency::ConcurrencyLimits;
+    use crate::dag_schedule::context::TaskContext;
+    use crate::dag_schedule::schedule::{
+        Schedule, ScheduleError, TaskDependenciesSpecs, TaskSpec, TaskSpecRef,
+    };
+    use crate::dag_schedule::task::{
+        ExecutionMode, ExecutionState, RetryOptions, Runnable, StatsMap, TaskError, TriggerRule,
+    };
+    use crate::dag_schedule::testing::{FakeBehavior, TestDag};
+    use crate::dag_schedule::window::TimeWindow;
+    use crate::database::run_history_service::MockRunHistoryServiceTrait;
+    use crate::database::task_control_service::{MockTaskControlServiceTrait, TaskControl};
+    use crate::utils::notification::{NotificationEvent, NotificationSink, Notifier, SinkFormat};
+    use crate::utils::test_helpers::get_test_client;
     use async_trait::async_trait;
+    use chrono::Utc;
+    use httpmock::Method::POST;
+    use httpmock::MockServer;
     use rand::rngs::OsRng;
     use rand::seq::SliceRandom;
     use rand::Rng;
-    use std::collections::HashMap;
+    use secrecy::Secret;
+    use std::collections::{HashMap, HashSet};
     use std::panic;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Arc;
     use std::time::Duration;
-    use tokio::sync::Mutex;
+    use tokio::sync::{broadcast, mpsc, Mutex};
+    use tokio_util::sync::CancellationToken;
     use uuid::Uuid;
 
     #[tokio::test]
@@ -347,7 +1217,7 @@ mod test {
         tasks_specs.insert(task_specs.get(&4).unwrap().clone(), deps4);
         tasks_specs.insert(task_specs.get(&5).unwrap().clone(), deps5);
 
-        scheduler.schedule_tasks(tasks_specs.clone()).await;
+        scheduler.schedule_tasks(tasks_specs.clone()).await.unwrap();
 
         assert_eq!(scheduler.tasks.len(), 5);
 
@@ -385,11 +1255,11 @@ mod test {
         let task_specs = create_test_task_specs(100);
         let tasks_dep_specs = create_random_task_dependencies(&task_specs, 50);
 
-        scheduler.schedule_tasks(tasks_dep_specs).await;
-        scheduler.run_checks().await;
+        scheduler.schedule_tasks(tasks_dep_specs).await.unwrap();
+        scheduler.run_checks().await.unwrap();
 
         tokio::select! {
-          _ =  scheduler.run_schedule() => {}
+          result =  scheduler.run_schedule() => {assert_eq!(result.unwrap().tasks.len(), 100)}
          _ = tokio::time::sleep(Duration::from_secs(3)) => {
                 panic!("scheduled tasks did not finish in time, maybe (undetected) cycle")
             }
@@ -397,7 +1267,6 @@ mod test {
     }
 
     #[tokio::test]
-    #[should_panic]
     async fn test_detects_cycles() {
         let mut scheduler = Schedule::new();
 
@@ -422,14 +1291,23 @@ mod test {
         tasks_specs.insert(task_specs.get(&4).unwrap().clone(), deps4);
         tasks_specs.insert(task_specs.get(&5).unwrap().clone(), deps5);
 
-        scheduler.schedule_tasks(tasks_specs.clone()).await;
-
-        // must panic
-        scheduler.run_checks().await;
+        scheduler.schedule_tasks(tasks_specs.clone()).await.unwrap();
+
+        assert_eq!(
+            scheduler.run_checks().await,
+            Err(ScheduleError::Cycle(vec![
+                "task_3".to_string(),
+                "task_1".to_string(),
+                "task_3".to_string()
+            ]))
+        );
+        assert_eq!(
+            scheduler.run_schedule().await,
+            Err(ScheduleError::NotChecked)
+        );
     }
 
     #[tokio::test]
-    #[should_panic]
     async fn test_detects_unconnected_tasks() {
         let mut scheduler = Schedule::new();
 
@@ -455,30 +1333,731 @@ mod test {
         tasks_specs.insert(task_specs.get(&4).unwrap().clone(), unconnected_deps4);
         tasks_specs.insert(task_specs.get(&5).unwrap().clone(), deps5);
 
-        scheduler.schedule_tasks(tasks_specs.clone()).await;
+        scheduler.schedule_tasks(tasks_specs.clone()).await.unwrap();
 
-        // must panic
-        scheduler.run_checks().await;
+        assert_eq!(
+            scheduler.run_checks().await,
+            Err(ScheduleError::UnreachableTasks(vec![
+                "task_1".to_string(),
+                "task_4".to_string()
+            ]))
+        );
     }
 
     #[tokio::test]
-    #[should_panic]
     async fn test_detects_errors_in_random_task_dependencies() {
         let mut scheduler = Schedule::new();
         let task_specs = create_test_task_specs(100);
         let tasks_dep_specs = create_random_task_dependencies_with_maybe_cycles(&task_specs, 20);
 
-        scheduler.schedule_tasks(tasks_dep_specs).await;
+        scheduler.schedule_tasks(tasks_dep_specs).await.unwrap();
 
-        // maybe panic
-        scheduler.run_checks().await;
+        // if no problem is detected, the random dependencies must form a proper dag
+        if scheduler.run_checks().await.is_ok() {
+            tokio::time::timeout(Duration::from_secs(3), scheduler.run_schedule())
+                .await
+                .expect("scheduled tasks did not finish in time, maybe (undetected) cycle")
+                .unwrap();
+        }
+    }
 
-        tokio::select! {
-            // case if the random dependencies form a proper dag
-            _ =  scheduler.run_schedule() => {panic!("this is correct and is done because test must fail if correct")}
-            // case if a cycles was not detected or tasks cannot be reached
-            _ = tokio::time::sleep(Duration::from_secs(3)) => {}
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn test_detects_task_depending_on_itself() {
+        let mut scheduler = Schedule::new();
+        let task_specs = create_test_task_specs(2);
+        let task_1 = task_specs.get(&1).unwrap().clone();
+        let task_2 = task_specs.get(&2).unwrap().clone();
+
+        let mut tasks_specs: TaskDependenciesSpecs = HashMap::new();
+        tasks_specs.insert(task_1.clone(), vec![]);
+        tasks_specs.insert(task_2.clone(), vec![task_1, task_2]);
+
+        scheduler.schedule_tasks(tasks_specs).await.unwrap();
+
+        assert_eq!(
+            scheduler.run_checks().await,
+            Err(ScheduleError::Cycle(vec![
+                "task_2".to_string(),
+                "task_2".to_string()
+            ]))
+        );
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn test_reports_all_problems_at_once() {
+        let mut scheduler = Schedule::new();
+        let task_specs = create_test_task_specs(4);
+        let task_1 = task_specs.get(&1).unwrap().clone();
+        let task_2 = task_specs.get(&2).unwrap().clone();
+        let task_3 = task_specs.get(&3).unwrap().clone();
+        let task_4 = task_specs.get(&4).unwrap().clone();
+
+        // task 2 and 3 form a cycle, task 4 depends on a task which is not scheduled
+        let mut tasks_specs: TaskDependenciesSpecs = HashMap::new();
+        tasks_specs.insert(task_2.clone(), vec![task_3.clone()]);
+        tasks_specs.insert(task_3.clone(), vec![task_2]);
+        tasks_specs.insert(task_4, vec![task_1]);
+
+        let problems = scheduler
+            .schedule_tasks(tasks_specs)
+            .await
+            .unwrap_err()
+            .into_problems();
+        assert_eq!(
+            problems,
+            vec![ScheduleError::UnknownDependency {
+                task: "task_4".to_string(),
+                dependency: "task_1".to_string()
+            }]
+        );
+
+        let problems = scheduler.run_checks().await.unwrap_err().into_problems();
+        assert_eq!(
+            problems,
+            vec![
+                ScheduleError::NoSourceTasks,
+                ScheduleError::UnreachableTasks(vec![
+                    "task_2".to_string(),
+                    "task_3".to_string(),
+                    "task_4".to_string()
+                ])
+            ]
+        );
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn test_daemon_reruns_tasks_with_cadence() {
+        let mut scheduler = Schedule::new();
+        let runner = Arc::new(CountingRunner::default());
+        let cadence = Some(Cadence::Interval(Duration::from_millis(20)));
+        let source = TaskSpecRef::from(
+            TaskSpec::new(
+                "source".to_string(),
+                RetryOptions::default(),
+                ExecutionMode::Once,
+                Arc::new(Default::default()),
+                runner.clone(),
+            )
+            .with_cadence(cadence.clone()),
+        );
+        let follower = TaskSpecRef::from(TaskSpec::new(
+            "follower".to_string(),
+            RetryOptions::default(),
+            ExecutionMode::Once,
+            Arc::new(Default::default()),
+            runner.clone(),
+        ));
+        let mut tasks_specs: TaskDependenciesSpecs = HashMap::new();
+        tasks_specs.insert(source.clone(), vec![]);
+        tasks_specs.insert(follower, vec![source]);
+
+        scheduler.schedule_tasks(tasks_specs).await.unwrap();
+        scheduler.run_checks().await.unwrap();
+
+        let (stop, _) = broadcast::channel(1);
+        let stop_receiver = stop.subscribe();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(150)).await;
+            stop.send(()).unwrap();
+        });
+
+        tokio::time::timeout(Duration::from_secs(3), scheduler.run_daemon(stop_receiver))
+            .await
+            .expect("daemon must stop after stop signal")
+            .unwrap();
+
+        // both tasks run on every trigger of the source task
+        let count = runner.count.load(Ordering::SeqCst);
+        assert!(count >= 4);
+        assert_eq!(count % 2, 0);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn test_daemon_returns_without_recurring_tasks() {
+        let mut scheduler = Schedule::new();
+        let task_specs = create_test_task_specs(10);
+        let tasks_dep_specs = create_random_task_dependencies(&task_specs, 5);
+        let (_stop, stop_receiver) = broadcast::channel(1);
+
+        scheduler.schedule_tasks(tasks_dep_specs).await.unwrap();
+        scheduler.run_checks().await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(3), scheduler.run_daemon(stop_receiver))
+            .await
+            .expect("daemon must return if no task has a cadence")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn test_daemon_runs_tasks_triggered_by_events() {
+        let started = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let create_task = |name: &str| {
+            TaskSpecRef::from(TaskSpec::new(
+                name.to_string(),
+                RetryOptions::default(),
+                ExecutionMode::Once,
+                Arc::new(Default::default()),
+                Arc::new(RecordingRunner {
+                    name: name.to_string(),
+                    started: started.clone(),
+                }),
+            ))
+        };
+        let collector = create_task("collector");
+        let stager = create_task("stager");
+        let consumer = create_task("consumer");
+        let mut tasks_specs: TaskDependenciesSpecs = HashMap::new();
+        tasks_specs.insert(collector.clone(), vec![]);
+        tasks_specs.insert(stager.clone(), vec![collector]);
+        tasks_specs.insert(consumer, vec![stager]);
+        // only the first run executes the whole schedule and is recorded
+        let mut run_history = MockRunHistoryServiceTrait::new();
+        run_history
+            .expect_start_schedule_run()
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        run_history
+            .expect_save_task_run()
+            .times(3)
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        run_history
+            .expect_finish_schedule_run()
+            .times(1)
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        let (events, task_events) = mpsc::channel(10);
+        let mut scheduler = Schedule::new()
+            .with_task_events(task_events)
+            .with_run_history(Arc::new(run_history));
+        scheduler.schedule_tasks(tasks_specs).await.unwrap();
+        scheduler.run_checks().await.unwrap();
+
+        let (stop, stop_receiver) = broadcast::channel(1);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            // a task of another schedule does not start a run
+            events.send("other".to_string()).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            events.send("stager".to_string()).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            stop.send(()).unwrap();
+        });
+
+        tokio::time::timeout(Duration::from_secs(3), scheduler.run_daemon(stop_receiver))
+            .await
+            .expect("daemon must stop after stop signal")
+            .unwrap();
+
+        // the collector is not executed again, the consumer runs after the triggered stager
+        assert_eq!(
+            *started.lock().unwrap(),
+            vec!["collector", "stager", "consumer", "stager", "consumer"]
+        );
+        let results = scheduler.results();
+        assert_eq!(results["collector"].state, ExecutionState::Skipped);
+        assert_eq!(results["stager"].state, ExecutionState::Finished);
+        assert_eq!(results["consumer"].state, ExecutionState::Finished);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn test_trigger_rules_skip_and_cascade() {
+        let mut scheduler = Schedule::new();
+        let counter = Arc::new(CountingRunner::default());
+        let failing = create_task_spec("failing", Arc::new(FailingRunner {}), HashMap::new());
+        let on_success = create_task_spec("on_success", counter.clone(), HashMap::new());
+        let after_skipped = create_task_spec("after_skipped", counter.clone(), HashMap::new());
+        let on_failure = create_task_spec(
+            "on_failure",
+            counter.clone(),
+            HashMap::from([("failing".to_string(), TriggerRule::OnFailure)]),
+        );
+        let always = create_task_spec(
+            "always",
+            counter.clone(),
+            HashMap::from([("on_success".to_string(), TriggerRule::Always)]),
+        );
+
+        let mut tasks_specs: TaskDependenciesSpecs = HashMap::new();
+        tasks_specs.insert(failing.clone(), vec![]);
+        tasks_specs.insert(on_success.clone(), vec![failing.clone()]);
+        tasks_specs.insert(after_skipped, vec![on_success.clone()]);
+        tasks_specs.insert(on_failure, vec![failing]);
+        tasks_specs.insert(always, vec![on_success]);
+
+        scheduler.schedule_tasks(tasks_specs).await.unwrap();
+        scheduler.run_checks().await.unwrap();
+        tokio::time::timeout(Duration::from_secs(3), scheduler.run_schedule())
+            .await
+            .expect("schedule must finish although tasks are skipped")
+            .unwrap();
+
+        for task in scheduler.tasks.values() {
+            let locked_task = task.lock().await;
+            let expected_state = match locked_task.name.as_str() {
+                "failing" => ExecutionState::Failed,
+                "on_success" | "after_skipped" => ExecutionState::Skipped,
+                "on_failure" | "always" => ExecutionState::Finished,
+                a => panic!("name did not match expected names: {}", a),
+            };
+            assert_eq!(locked_task.execution_state, expected_state);
         }
+        assert_eq!(counter.count.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn test_run_history_records_result_of_every_task() {
+        let mut run_history = MockRunHistoryServiceTrait::new();
+        run_history
+            .expect_start_schedule_run()
+            .times(1)
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        run_history
+            .expect_save_task_run()
+            .times(1)
+            .withf(|entry, updates_freshness| {
+                *updates_freshness
+                    && entry.task_name == "failing"
+                    && entry.state == ExecutionState::Failed
+                    && entry.attempts == 1
+                    && entry.started_at.is_some()
+                    && entry.error.as_deref() == Some("Nothing was executed")
+            })
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        run_history
+            .expect_save_task_run()
+            .times(1)
+            .withf(|entry, _| {
+                entry.task_name == "after_failing"
+                    && entry.state == ExecutionState::Skipped
+                    && entry.attempts == 0
+                    && entry.started_at.is_none()
+            })
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        run_history
+            .expect_finish_schedule_run()
+            .times(1)
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+
+        let mut scheduler = Schedule::new().with_run_history(Arc::new(run_history));
+        let failing = create_task_spec("failing", Arc::new(FailingRunner {}), HashMap::new());
+        let after_failing = create_task_spec("after_failing", build_test_runner(), HashMap::new());
+        let mut tasks_specs: TaskDependenciesSpecs = HashMap::new();
+        tasks_specs.insert(failing.clone(), vec![]);
+        tasks_specs.insert(after_failing, vec![failing]);
+
+        scheduler.schedule_tasks(tasks_specs).await.unwrap();
+        scheduler.run_checks().await.unwrap();
+        tokio::time::timeout(Duration::from_secs(3), scheduler.run_schedule())
+            .await
+            .expect("schedule must finish although a task failed")
+            .unwrap();
+
+        assert_ne!(scheduler.run_id(), Uuid::nil());
+        assert_eq!(scheduler.results().len(), 2);
+        assert!(scheduler.results()["failing"].stats.is_error);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn successful_tasks_of_a_backfill_do_not_become_fresh() {
+        let mut run_history = MockRunHistoryServiceTrait::new();
+        run_history
+            .expect_start_schedule_run()
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        run_history
+            .expect_save_task_run()
+            .times(1)
+            .withf(|entry, updates_freshness| {
+                !*updates_freshness && entry.state == ExecutionState::Finished
+            })
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        run_history
+            .expect_finish_schedule_run()
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+
+        let mut scheduler = Schedule::new()
+            .with_run_history(Arc::new(run_history))
+            .with_freshness_updates(false);
+        let backfill = create_task_spec("backfill", build_test_runner(), HashMap::new());
+        let mut tasks_specs: TaskDependenciesSpecs = HashMap::new();
+        tasks_specs.insert(backfill, vec![]);
+
+        scheduler.schedule_tasks(tasks_specs).await.unwrap();
+        scheduler.run_checks().await.unwrap();
+        tokio::time::timeout(Duration::from_secs(3), scheduler.run_schedule())
+            .await
+            .expect("schedule must finish")
+            .unwrap();
+
+        assert_eq!(
+            scheduler.results()["backfill"].state,
+            ExecutionState::Finished
+        );
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn test_resume_executes_unfinished_tasks_and_descendants() {
+        let mut scheduler = Schedule::new();
+        let counter = Arc::new(CountingRunner::default());
+        // previous run: source and side finished, failed did not, after_failed never started
+        // and after_failed_always finished although failed did not
+        let source = create_task_spec("source", counter.clone(), HashMap::new());
+        let side = create_task_spec("side", counter.clone(), HashMap::new());
+        let failed = create_task_spec("failed", counter.clone(), HashMap::new());
+        let after_failed = create_task_spec("after_failed", counter.clone(), HashMap::new());
+        let after_failed_always = create_task_spec(
+            "after_failed_always",
+            counter.clone(),
+            HashMap::from([("failed".to_string(), TriggerRule::Always)]),
+        );
+
+        let mut tasks_specs: TaskDependenciesSpecs = HashMap::new();
+        tasks_specs.insert(source.clone(), vec![]);
+        tasks_specs.insert(side, vec![source.clone()]);
+        tasks_specs.insert(failed.clone(), vec![source]);
+        tasks_specs.insert(after_failed, vec![failed.clone()]);
+        tasks_specs.insert(after_failed_always, vec![failed]);
+
+        scheduler.schedule_tasks(tasks_specs).await.unwrap();
+        scheduler.run_checks().await.unwrap();
+        let finished_tasks = HashSet::from([
+            "source".to_string(),
+            "side".to_string(),
+            "after_failed_always".to_string(),
+        ]);
+        scheduler.resume(&finished_tasks).await;
+        tokio::time::timeout(Duration::from_secs(3), scheduler.run_schedule())
+            .await
+            .expect("resumed schedule must finish")
+            .unwrap();
+
+        // failed, after_failed and after_failed_always
+        assert_eq!(counter.count.load(Ordering::SeqCst), 3);
+        for task in scheduler.tasks.values() {
+            assert_eq!(task.lock().await.execution_state, ExecutionState::Finished);
+        }
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn test_concurrency_limits_queue_ready_tasks() {
+        let limits = ConcurrencyLimits::new(Some(2), HashMap::from([("api".to_string(), 1)]));
+        let mut scheduler = Schedule::new().with_concurrency_limits(limits);
+        let api_runner = Arc::new(ParallelismRunner::default());
+        let other_runner = Arc::new(ParallelismRunner {
+            total_running: api_runner.total_running.clone(),
+            total_max_parallel: api_runner.total_max_parallel.clone(),
+            ..Default::default()
+        });
+
+        let mut tasks_specs: TaskDependenciesSpecs = HashMap::new();
+        for i in 0..3 {
+            let api_task = TaskSpec::new(
+                format!("api_{}", i),
+                RetryOptions::default(),
+                ExecutionMode::Once,
+                Arc::new(Default::default()),
+                api_runner.clone(),
+            )
+            .with_resources(vec!["api".to_string()]);
+            tasks_specs.insert(TaskSpecRef::from(api_task), vec![]);
+            let other_task = create_task_spec(
+                &format!("other_{}", i),
+                other_runner.clone(),
+                HashMap::new(),
+            );
+            tasks_specs.insert(other_task, vec![]);
+        }
+
+        scheduler.schedule_tasks(tasks_specs).await.unwrap();
+        scheduler.run_checks().await.unwrap();
+        tokio::time::timeout(Duration::from_secs(3), scheduler.run_schedule())
+            .await
+            .expect("schedule must finish with concurrency limits")
+            .unwrap();
+
+        assert_eq!(api_runner.count.load(Ordering::SeqCst), 3);
+        assert_eq!(other_runner.count.load(Ordering::SeqCst), 3);
+        assert_eq!(api_runner.max_parallel.load(Ordering::SeqCst), 1);
+        assert!(api_runner.total_max_parallel.load(Ordering::SeqCst) <= 2);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn test_detects_unknown_resources() {
+        let mut scheduler = Schedule::new();
+        let task = TaskSpec::new(
+            "task".to_string(),
+            RetryOptions::default(),
+            ExecutionMode::Once,
+            Arc::new(Default::default()),
+            build_test_runner(),
+        )
+        .with_resources(vec!["api".to_string()]);
+        let tasks_specs: TaskDependenciesSpecs = HashMap::from([(TaskSpecRef::from(task), vec![])]);
+
+        scheduler.schedule_tasks(tasks_specs).await.unwrap();
+
+        assert_eq!(
+            scheduler.run_checks().await,
+            Err(ScheduleError::UnknownResource {
+                task: "task".to_string(),
+                resource: "api".to_string()
+            })
+        );
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn test_cancelled_schedule_stops_running_and_pending_tasks() {
+        let cancel = CancellationToken::new();
+        let mut scheduler = Schedule::new().with_cancellation(cancel.clone());
+        let counter = Arc::new(CountingRunner::default());
+        let long_running = create_task_spec(
+            "long_running",
+            Arc::new(CooperativeRunner {}),
+            HashMap::new(),
+        );
+        let after_long_running = create_task_spec(
+            "after_long_running",
+            counter.clone(),
+            HashMap::from([("long_running".to_string(), TriggerRule::Always)]),
+        );
+        let mut tasks_specs: TaskDependenciesSpecs = HashMap::new();
+        tasks_specs.insert(long_running.clone(), vec![]);
+        tasks_specs.insert(after_long_running, vec![long_running]);
+
+        scheduler.schedule_tasks(tasks_specs).await.unwrap();
+        scheduler.run_checks().await.unwrap();
+        let stop = tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            cancel.cancel();
+        });
+        tokio::time::timeout(Duration::from_secs(3), scheduler.run_schedule())
+            .await
+            .expect("schedule must finish after it was cancelled")
+            .unwrap();
+        stop.await.unwrap();
+
+        assert_eq!(counter.count.load(Ordering::SeqCst), 0);
+        for task in scheduler.tasks.values() {
+            assert_eq!(task.lock().await.execution_state, ExecutionState::Cancelled);
+        }
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn test_ready_tasks_start_by_priority() {
+        let limits = ConcurrencyLimits::new(Some(1), HashMap::new());
+        let mut scheduler = Schedule::new().with_concurrency_limits(limits);
+        let started = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        let mut tasks_specs: TaskDependenciesSpecs = HashMap::new();
+        for (name, priority) in [("collector", -1), ("default", 0), ("stager", 10)] {
+            let runner = Arc::new(RecordingRunner {
+                name: name.to_string(),
+                started: started.clone(),
+            });
+            let task = TaskSpec::new(
+                name.to_string(),
+                RetryOptions::default(),
+                ExecutionMode::Once,
+                Arc::new(Default::default()),
+                runner,
+            )
+            .with_priority(priority);
+            tasks_specs.insert(TaskSpecRef::from(task), vec![]);
+        }
+
+        scheduler.schedule_tasks(tasks_specs).await.unwrap();
+        scheduler.run_checks().await.unwrap();
+        tokio::time::timeout(Duration::from_secs(3), scheduler.run_schedule())
+            .await
+            .expect("schedule must finish")
+            .unwrap();
+
+        assert_eq!(
+            *started.lock().unwrap(),
+            vec!["stager", "default", "collector"]
+        );
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn test_graph_shows_dependencies_and_states_of_last_run() {
+        let mut scheduler = Schedule::new();
+        let counter = Arc::new(CountingRunner::default());
+        let failing = create_task_spec("failing", Arc::new(FailingRunner {}), HashMap::new());
+        let on_success = create_task_spec("on_success", counter.clone(), HashMap::new());
+        let always = create_task_spec(
+            "always",
+            counter.clone(),
+            HashMap::from([("failing".to_string(), TriggerRule::Always)]),
+        );
+
+        let mut tasks_specs: TaskDependenciesSpecs = HashMap::new();
+        tasks_specs.insert(failing.clone(), vec![]);
+        tasks_specs.insert(on_success, vec![failing.clone()]);
+        tasks_specs.insert(always, vec![failing]);
+        scheduler.schedule_tasks(tasks_specs).await.unwrap();
+        scheduler.run_checks().await.unwrap();
+
+        let planned = scheduler.graph().await;
+        assert_eq!(
+            planned.dependencies,
+            vec![
+                (
+                    "failing".to_string(),
+                    "always".to_string(),
+                    TriggerRule::Always
+                ),
+                (
+                    "failing".to_string(),
+                    "on_success".to_string(),
+                    TriggerRule::OnSuccess
+                ),
+            ]
+        );
+        assert_eq!(
+            planned.execution_waves(),
+            vec![
+                vec!["failing".to_string()],
+                vec!["always".to_string(), "on_success".to_string()],
+            ]
+        );
+        assert!(planned
+            .tasks
+            .iter()
+            .all(|(_, state)| *state == ExecutionState::Pending));
+        // building the graph does not execute any task
+        assert_eq!(counter.count.load(Ordering::SeqCst), 0);
+
+        tokio::time::timeout(Duration::from_secs(3), scheduler.run_schedule())
+            .await
+            .expect("schedule must finish")
+            .unwrap();
+
+        assert_eq!(
+            scheduler.graph().await.tasks,
+            vec![
+                ("always".to_string(), ExecutionState::Finished),
+                ("failing".to_string(), ExecutionState::Failed),
+                ("on_success".to_string(), ExecutionState::Skipped),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn test_outputs_are_passed_to_outgoing_tasks() {
+        let mut scheduler = Schedule::new();
+        let received = Arc::new(std::sync::Mutex::new(HashMap::new()));
+        let summing_task = |name: &str, value: u32| {
+            create_task_spec(
+                name,
+                Arc::new(SummingRunner {
+                    name: name.to_string(),
+                    value,
+                    received: received.clone(),
+                }),
+                HashMap::new(),
+            )
+        };
+        let first = summing_task("first", 1);
+        let second = summing_task("second", 2);
+        let combined = summing_task("combined", 10);
+        let last = summing_task("last", 100);
+
+        let mut tasks_specs: TaskDependenciesSpecs = HashMap::new();
+        tasks_specs.insert(first.clone(), vec![]);
+        tasks_specs.insert(second.clone(), vec![]);
+        tasks_specs.insert(combined.clone(), vec![first, second]);
+        tasks_specs.insert(last, vec![combined]);
+        scheduler.schedule_tasks(tasks_specs).await.unwrap();
+        scheduler.run_checks().await.unwrap();
+        tokio::time::timeout(Duration::from_secs(3), scheduler.run_schedule())
+            .await
+            .expect("schedule must finish")
+            .unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.get("first"), Some(&vec![]));
+        assert_eq!(received.get("combined"), Some(&vec![1, 2]));
+        // only the outputs of direct dependencies are passed
+        assert_eq!(received.get("last"), Some(&vec![13]));
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn test_task_waits_for_its_window() {
+        let mut scheduler = Schedule::new();
+        let counter = Arc::new(CountingRunner::default());
+        let opens_at = Utc::now() + chrono::Duration::milliseconds(300);
+        let task = TaskSpec::new(
+            "windowed".to_string(),
+            RetryOptions::default(),
+            ExecutionMode::Once,
+            Arc::new(Default::default()),
+            counter.clone(),
+        )
+        .with_window(TimeWindow::new(
+            Some(opens_at.time()),
+            Some((opens_at + chrono::Duration::hours(1)).time()),
+        ));
+        let mut tasks_specs: TaskDependenciesSpecs = HashMap::new();
+        tasks_specs.insert(TaskSpecRef::from(task), vec![]);
+        scheduler.schedule_tasks(tasks_specs).await.unwrap();
+        scheduler.run_checks().await.unwrap();
+
+        tokio::time::timeout(Duration::from_secs(3), scheduler.run_schedule())
+            .await
+            .expect("schedule must finish")
+            .unwrap();
+
+        assert_eq!(counter.count.load(Ordering::SeqCst), 1);
+        assert!(Utc::now() >= opens_at);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::mutable_key_type)]
+    async fn test_cancelled_schedule_does_not_wait_for_closed_windows() {
+        let cancel = CancellationToken::new();
+        let mut scheduler = Schedule::new().with_cancellation(cancel.clone());
+        let counter = Arc::new(CountingRunner::default());
+        let opens_at = Utc::now() + chrono::Duration::hours(1);
+        let task = TaskSpec::new(
+            "windowed".to_string(),
+            RetryOptions::default(),
+            ExecutionMode::Once,
+            Arc::new(Default::default()),
+            counter.clone(),
+        )
+        .with_window(TimeWindow::new(
+            Some(opens_at.time()),
+            Some((opens_at + chrono::Duration::hours(1)).time()),
+        ));
+        let mut tasks_specs: TaskDependenciesSpecs = HashMap::new();
+        tasks_specs.insert(TaskSpecRef::from(task), vec![]);
+        scheduler.schedule_tasks(tasks_specs).await.unwrap();
+        scheduler.run_checks().await.unwrap();
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            cancel.cancel();
+        });
+        tokio::time::timeout(Duration::from_secs(3), scheduler.run_schedule())
+            .await
+            .expect("schedule must finish once cancelled")
+            .unwrap();
+
+        assert_eq!(counter.count.load(Ordering::SeqCst), 0);
     }
 
     /*
@@ -491,7 +2070,10 @@ mod test {
 
     #[async_trait]
     impl Runnable for TestRunner {
-        async fn run(&self) -> Result<Option<StatsMap>, crate::dag_schedule::task::TaskError> {
+        async fn run(
+            &self,
+            _context: TaskContext,
+        ) -> Result<Option<StatsMap>, crate::dag_schedule::task::TaskError> {
             let stats: StatsMap = Arc::new(Mutex::new(HashMap::new()));
             let mut rng = OsRng;
             let number = rng.gen_range(1..=30);
@@ -501,6 +2083,130 @@ mod test {
         }
     }
 
+    #[derive(Debug, Default)]
+    struct CountingRunner {
+        count: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Runnable for CountingRunner {
+        async fn run(
+            &self,
+            _context: TaskContext,
+        ) -> Result<Option<StatsMap>, crate::dag_schedule::task::TaskError> {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            Ok(None)
+        }
+    }
+
+    /// counts executions and tracks the maximum number of executions running at the same time,
+    /// runners sharing the total counters also track their combined maximum
+    #[derive(Debug, Default)]
+    struct ParallelismRunner {
+        count: AtomicUsize,
+        running: AtomicUsize,
+        max_parallel: AtomicUsize,
+        total_running: Arc<AtomicUsize>,
+        total_max_parallel: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl Runnable for ParallelismRunner {
+        async fn run(&self, _context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_parallel.fetch_max(running, Ordering::SeqCst);
+            let total_running = self.total_running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.total_max_parallel
+                .fetch_max(total_running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            self.total_running.fetch_sub(1, Ordering::SeqCst);
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            self.count.fetch_add(1, Ordering::SeqCst);
+            Ok(None)
+        }
+    }
+
+    /// records the names of the started tasks in the order they were started
+    #[derive(Debug)]
+    struct RecordingRunner {
+        name: String,
+        started: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl Runnable for RecordingRunner {
+        async fn run(&self, _context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
+            self.started.lock().unwrap().push(self.name.clone());
+            Ok(None)
+        }
+    }
+
+    /// records the outputs of its upstream tasks and outputs their sum plus its own value
+    #[derive(Debug)]
+    struct SummingRunner {
+        name: String,
+        value: u32,
+        received: Arc<std::sync::Mutex<HashMap<String, Vec<u32>>>>,
+    }
+
+    #[async_trait]
+    impl Runnable for SummingRunner {
+        async fn run(&self, context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
+            let upstream: Vec<u32> = context
+                .upstream_outputs::<u32>()
+                .into_iter()
+                .map(|output| *output)
+                .collect();
+            context.set_output(self.value + upstream.iter().sum::<u32>());
+            self.received
+                .lock()
+                .unwrap()
+                .insert(self.name.clone(), upstream);
+            Ok(None)
+        }
+    }
+
+    /// runs until it is cancelled
+    #[derive(Debug)]
+    struct CooperativeRunner {}
+
+    #[async_trait]
+    impl Runnable for CooperativeRunner {
+        async fn run(&self, context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
+            while !context.cancel.is_cancelled() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+            Err(TaskError::Cancelled)
+        }
+    }
+
+    #[derive(Debug)]
+    struct FailingRunner {}
+
+    #[async_trait]
+    impl Runnable for FailingRunner {
+        async fn run(&self, _context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
+            Err(TaskError::NoExecutionError)
+        }
+    }
+
+    fn create_task_spec(
+        name: &str,
+        runnable: Arc<dyn Runnable>,
+        trigger_rules: HashMap<String, TriggerRule>,
+    ) -> TaskSpecRef {
+        TaskSpecRef::from(
+            TaskSpec::new(
+                name.to_string(),
+                RetryOptions::default(),
+                ExecutionMode::Once,
+                Arc::new(Default::default()),
+                runnable,
+            )
+            .with_trigger_rules(trigger_rules),
+        )
+    }
+
     fn build_test_runner() -> Arc<dyn Runnable> {
         Arc::new(TestRunner {})
     }
@@ -515,6 +2221,13 @@ mod test {
                 name: format!("task_{}", i),
                 retry_options: RetryOptions::default(),
                 execution_mode: ExecutionMode::Once,
+                cadence: None,
+                trigger_rules: HashMap::new(),
+                resources: Vec::new(),
+                timeout: None,
+                priority: 0,
+                window: Default::default(),
+                freshness: None,
                 tools: Arc::new(Default::default()),
                 runnable: runner,
             };
@@ -594,4 +2307,181 @@ mod test {
 
         task_deps
     }
+
+    #[tokio::test]
+    async fn failed_tasks_and_completed_runs_are_notified() {
+        let server = MockServer::start();
+        let failed = server.mock(|when, then| {
+            when.method(POST)
+                .path("/webhook")
+                .json_body_partial(r#"{"event": "task_failed", "pipeline": "daily", "task": {"name": "fatal", "state": "failed"}}"#);
+            then.status(200);
+        });
+        let completed = server.mock(|when, then| {
+            when.method(POST).path("/webhook").json_body_partial(
+                r#"{"event": "schedule_completed", "report": {"pipeline": "daily"}}"#,
+            );
+            then.status(200);
+        });
+        let notifier = Notifier::new(get_test_client()).with_sink(NotificationSink::new(
+            Secret::new(server.url("/webhook")),
+            SinkFormat::Json,
+            vec![
+                NotificationEvent::TaskFailed,
+                NotificationEvent::ScheduleCompleted,
+            ],
+        ));
+        let dag = TestDag::new()
+            .with_task("source", FakeBehavior::Succeed, &[])
+            .with_task("fatal", FakeBehavior::Fail, &["source"]);
+        let mut schedule = dag
+            .build(
+                Schedule::new()
+                    .with_name("daily".to_string())
+                    .with_notifier(notifier),
+            )
+            .await;
+
+        let report = schedule.run_schedule().await.unwrap();
+
+        assert_eq!(report.failed_tasks(), vec!["fatal"]);
+        failed.assert();
+        completed.assert();
+    }
+
+    #[tokio::test]
+    async fn fresh_tasks_are_skipped_and_satisfy_their_dependents() {
+        let mut run_history = MockRunHistoryServiceTrait::new();
+        run_history
+            .expect_start_schedule_run()
+            .returning(|_, _, _| Box::pin(async { Ok(()) }));
+        run_history
+            .expect_save_task_run()
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        run_history
+            .expect_finish_schedule_run()
+            .returning(|_, _| Box::pin(async { Ok(()) }));
+        run_history
+            .expect_get_last_successes()
+            .withf(|task_names| task_names.len() == 2)
+            .returning(|_| {
+                Box::pin(async {
+                    Ok(HashMap::from([(
+                        "stored".to_string(),
+                        Utc::now() - chrono::TimeDelta::minutes(10),
+                    )]))
+                })
+            });
+        let fresh_for_an_hour =
+            |spec: TaskSpec| spec.with_freshness(Some(Duration::from_secs(3600)));
+        let dag = TestDag::new()
+            .with_configured_task("stored", FakeBehavior::Succeed, &[], fresh_for_an_hour)
+            .with_configured_task("collect", FakeBehavior::Succeed, &[], fresh_for_an_hour)
+            .with_task("stage", FakeBehavior::Succeed, &["stored", "collect"]);
+        let mut schedule = dag
+            .build(Schedule::new().with_run_history(Arc::new(run_history)))
+            .await;
+
+        schedule.run_schedule().await.unwrap();
+        schedule.reset_tasks().await;
+        schedule.run_schedule().await.unwrap();
+
+        // succeeded in an earlier process
+        assert_eq!(dag.runnable("stored").attempts(), 0);
+        // succeeded in the first run
+        assert_eq!(dag.runnable("collect").attempts(), 1);
+        assert_eq!(dag.runnable("stage").attempts(), 2);
+        let collect = &schedule.results()["collect"];
+        assert_eq!(collect.state, ExecutionState::Finished);
+        assert!(collect.stats.started_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn paused_tasks_are_skipped_and_forced_tasks_run_once() {
+        let mut task_controls = MockTaskControlServiceTrait::new();
+        let collect_loads = Arc::new(AtomicUsize::new(0));
+        task_controls
+            .expect_get_control()
+            .returning(move |task_name| {
+                let control = match task_name {
+                    "paused" => Some(TaskControl {
+                        paused: true,
+                        ..Default::default()
+                    }),
+                    // forced in the second run only
+                    "collect" if collect_loads.fetch_add(1, Ordering::SeqCst) == 1 => {
+                        Some(TaskControl {
+                            force_run: true,
+                            ..Default::default()
+                        })
+                    }
+                    _ => None,
+                };
+                Box::pin(async move { Ok(control) })
+            });
+        task_controls
+            .expect_clear_force_run()
+            .withf(|task_name| task_name == "collect")
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(()) }));
+        let dag = TestDag::new()
+            .with_configured_task("collect", FakeBehavior::Succeed, &[], |spec: TaskSpec| {
+                spec.with_freshness(Some(Duration::from_secs(3600)))
+            })
+            .with_task("paused", FakeBehavior::Succeed, &[])
+            .with_task("dependent", FakeBehavior::Succeed, &["paused"]);
+        let mut schedule = dag
+            .build(Schedule::new().with_task_controls(Arc::new(task_controls)))
+            .await;
+
+        schedule.run_schedule().await.unwrap();
+        assert_eq!(dag.runnable("collect").attempts(), 1);
+        schedule.reset_tasks().await;
+        schedule.run_schedule().await.unwrap();
+
+        // fresh after the first run, but forced in the second
+        assert_eq!(dag.runnable("collect").attempts(), 2);
+        assert_eq!(dag.runnable("paused").attempts(), 0);
+        assert_eq!(dag.runnable("dependent").attempts(), 0);
+        assert_eq!(schedule.results()["paused"].state, ExecutionState::Skipped);
+        assert_eq!(
+            schedule.results()["dependent"].state,
+            ExecutionState::Skipped
+        );
+    }
+
+    #[tokio::test]
+    async fn controls_are_loaded_once_per_run_and_paused_tasks_keep_their_forced_run() {
+        let mut task_controls = MockTaskControlServiceTrait::new();
+        task_controls
+            .expect_get_control()
+            .times(3)
+            .returning(|task_name| {
+                let control = (task_name == "paused").then(|| TaskControl {
+                    paused: true,
+                    force_run: true,
+                    ..Default::default()
+                });
+                Box::pin(async move { Ok(control) })
+            });
+        task_controls.expect_clear_force_run().times(0);
+        let dag = TestDag::new()
+            .with_task("slow", FakeBehavior::Sleep(Duration::from_millis(50)), &[])
+            .with_task("waiting", FakeBehavior::Succeed, &[])
+            .with_task("paused", FakeBehavior::Succeed, &[]);
+        let mut schedule = dag
+            .build(
+                Schedule::new()
+                    .with_task_controls(Arc::new(task_controls))
+                    .with_concurrency_limits(ConcurrencyLimits::new(Some(1), HashMap::new())),
+            )
+            .await;
+
+        schedule.run_schedule().await.unwrap();
+
+        assert_eq!(dag.runnable("slow").attempts(), 1);
+        assert_eq!(dag.runnable("waiting").attempts(), 1);
+        assert_eq!(dag.runnable("paused").attempts(), 0);
+        assert_eq!(schedule.results()["paused"].state, ExecutionState::Skipped);
+    }
 }
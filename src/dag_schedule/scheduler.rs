@@ -0,0 +1,179 @@
+use crate::dag_schedule::report::RunReport;
+use crate::dag_schedule::schedule::{Schedule, ScheduleError};
+use futures::future::join_all;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Name of the pipeline built from the top level task dependencies of the configuration
+pub const DEFAULT_PIPELINE: &str = "default";
+
+/// Shared between the schedules of a scheduler. A task holds the lock of its name while it runs,
+/// so that a task which is part of several schedules never runs in two of them at the same time.
+#[derive(Clone, Debug, Default)]
+pub struct TaskLocks {
+    locks: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
+}
+
+impl TaskLocks {
+    pub fn lock_of(&self, task_name: &str) -> Arc<tokio::sync::Mutex<()>> {
+        self.locks
+            .lock()
+            .expect("task locks poisoned")
+            .entry(task_name.to_string())
+            .or_default()
+            .clone()
+    }
+}
+
+/// Owns the schedules of several named pipelines, e.g. daily prices and weekly reference data,
+/// and runs them side by side. Each schedule keeps its own DAG and cadences.
+#[derive(Default)]
+pub struct Scheduler {
+    schedules: Vec<Schedule>,
+    task_locks: TaskLocks,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Scheduler::default()
+    }
+
+    /// Adds the schedule of a pipeline, it shares the task locks with all other schedules
+    pub fn add_schedule(&mut self, schedule: Schedule) {
+        self.schedules
+            .push(schedule.with_task_locks(self.task_locks.clone()));
+    }
+
+    pub fn schedules(&self) -> &[Schedule] {
+        &self.schedules
+    }
+
+    pub fn schedules_mut(&mut self) -> &mut [Schedule] {
+        &mut self.schedules
+    }
+
+    /// Runs all schedules once at the same time and returns when all of them finished,
+    /// the problems of all schedules are combined
+    pub async fn run_schedules(&mut self) -> Result<Vec<RunReport>, ScheduleError> {
+        let results = join_all(
+            self.schedules
+                .iter_mut()
+                .map(|schedule| schedule.run_schedule()),
+        )
+        .await;
+        let mut reports = vec![];
+        let mut problems = vec![];
+        for result in results {
+            match result {
+                Ok(report) => reports.push(report),
+                Err(e) => problems.extend(e.into_problems()),
+            }
+        }
+        ScheduleError::from_problems(problems)?;
+        Ok(reports)
+    }
+
+    /// Runs all schedules as daemon, see [`Schedule::run_daemon`]. Returns when all of them stopped.
+    pub async fn run_daemons(&mut self, stop: &broadcast::Sender<()>) -> Result<(), ScheduleError> {
+        let results = join_all(
+            self.schedules
+                .iter_mut()
+                .map(|schedule| schedule.run_daemon(stop.subscribe())),
+        )
+        .await;
+        combine_results(results)
+    }
+}
+
+fn combine_results(results: Vec<Result<(), ScheduleError>>) -> Result<(), ScheduleError> {
+    ScheduleError::from_problems(
+        results
+            .into_iter()
+            .filter_map(Result::err)
+            .flat_map(ScheduleError::into_problems)
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dag_schedule::context::TaskContext;
+    use crate::dag_schedule::schedule::{Schedule, TaskDependenciesSpecs, TaskSpec, TaskSpecRef};
+    use crate::dag_schedule::scheduler::Scheduler;
+    use crate::dag_schedule::task::{ExecutionMode, RetryOptions, Runnable, StatsMap, TaskError};
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// counts how often it runs at the same time
+    #[derive(Debug, Default)]
+    struct OverlapRunner {
+        running: AtomicUsize,
+        max_running: AtomicUsize,
+        runs: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Runnable for OverlapRunner {
+        async fn run(&self, _context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_running.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            self.runs.fetch_add(1, Ordering::SeqCst);
+            Ok(None)
+        }
+    }
+
+    #[allow(clippy::mutable_key_type)]
+    async fn create_pipeline(name: &str, runnable: Arc<OverlapRunner>) -> Schedule {
+        let mut schedule = Schedule::new().with_name(name.to_string());
+        let task = TaskSpec::new(
+            "shared".to_string(),
+            RetryOptions::default(),
+            ExecutionMode::Once,
+            Arc::new(Default::default()),
+            runnable,
+        );
+        let mut tasks_specs: TaskDependenciesSpecs = HashMap::new();
+        tasks_specs.insert(TaskSpecRef::from(task), vec![]);
+        schedule.schedule_tasks(tasks_specs).await.unwrap();
+        schedule.run_checks().await.unwrap();
+        schedule
+    }
+
+    #[tokio::test]
+    async fn task_of_several_pipelines_does_not_overlap() {
+        let runner = Arc::new(OverlapRunner::default());
+        let mut scheduler = Scheduler::new();
+        scheduler.add_schedule(create_pipeline("daily", runner.clone()).await);
+        scheduler.add_schedule(create_pipeline("weekly", runner.clone()).await);
+
+        let reports = tokio::time::timeout(Duration::from_secs(3), scheduler.run_schedules())
+            .await
+            .expect("scheduler must finish")
+            .unwrap();
+
+        assert_eq!(runner.runs.load(Ordering::SeqCst), 2);
+        assert_eq!(runner.max_running.load(Ordering::SeqCst), 1);
+        let names: Vec<&str> = scheduler.schedules().iter().map(|s| s.name()).collect();
+        assert_eq!(names, vec!["daily", "weekly"]);
+        let pipelines: Vec<&str> = reports.iter().map(|r| r.pipeline.as_str()).collect();
+        assert_eq!(pipelines, vec!["daily", "weekly"]);
+    }
+
+    #[tokio::test]
+    async fn problems_of_all_pipelines_are_reported() {
+        let mut scheduler = Scheduler::new();
+        // schedules which were not checked cannot be run
+        scheduler.add_schedule(Schedule::new().with_name("daily".to_string()));
+        scheduler.add_schedule(Schedule::new().with_name("weekly".to_string()));
+
+        let problems = scheduler.run_schedules().await.unwrap_err().into_problems();
+
+        assert_eq!(problems.len(), 2);
+    }
+}
@@ -10,39 +10,79 @@
 // Callback or Notification Mechanism: A way to notify other systems or components upon task completion or failure. This can be useful for triggering downstream processes.
 // Metadata: Additional information like task creator, creation date, last modified date, etc., for audit and tracking purposes.
 
+use crate::dag_schedule::cadence::Cadence;
+use crate::dag_schedule::context::{TaskContext, TaskOutput};
+use crate::dag_schedule::fan_out::FanOutResult;
 use crate::dag_schedule::schedule::TaskSpecRef;
+use crate::dag_schedule::window::TimeWindow;
+use crate::database::task_control_service::TaskControl;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use core::fmt::Debug;
+use rand::Rng;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
 use std::any::Any;
 use std::collections::HashMap;
 
+use crate::api_keys::key_manager::KeyErrors;
 use crate::dag_schedule::task::TaskError::NoExecutionError;
 use anyhow::Error;
 use std::future::Future;
 use std::hash::{Hash, Hasher};
 use std::ops::Add;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::broadcast::error::TryRecvError;
 use tokio::sync::mpsc;
 use tokio::sync::{broadcast, Mutex};
-use tokio::task::JoinHandle;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
 use tracing::log::warn;
-use tracing::Instrument;
+use tracing::{debug, info, Instrument};
 use uuid::Uuid;
 
-#[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Clone, Debug, Default)]
 pub struct ExecutionStats {
-    is_error: bool,
-    runtime: Duration,
-    retries: Option<u8>,
+    pub is_error: bool,
+    /// empty if the runnable was not executed
+    pub started_at: Option<DateTime<Utc>>,
+    pub runtime: Duration,
+    /// number of executions of the runnable including retries
+    pub attempts: u32,
+    pub retries: Option<u8>,
+    /// error of the last execution including all its causes
+    pub error: Option<String>,
     pub custom_stats: Option<StatsMap>,
+    /// time by which the task overran its deadline, if it did not finish before it
+    pub deadline_overrun: Option<Duration>,
+    /// outcome of every item of a fan-out task, also kept if the task failed
+    pub items: Option<Arc<FanOutResult>>,
 }
 
-#[allow(dead_code)]
+/// Sent by a task when it reached a final state to trigger its outgoing tasks
+#[derive(Debug)]
 pub struct Trigger {
-    _is_error: bool,
-    next_tasks: Vec<TaskRef>,
+    pub task_name: String,
+    pub state: ExecutionState,
+    pub stats: ExecutionStats,
+    /// output of the task handed to the next tasks
+    pub output: Option<TaskOutput>,
+    pub next_tasks: Vec<TaskRef>,
+    /// the task was skipped without affecting its next tasks, they run as if it succeeded
+    pub passed_through: bool,
+}
+
+impl Trigger {
+    /// state the trigger rules of the next tasks are evaluated against
+    pub fn dependency_state(&self) -> ExecutionState {
+        if self.passed_through {
+            ExecutionState::Finished
+        } else {
+            self.state
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -55,7 +95,8 @@ pub enum ExecutionMode {
 }
 
 // todo run task based on this (fsm) or actor
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ExecutionState {
     Pending,
     Running,
@@ -64,21 +105,77 @@ pub enum ExecutionState {
     Failed,
     Cancelled,
     // Retry,
-    // Skipped,
+    Skipped,
 }
 
-#[derive(PartialEq, Debug)]
+impl ExecutionState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExecutionState::Pending => "pending",
+            ExecutionState::Running => "running",
+            ExecutionState::Finished => "finished",
+            ExecutionState::Failed => "failed",
+            ExecutionState::Cancelled => "cancelled",
+            ExecutionState::Skipped => "skipped",
+        }
+    }
+}
+
+impl TryFrom<String> for ExecutionState {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.to_lowercase().as_str() {
+            "pending" => Ok(Self::Pending),
+            "running" => Ok(Self::Running),
+            "finished" => Ok(Self::Finished),
+            "failed" => Ok(Self::Failed),
+            "cancelled" => Ok(Self::Cancelled),
+            "skipped" => Ok(Self::Skipped),
+            other => Err(format!("{} is not a known execution state", other)),
+        }
+    }
+}
+
+/// Defines which final state of a dependency allows the dependent task to run.
+/// If the rule of any dependency is not satisfied, the task is skipped.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerRule {
+    #[default]
+    OnSuccess,
+    OnFailure,
+    Always,
+}
+
+impl TriggerRule {
+    pub fn is_satisfied_by(&self, state: ExecutionState) -> bool {
+        match self {
+            TriggerRule::OnSuccess => state == ExecutionState::Finished,
+            TriggerRule::OnFailure => state == ExecutionState::Failed,
+            TriggerRule::Always => true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
 pub enum CycleCheck {
     Unknown,
     Visited { max_allowed: usize },
     Finished,
 }
 
+/// time a cancelled task gets to stop gracefully before it is aborted
+const CANCELLATION_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
 pub type StatsMap = Arc<Mutex<HashMap<String, Arc<dyn Any + Send + Sync>>>>;
 
 #[async_trait]
 pub trait Runnable: Send + Sync + Debug {
-    async fn run(&self) -> Result<Option<StatsMap>, TaskError>;
+    /// Executes the action. Long running actions should check the cancellation token of the
+    /// context regularly, e.g. between symbols, and stop with [`TaskError::Cancelled`] once it is
+    /// cancelled. Outputs of upstream tasks are read from and the own output is set on the context.
+    async fn run(&self, context: TaskContext) -> Result<Option<StatsMap>, TaskError>;
 }
 
 // todo generalize for lib usage
@@ -92,6 +189,68 @@ pub enum TaskError {
     UnexpectedError(#[source] Error),
     #[error("Nothing was executed")]
     NoExecutionError,
+    #[error("The task was cancelled")]
+    Cancelled,
+    #[error("The task timed out after {0:?}")]
+    Timeout(Duration),
+    #[error("The task did not finish before its deadline {0}")]
+    DeadlineExceeded(DateTime<Utc>),
+    #[error("The task failed on a worker: {message}")]
+    WorkerError { message: String, retryable: bool },
+    /// more items of a fan-out task failed than tolerated, retryable if one of the items is
+    #[error("{} of {total} items failed: {}", .failed.len(), .failed.join(", "))]
+    ItemsFailed {
+        failed: Vec<String>,
+        total: usize,
+        retryable: bool,
+    },
+}
+
+impl TaskError {
+    /// Transient errors like network failures, 5xx responses or pool timeouts are worth a retry.
+    /// Fatal errors like parse errors or missing api keys fail the same way on every attempt.
+    /// Errors of unknown cause are retried.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            TaskError::DatabaseError(e) => is_retryable_database_error(e),
+            TaskError::ClientRequestError(e) => is_retryable_request_error(e),
+            TaskError::UnexpectedError(e) => e
+                .chain()
+                .find_map(|cause| {
+                    if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                        Some(is_retryable_request_error(e))
+                    } else if let Some(e) = cause.downcast_ref::<sqlx::Error>() {
+                        Some(is_retryable_database_error(e))
+                    } else if cause.is::<std::io::Error>() {
+                        Some(true)
+                    } else if cause.is::<serde_json::Error>() || cause.is::<KeyErrors>() {
+                        Some(false)
+                    } else {
+                        None
+                    }
+                })
+                .unwrap_or(true),
+            TaskError::NoExecutionError => true,
+            TaskError::WorkerError { retryable, .. } | TaskError::ItemsFailed { retryable, .. } => {
+                *retryable
+            }
+            TaskError::Cancelled | TaskError::Timeout(_) | TaskError::DeadlineExceeded(_) => false,
+        }
+    }
+}
+
+fn is_retryable_request_error(error: &reqwest::Error) -> bool {
+    match error.status() {
+        Some(status) => status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS,
+        None => error.is_timeout() || error.is_connect() || error.is_request(),
+    }
+}
+
+fn is_retryable_database_error(error: &sqlx::Error) -> bool {
+    matches!(
+        error,
+        sqlx::Error::Io(_) | sqlx::Error::PoolTimedOut | sqlx::Error::Tls(_)
+    )
 }
 
 pub type TaskRef = Arc<Mutex<Task>>;
@@ -122,18 +281,46 @@ pub struct Task {
     pub id: Uuid,
     pub name: String,
     pub num_ingoing_tasks: Option<usize>,
+    pub num_dependencies: Option<usize>,
+    pub trigger_rules: HashMap<String, TriggerRule>,
+    pub dependencies_satisfied: bool,
+    pub resources: Vec<String>,
+    pub priority: i32,
     pub outgoing_tasks: Vec<TaskRef>,
     pub cycle_check: CycleCheck,
     pub retry_options: RetryOptions,
     pub repeat: Option<usize>,
     pub execution_mode: ExecutionMode,
+    pub cadence: Option<Cadence>,
+    pub next_due: Option<DateTime<Utc>>,
+    /// set when resuming a run in which the task already finished, the task is not executed again
+    pub finished_in_resumed_run: bool,
+    /// set for the tasks not affected by task events in runs of the daemon triggered by events,
+    /// the task is skipped while its outgoing tasks run as if it succeeded
+    pub not_triggered: bool,
     pub tools: Tools,
     pub runnable: Arc<dyn Runnable>,
     // pub s_finished: Option<mpsc::Sender<(bool, Vec<TaskRef>)>>,
     pub execution_state: ExecutionState,
     pub stats: Option<ExecutionStats>,
-    // todo hier die stats einfuegen oder spaeter die ergebnisse sammeln in einer map im scheduler??
-    pub job_handle: Option<JoinHandle<()>>, // todo save handle handle of runnable to be able to cancel jobs
+    /// maximum runtime of a single execution including retries
+    pub timeout: Option<Duration>,
+    /// daily time window in which the task is started and has to finish
+    pub window: TimeWindow,
+    /// the task is not executed while its last success lies within the given duration
+    pub freshness: Option<Duration>,
+    /// end of the last successful execution, kept across runs of the schedule
+    pub last_succeeded_at: Option<DateTime<Utc>>,
+    /// control set by an operator, loaded by the schedule before the task is started
+    pub control: TaskControl,
+    /// deadline of the current run, set by the schedule once the window of the task is open
+    pub deadline_at: Option<DateTime<Utc>>,
+    /// cancels the running execution, tasks started afterwards are not executed anymore
+    pub cancel: CancellationToken,
+    /// outputs of the upstream tasks which finished in the current run, by task name
+    pub upstream_outputs: HashMap<String, TaskOutput>,
+    /// output of the last execution in the current run
+    pub output: Option<TaskOutput>,
 }
 
 impl Task {
@@ -141,23 +328,40 @@ impl Task {
         name: String,
         runnable: Arc<dyn Runnable>,
         tools: Tools,
-        _s_finished: Option<mpsc::Sender<(bool, Vec<TaskRef>)>>,
+        _s_finished: Option<mpsc::Sender<Trigger>>,
     ) -> TaskRef {
         let task = Task {
             id: Uuid::new_v4(),
             name,
             num_ingoing_tasks: None,
+            num_dependencies: None,
+            trigger_rules: HashMap::new(),
+            dependencies_satisfied: true,
+            resources: Vec::new(),
+            priority: 0,
             outgoing_tasks: Vec::new(),
             cycle_check: CycleCheck::Unknown,
             retry_options: RetryOptions::default(),
             repeat: None,
             execution_mode: ExecutionMode::Once,
+            cadence: None,
+            next_due: None,
+            finished_in_resumed_run: false,
+            not_triggered: false,
             tools,
             runnable,
             // s_finished,
             execution_state: ExecutionState::Pending,
             stats: None,
-            job_handle: None,
+            timeout: None,
+            window: TimeWindow::default(),
+            freshness: None,
+            last_succeeded_at: None,
+            control: TaskControl::default(),
+            deadline_at: None,
+            cancel: CancellationToken::new(),
+            upstream_outputs: HashMap::new(),
+            output: None,
         };
         Arc::new(Mutex::new(task))
     }
@@ -170,60 +374,460 @@ impl Task {
             id: task_spec.get_uuid(),
             name: task_spec.name.clone(),
             num_ingoing_tasks: None,
+            num_dependencies: None,
+            trigger_rules: task_spec.trigger_rules.clone(),
+            dependencies_satisfied: true,
+            resources: task_spec.resources.clone(),
+            priority: task_spec.priority,
             outgoing_tasks: Vec::new(),
             cycle_check: CycleCheck::Unknown,
             retry_options: task_spec.retry_options,
             repeat: None,
             execution_mode: task_spec.execution_mode.clone(),
+            cadence: task_spec.cadence.clone(),
+            next_due: None,
+            finished_in_resumed_run: false,
+            not_triggered: false,
             tools: task_spec.tools.clone(),
             runnable: task_spec.runnable.clone(),
             // s_finished,
             execution_state: ExecutionState::Pending,
             stats: None,
-            job_handle: None,
+            timeout: task_spec.timeout,
+            window: task_spec.window,
+            freshness: task_spec.freshness,
+            last_succeeded_at: None,
+            control: TaskControl::default(),
+            deadline_at: None,
+            cancel: CancellationToken::new(),
+            upstream_outputs: HashMap::new(),
+            output: None,
         };
         Arc::new(Mutex::new(task))
     }
 
+    /// restores the initial state, so that the task can be executed again by the schedule
+    pub fn reset(&mut self) {
+        self.num_ingoing_tasks = self.num_dependencies;
+        self.dependencies_satisfied = true;
+        self.execution_state = ExecutionState::Pending;
+        self.stats = None;
+        self.finished_in_resumed_run = false;
+        self.not_triggered = false;
+        self.upstream_outputs.clear();
+        self.output = None;
+        self.deadline_at = None;
+        self.control = TaskControl::default();
+    }
+
+    /// Registers the final state of a finished dependency and
+    /// returns true if it was the last dependency the task was waiting for
+    pub fn register_finished_dependency(
+        &mut self,
+        dependency: &str,
+        state: ExecutionState,
+    ) -> bool {
+        let rule = self
+            .trigger_rules
+            .get(dependency)
+            .copied()
+            .unwrap_or_default();
+        if !rule.is_satisfied_by(state) {
+            debug!(
+                "trigger rule {:?} of task {} not satisfied by state {:?} of {}",
+                rule, self.name, state, dependency
+            );
+            self.dependencies_satisfied = false;
+        }
+        self.num_ingoing_tasks = self.num_ingoing_tasks.map(|i| i.saturating_sub(1));
+        self.num_ingoing_tasks == Some(0)
+    }
+
+    /// Marks the task as skipped and returns the trigger for its outgoing tasks
+    pub fn skip(&mut self) -> Trigger {
+        self.execution_state = ExecutionState::Skipped;
+        self.trigger()
+    }
+
+    fn trigger(&self) -> Trigger {
+        Trigger {
+            task_name: self.name.clone(),
+            state: self.execution_state,
+            stats: self.stats.clone().unwrap_or_default(),
+            output: self.output.clone(),
+            next_tasks: self.outgoing_tasks.clone(),
+            passed_through: false,
+        }
+    }
+
+    /// Returns when the time window of the task opens, if the task would be executed now
+    /// but its window is closed. Tasks which finish without execution do not wait for their window.
+    pub fn window_opens_at(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        if self.cancel.is_cancelled()
+            || self.finishes_without_execution(now)
+            || self.deadline_at.is_some()
+            || self.window.is_open(now)
+        {
+            return None;
+        }
+        Some(self.window.opens_at(now))
+    }
+
+    /// A task without cadence is due on every run of the schedule,
+    /// otherwise only when its cadence fired since its last start
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.next_due.is_none_or(|next_due| next_due <= now)
+    }
+
+    /// Paused tasks are skipped. Tasks which already finished in a resumed run, were not triggered,
+    /// are not due or are fresh pass the trigger to their outgoing tasks, unless their run is forced.
+    fn finishes_without_execution(&self, now: DateTime<Utc>) -> bool {
+        self.control.is_paused(now)
+            || (!self.control.force_run
+                && (self.finished_in_resumed_run
+                    || self.not_triggered
+                    || !self.is_due(now)
+                    || self.is_fresh(now)))
+    }
+
+    /// A task with freshness is fresh while its last success lies within its freshness
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        match (self.freshness, self.last_succeeded_at) {
+            (Some(freshness), Some(last_succeeded_at)) => {
+                (now - last_succeeded_at).to_std().unwrap_or_default() < freshness
+            }
+            _ => false,
+        }
+    }
+
     #[tracing::instrument(name = "Start task", skip_all, fields(self.name = %self.name) )]
     pub async fn run(
         &mut self,
-        s_finished: mpsc::Sender<(bool, Vec<TaskRef>)>,
+        s_finished: mpsc::Sender<Trigger>,
     ) -> anyhow::Result<ExecutionStats, TaskError> {
-        // init stats .. think about whats helpful
-        let mut stats = ExecutionStats {
-            is_error: false,
-            runtime: Default::default(),
-            retries: None,
-            custom_stats: None,
+        if self.cancel.is_cancelled() {
+            debug!("task {} was cancelled before it started", self.name);
+            return self
+                .finish_without_execution(ExecutionState::Cancelled, false, s_finished)
+                .await;
+        }
+        let now = Utc::now();
+        if self.control.is_paused(now) {
+            info!(
+                "task {} is paused until {:?}, skipping it",
+                self.name, self.control.disabled_until
+            );
+            return self
+                .finish_without_execution(ExecutionState::Skipped, false, s_finished)
+                .await;
+        }
+        let forced = self.control.force_run;
+        if forced {
+            info!("run of task {} is forced", self.name);
+        }
+        if self.not_triggered && !forced {
+            debug!(
+                "task {} was not triggered by the events of the run, continue with outgoing tasks",
+                self.name
+            );
+            return self
+                .finish_without_execution(ExecutionState::Skipped, true, s_finished)
+                .await;
+        }
+        if self.finished_in_resumed_run && !forced {
+            debug!(
+                "task {} already finished in the resumed run, continue with outgoing tasks",
+                self.name
+            );
+            return self
+                .finish_without_execution(ExecutionState::Finished, false, s_finished)
+                .await;
+        }
+        if !self.is_due(now) && !forced {
+            debug!(
+                "task {} is not due before {:?}, continue with outgoing tasks",
+                self.name, self.next_due
+            );
+            return self
+                .finish_without_execution(ExecutionState::Finished, false, s_finished)
+                .await;
+        }
+        if self.is_fresh(now) && !forced {
+            debug!(
+                "task {} last succeeded at {:?} within its freshness, continue with outgoing tasks",
+                self.name, self.last_succeeded_at
+            );
+            return self
+                .finish_without_execution(ExecutionState::Finished, false, s_finished)
+                .await;
+        }
+        if let Some(deadline) = self.deadline_at.filter(|deadline| *deadline <= now) {
+            debug!(
+                "task {} was not started before its deadline {}",
+                self.name, deadline
+            );
+            return self.fail_after_deadline(deadline, s_finished).await;
+        }
+        let mut stats = ExecutionStats::default();
+        self.next_due = self.cadence.as_ref().and_then(|c| c.next_after(now));
+        self.execution_state = ExecutionState::Running;
+        stats.started_at = Some(now);
+        let start = Instant::now();
+        let attempts = Arc::new(AtomicU32::new(0));
+        let mut executions = 0;
+        let context = TaskContext::new(self.cancel.child_token(), self.upstream_outputs.clone());
+
+        let result = match self.execution_mode.clone() {
+            ExecutionMode::Once => {
+                executions += 1;
+                self.execute(&context, attempts.clone()).await
+            }
+            ExecutionMode::RepeatLimited { count } => {
+                let mut result = Err(NoExecutionError);
+                for _ in 0..count {
+                    executions += 1;
+                    result = self.execute(&context, attempts.clone()).await;
+                    if result.is_err() || self.cancel.is_cancelled() {
+                        break;
+                    }
+                }
+                result
+            }
+            ExecutionMode::RepeatForDuration { duration } => {
+                let end = Instant::now().add(duration);
+                loop {
+                    executions += 1;
+                    let result = self.execute(&context, attempts.clone()).await;
+                    if result.is_err() || self.cancel.is_cancelled() || Instant::now() >= end {
+                        break result;
+                    }
+                }
+            }
+            ExecutionMode::Continuously { kill } => {
+                let mut kill = kill.subscribe();
+                loop {
+                    executions += 1;
+                    let result = self.execute(&context, attempts.clone()).await;
+                    // repeat until the kill signal was sent or all senders are dropped
+                    if result.is_err()
+                        || self.cancel.is_cancelled()
+                        || !matches!(kill.try_recv(), Err(TryRecvError::Empty))
+                    {
+                        break result;
+                    }
+                }
+            }
         };
+
+        self.output = context.output();
+        stats.items = self
+            .output
+            .clone()
+            .and_then(|output| output.downcast::<FanOutResult>().ok());
+        stats.runtime = start.elapsed();
+        stats.attempts = attempts.load(Ordering::SeqCst);
+        stats.retries =
+            Some(u8::try_from(stats.attempts.saturating_sub(executions)).unwrap_or(u8::MAX));
+        match &result {
+            Ok(custom_stats) => {
+                self.execution_state = ExecutionState::Finished;
+                self.last_succeeded_at = Some(Utc::now());
+                stats.custom_stats = custom_stats.clone();
+            }
+            Err(TaskError::Cancelled) => {
+                self.execution_state = ExecutionState::Cancelled;
+                stats.error = Some(TaskError::Cancelled.to_string());
+            }
+            Err(e) => {
+                self.execution_state = ExecutionState::Failed;
+                stats.is_error = true;
+                stats.error = Some(error_chain(e));
+                if let TaskError::DeadlineExceeded(deadline) = e {
+                    stats.deadline_overrun = Some(overrun(*deadline));
+                }
+            }
+        }
+        self.stats = Some(stats.clone());
+        s_finished
+            .send(self.trigger())
+            .await
+            .expect("finished-task channel closed while scheduler is running");
+
+        result.map(|_| stats)
+    }
+
+    /// Sets the final state without executing the runnable and triggers the outgoing tasks.
+    /// If `passed_through`, the outgoing tasks run as if the task succeeded.
+    async fn finish_without_execution(
+        &mut self,
+        state: ExecutionState,
+        passed_through: bool,
+        s_finished: mpsc::Sender<Trigger>,
+    ) -> anyhow::Result<ExecutionStats, TaskError> {
+        let stats = ExecutionStats::default();
+        self.execution_state = state;
+        self.stats = Some(stats.clone());
+        let trigger = Trigger {
+            passed_through,
+            ..self.trigger()
+        };
+        s_finished
+            .send(trigger)
+            .await
+            .expect("finished-task channel closed while scheduler is running");
+        Ok(stats)
+    }
+
+    /// fails the task without executing it, since its deadline passed while it was waiting
+    async fn fail_after_deadline(
+        &mut self,
+        deadline: DateTime<Utc>,
+        s_finished: mpsc::Sender<Trigger>,
+    ) -> anyhow::Result<ExecutionStats, TaskError> {
+        let error = TaskError::DeadlineExceeded(deadline);
+        let stats = ExecutionStats {
+            is_error: true,
+            error: Some(error.to_string()),
+            deadline_overrun: Some(overrun(deadline)),
+            ..Default::default()
+        };
+        self.execution_state = ExecutionState::Failed;
+        self.stats = Some(stats);
+        s_finished
+            .send(self.trigger())
+            .await
+            .expect("finished-task channel closed while scheduler is running");
+        Err(error)
+    }
+
+    /// Executes the runnable (including retries) in a separate tokio task,
+    /// each execution is counted by `attempts`. The execution is cancelled if it exceeds the timeout
+    /// or the deadline and aborted if it does not stop within a grace period after it was cancelled.
+    async fn execute(
+        &self,
+        context: &TaskContext,
+        attempts: Arc<AtomicU32>,
+    ) -> Result<Option<StatsMap>, TaskError> {
         let f = self.runnable.clone();
         let r = self.retry_options;
+        let cancel = context.cancel.clone();
+        let job_context = context.clone();
 
         let span = tracing::Span::current();
 
-        let result = tokio::spawn(async move { retry(r, || f.run()).instrument(span).await })
-            .await
-            .map_err(|e| TaskError::UnexpectedError(Error::from(e)))?;
-
-        if result.is_err() {
-            self.execution_state = ExecutionState::Failed;
-            s_finished
-                .send((true, self.outgoing_tasks.clone()))
-                .await
-                .expect("TODO: panic message");
-        } else {
-            self.execution_state = ExecutionState::Finished;
-            s_finished
-                .send((false, self.outgoing_tasks.clone()))
+        let mut job = tokio::spawn(
+            async move {
+                retry(r, || {
+                    attempts.fetch_add(1, Ordering::SeqCst);
+                    f.run(job_context.clone())
+                })
                 .await
-                .expect("TODO: panic message");
+            }
+            .instrument(span),
+        );
+        let timeout = self.timeout;
+        let timed_out = async move {
+            match timeout {
+                Some(timeout) => {
+                    tokio::time::sleep(timeout).await;
+                    timeout
+                }
+                None => std::future::pending().await,
+            }
+        };
+
+        let deadline = self.deadline_at;
+        let deadline_reached = async move {
+            match deadline {
+                Some(deadline) => {
+                    let remaining = (deadline - Utc::now()).to_std().unwrap_or_default();
+                    tokio::time::sleep(remaining).await;
+                    deadline
+                }
+                None => std::future::pending().await,
+            }
+        };
+
+        let error = tokio::select! {
+            result = &mut job => {
+                return result.map_err(|e| TaskError::UnexpectedError(Error::from(e)))?;
+            }
+            timeout = timed_out => {
+                warn!("task {} timed out after {:?}", self.name, timeout);
+                TaskError::Timeout(timeout)
+            }
+            deadline = deadline_reached => {
+                warn!("task {} did not finish before its deadline {}", self.name, deadline);
+                TaskError::DeadlineExceeded(deadline)
+            }
+            _ = self.cancel.cancelled() => TaskError::Cancelled,
+        };
+        cancel.cancel();
+
+        // give the runnable the chance to stop gracefully, e.g. after the current symbol or by
+        // cancelling its entry in the task queue
+        let stopped = tokio::time::timeout(CANCELLATION_GRACE_PERIOD, &mut job).await;
+        if stopped.is_err() {
+            warn!(
+                "task {} did not stop within {:?} after it was cancelled, abort it",
+                self.name, CANCELLATION_GRACE_PERIOD
+            );
+            job.abort();
+        }
+        match (error, stopped) {
+            (TaskError::Cancelled, Ok(result)) => {
+                result.map_err(|e| TaskError::UnexpectedError(Error::from(e)))?
+            }
+            (error, _) => Err(error),
         }
+    }
+}
 
-        result.map(|s| {
-            stats.custom_stats = s;
-            stats
-        })
+/// time passed since the deadline
+fn overrun(deadline: DateTime<Utc>) -> Duration {
+    (Utc::now() - deadline).to_std().unwrap_or_default()
+}
+
+/// formats the error with all its causes, e.g. "Database interaction failed: pool timed out"
+pub fn error_chain(error: &(dyn std::error::Error + 'static)) -> String {
+    std::iter::successors(Some(error), |e| e.source())
+        .map(|e| e.to_string())
+        .collect::<Vec<_>>()
+        .join(": ")
+}
+
+/// Converts the custom stats of a task to json. Values of types which cannot be
+/// represented as json are replaced by a placeholder.
+pub async fn custom_stats_to_json(stats: &StatsMap) -> serde_json::Value {
+    let stats = stats.lock().await;
+    let map = stats
+        .iter()
+        .map(|(key, value)| (key.clone(), any_to_json(value.as_ref())))
+        .collect::<serde_json::Map<_, _>>();
+    serde_json::Value::Object(map)
+}
+
+fn any_to_json(value: &(dyn Any + Send + Sync)) -> serde_json::Value {
+    if let Some(v) = value.downcast_ref::<serde_json::Value>() {
+        v.clone()
+    } else if let Some(v) = value.downcast_ref::<String>() {
+        serde_json::json!(v)
+    } else if let Some(v) = value.downcast_ref::<&'static str>() {
+        serde_json::json!(v)
+    } else if let Some(v) = value.downcast_ref::<bool>() {
+        serde_json::json!(v)
+    } else if let Some(v) = value.downcast_ref::<i32>() {
+        serde_json::json!(v)
+    } else if let Some(v) = value.downcast_ref::<i64>() {
+        serde_json::json!(v)
+    } else if let Some(v) = value.downcast_ref::<u64>() {
+        serde_json::json!(v)
+    } else if let Some(v) = value.downcast_ref::<usize>() {
+        serde_json::json!(v)
+    } else if let Some(v) = value.downcast_ref::<f64>() {
+        serde_json::json!(v)
+    } else {
+        serde_json::Value::String("<unsupported type>".to_string())
     }
 }
 
@@ -245,6 +849,8 @@ impl Eq for Task {}
 pub struct RetryOptions {
     max_retries: u32,
     back_off: BackOff,
+    /// maximum random time added to each back off, so that failed tasks do not retry in lockstep
+    jitter: Duration,
 }
 
 impl Default for RetryOptions {
@@ -254,10 +860,26 @@ impl Default for RetryOptions {
             back_off: BackOff::Constant {
                 back_off: Default::default(),
             },
+            jitter: Duration::ZERO,
         }
     }
 }
 
+impl RetryOptions {
+    pub fn new(max_retries: u32, back_off: BackOff) -> Self {
+        RetryOptions {
+            max_retries,
+            back_off,
+            jitter: Duration::ZERO,
+        }
+    }
+
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub enum BackOff {
     Constant {
@@ -287,22 +909,36 @@ where
                 maybe_result = Ok(opt);
                 break;
             }
-            Err(e) if retry_count < options.max_retries => {
+            Err(e) if retry_count < options.max_retries && e.is_retryable() => {
                 warn!(
                     "error: {}, retry executing task, retries left: {}",
                     e,
                     options.max_retries - retry_count
                 );
                 // add 1 since retry_count is 0 based
-                let back_off = derive_back_off_time(options, retry_count.add(1));
+                let back_off = derive_back_off_time(options, retry_count.add(1))
+                    .saturating_add(random_jitter(options.jitter));
                 tokio::time::sleep(back_off).await;
             }
-            Err(e) => maybe_result = Err(e),
+            Err(e) => {
+                if retry_count < options.max_retries {
+                    debug!("error: {}, not retryable, skip remaining retries", e);
+                }
+                maybe_result = Err(e);
+                break;
+            }
         };
     }
     maybe_result
 }
 
+fn random_jitter(max_jitter: Duration) -> Duration {
+    if max_jitter.is_zero() {
+        return Duration::ZERO;
+    }
+    rand::thread_rng().gen_range(Duration::ZERO..=max_jitter)
+}
+
 #[tracing::instrument(level = "debug", skip(options))]
 fn derive_back_off_time(options: RetryOptions, current_retry_count: u32) -> Duration {
     match options.back_off {
@@ -330,11 +966,22 @@ fn derive_back_off_time(options: RetryOptions, current_retry_count: u32) -> Dura
 
 #[cfg(test)]
 mod test {
-    use crate::dag_schedule::task::{retry, BackOff, RetryOptions, TaskError};
+    use crate::api_keys::key_manager::KeyErrors;
+    use crate::dag_schedule::task::{
+        custom_stats_to_json, random_jitter, retry, BackOff, ExecutionMode, ExecutionState,
+        RetryOptions, Runnable, StatsMap, Task, TaskError, TriggerRule,
+    };
+    use async_trait::async_trait;
+    use chrono::Utc;
     use std::cell::RefCell;
+    use std::collections::HashMap;
 
+    use crate::dag_schedule::context::TaskContext;
     use std::rc::Rc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
     use std::time::Duration;
+    use tokio::sync::{broadcast, mpsc, Mutex};
     use tokio::time::Instant;
 
     #[tokio::test]
@@ -344,6 +991,7 @@ mod test {
             back_off: BackOff::Constant {
                 back_off: Duration::from_millis(10),
             },
+            ..Default::default()
         };
         let counter = Rc::new(RefCell::new(5));
         match retry(r, || run(counter.clone())).await {
@@ -357,6 +1005,23 @@ mod test {
         };
     }
 
+    #[tokio::test]
+    async fn test_cancelled_task_is_not_retried() {
+        let r = RetryOptions {
+            max_retries: 3,
+            ..Default::default()
+        };
+        let executions = AtomicUsize::new(0);
+        let result: Result<Option<()>, TaskError> = retry(r, || {
+            executions.fetch_add(1, Ordering::SeqCst);
+            async { Err(TaskError::Cancelled) }
+        })
+        .await;
+
+        assert!(matches!(result, Err(TaskError::Cancelled)));
+        assert_eq!(executions.load(Ordering::SeqCst), 1);
+    }
+
     #[tokio::test]
     async fn test_exponential_back_off_logic() {
         let r = RetryOptions {
@@ -366,6 +1031,7 @@ mod test {
                 min_back_off: Duration::from_millis(2),
                 max_back_off: Duration::from_millis(500),
             },
+            ..Default::default()
         };
         // start time
         let start = Instant::now();
@@ -387,6 +1053,7 @@ mod test {
                 min_back_off: Duration::from_millis(1),
                 max_back_off: Duration::from_millis(100),
             },
+            ..Default::default()
         };
         // start time
         let start = Instant::now();
@@ -400,6 +1067,324 @@ mod test {
         assert!((150..1000).contains(&elapsed.as_millis())) // bigger interval for slower envs
     }
 
+    #[tokio::test]
+    async fn test_fatal_error_is_not_retried() {
+        let r = RetryOptions::new(
+            3,
+            BackOff::Constant {
+                back_off: Duration::from_millis(1),
+            },
+        );
+        let attempts = Rc::new(RefCell::new(0));
+
+        let result = retry(r, || {
+            *attempts.borrow_mut() += 1;
+            async {
+                let parse_error = serde_json::from_str::<u32>("<html>").unwrap_err();
+                Err::<Option<()>, _>(TaskError::UnexpectedError(parse_error.into()))
+            }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.take(), 1);
+    }
+
+    #[test]
+    fn test_errors_are_classified_as_retryable_or_fatal() {
+        let io_error = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset");
+        let missing_key = KeyErrors::KeyNeverProvided(anyhow::anyhow!("polygon key not provided"));
+
+        assert!(TaskError::DatabaseError(sqlx::Error::PoolTimedOut).is_retryable());
+        assert!(!TaskError::DatabaseError(sqlx::Error::RowNotFound).is_retryable());
+        assert!(
+            TaskError::UnexpectedError(anyhow::Error::from(io_error).context("load data"))
+                .is_retryable()
+        );
+        assert!(!TaskError::UnexpectedError(missing_key.into()).is_retryable());
+        assert!(TaskError::UnexpectedError(anyhow::anyhow!("unknown")).is_retryable());
+        assert!(!TaskError::Timeout(Duration::from_secs(1)).is_retryable());
+        assert!(!TaskError::Cancelled.is_retryable());
+    }
+
+    #[test]
+    fn test_jitter_stays_within_maximum() {
+        assert_eq!(random_jitter(Duration::ZERO), Duration::ZERO);
+        for _ in 0..100 {
+            assert!(random_jitter(Duration::from_millis(10)) <= Duration::from_millis(10));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repeat_limited_runs_action_multiple_times() {
+        let runner = Arc::new(CountingRunner::default());
+        let task = Task::new(
+            "task".to_string(),
+            runner.clone(),
+            Arc::new(Default::default()),
+            None,
+        );
+        task.lock().await.execution_mode = ExecutionMode::RepeatLimited { count: 3 };
+        let (sender, mut receiver) = mpsc::channel(1);
+
+        task.lock().await.run(sender).await.unwrap();
+
+        assert_eq!(runner.count.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            receiver.recv().await.unwrap().state,
+            ExecutionState::Finished
+        );
+    }
+
+    #[tokio::test]
+    async fn test_repeat_for_duration_runs_until_duration_passed() {
+        let runner = Arc::new(CountingRunner::default());
+        let task = Task::new(
+            "task".to_string(),
+            runner.clone(),
+            Arc::new(Default::default()),
+            None,
+        );
+        task.lock().await.execution_mode = ExecutionMode::RepeatForDuration {
+            duration: Duration::from_millis(50),
+        };
+        let (sender, _receiver) = mpsc::channel(1);
+        let start = Instant::now();
+
+        task.lock().await.run(sender).await.unwrap();
+
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert!(runner.count.load(Ordering::SeqCst) > 1);
+    }
+
+    #[tokio::test]
+    async fn test_continuously_runs_until_killed() {
+        let runner = Arc::new(CountingRunner::default());
+        let task = Task::new(
+            "task".to_string(),
+            runner.clone(),
+            Arc::new(Default::default()),
+            None,
+        );
+        let (kill, _) = broadcast::channel(1);
+        task.lock().await.execution_mode = ExecutionMode::Continuously { kill: kill.clone() };
+        let (sender, _receiver) = mpsc::channel(1);
+
+        let handle = tokio::spawn(async move { task.lock().await.run(sender).await });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        kill.send(()).unwrap();
+
+        tokio::time::timeout(Duration::from_secs(3), handle)
+            .await
+            .expect("task must stop after kill signal")
+            .unwrap()
+            .unwrap();
+        assert!(runner.count.load(Ordering::SeqCst) > 1);
+    }
+
+    #[test]
+    fn test_trigger_rules() {
+        use ExecutionState::{Failed, Finished, Skipped};
+        assert!(TriggerRule::OnSuccess.is_satisfied_by(Finished));
+        assert!(!TriggerRule::OnSuccess.is_satisfied_by(Failed));
+        assert!(!TriggerRule::OnSuccess.is_satisfied_by(Skipped));
+        assert!(TriggerRule::OnFailure.is_satisfied_by(Failed));
+        assert!(!TriggerRule::OnFailure.is_satisfied_by(Finished));
+        assert!(!TriggerRule::OnFailure.is_satisfied_by(Skipped));
+        assert!(TriggerRule::Always.is_satisfied_by(Finished));
+        assert!(TriggerRule::Always.is_satisfied_by(Failed));
+        assert!(TriggerRule::Always.is_satisfied_by(Skipped));
+    }
+
+    #[tokio::test]
+    async fn test_run_fills_execution_stats() {
+        let task = Task::new(
+            "task".to_string(),
+            Arc::new(FailingRunner {}),
+            Arc::new(Default::default()),
+            None,
+        );
+        task.lock().await.retry_options = RetryOptions {
+            max_retries: 2,
+            back_off: BackOff::Constant {
+                back_off: Duration::from_millis(1),
+            },
+            ..Default::default()
+        };
+        let (sender, mut receiver) = mpsc::channel(1);
+
+        assert!(task.lock().await.run(sender).await.is_err());
+
+        let trigger = receiver.recv().await.unwrap();
+        assert_eq!(trigger.state, ExecutionState::Failed);
+        assert!(trigger.stats.is_error);
+        assert!(trigger.stats.started_at.is_some());
+        assert_eq!(trigger.stats.attempts, 3);
+        assert_eq!(trigger.stats.retries, Some(2));
+        assert_eq!(
+            trigger.stats.error.as_deref(),
+            Some("Something went wrong: connection reset")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_custom_stats_to_json() {
+        let stats: StatsMap = Arc::new(Mutex::new(HashMap::new()));
+        {
+            let mut stats = stats.lock().await;
+            stats.insert("rows".to_string(), Arc::new(42_usize));
+            stats.insert("source".to_string(), Arc::new("sec".to_string()));
+            stats.insert("unknown".to_string(), Arc::new(Duration::from_secs(1)));
+        }
+
+        assert_eq!(
+            custom_stats_to_json(&stats).await,
+            serde_json::json!({"rows": 42, "source": "sec", "unknown": "<unsupported type>"})
+        );
+    }
+
+    #[tokio::test]
+    async fn test_timeout_fails_task() {
+        let task = Task::new(
+            "task".to_string(),
+            Arc::new(CooperativeRunner {}),
+            Arc::new(Default::default()),
+            None,
+        );
+        task.lock().await.timeout = Some(Duration::from_millis(20));
+        let (sender, mut receiver) = mpsc::channel(1);
+
+        let result = task.lock().await.run(sender).await;
+
+        assert!(matches!(result, Err(TaskError::Timeout(_))));
+        assert_eq!(receiver.recv().await.unwrap().state, ExecutionState::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_stops_running_task() {
+        let task = Task::new(
+            "task".to_string(),
+            Arc::new(CooperativeRunner {}),
+            Arc::new(Default::default()),
+            None,
+        );
+        let cancel = task.lock().await.cancel.clone();
+        let (sender, mut receiver) = mpsc::channel(1);
+
+        let handle = tokio::spawn(async move { task.lock().await.run(sender).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        cancel.cancel();
+
+        let trigger = tokio::time::timeout(Duration::from_secs(3), receiver.recv())
+            .await
+            .expect("task must stop after it was cancelled")
+            .unwrap();
+        assert_eq!(trigger.state, ExecutionState::Cancelled);
+        assert!(!trigger.stats.is_error);
+        assert!(matches!(handle.await.unwrap(), Err(TaskError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_task_is_not_started() {
+        let runner = Arc::new(CountingRunner::default());
+        let task = Task::new(
+            "task".to_string(),
+            runner.clone(),
+            Arc::new(Default::default()),
+            None,
+        );
+        task.lock().await.cancel.cancel();
+        let (sender, mut receiver) = mpsc::channel(1);
+
+        task.lock().await.run(sender).await.unwrap();
+
+        assert_eq!(runner.count.load(Ordering::SeqCst), 0);
+        assert_eq!(
+            receiver.recv().await.unwrap().state,
+            ExecutionState::Cancelled
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deadline_fails_running_task() {
+        let task = Task::new(
+            "task".to_string(),
+            Arc::new(CooperativeRunner {}),
+            Arc::new(Default::default()),
+            None,
+        );
+        task.lock().await.deadline_at = Some(Utc::now() + chrono::Duration::milliseconds(20));
+        let (sender, mut receiver) = mpsc::channel(1);
+
+        let result = task.lock().await.run(sender).await;
+
+        assert!(matches!(result, Err(TaskError::DeadlineExceeded(_))));
+        let trigger = receiver.recv().await.unwrap();
+        assert_eq!(trigger.state, ExecutionState::Failed);
+        assert!(trigger.stats.deadline_overrun.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_task_past_deadline_is_not_started() {
+        let runner = Arc::new(CountingRunner::default());
+        let task = Task::new(
+            "task".to_string(),
+            runner.clone(),
+            Arc::new(Default::default()),
+            None,
+        );
+        task.lock().await.deadline_at = Some(Utc::now() - chrono::Duration::minutes(5));
+        let (sender, mut receiver) = mpsc::channel(1);
+
+        let result = task.lock().await.run(sender).await;
+
+        assert!(matches!(result, Err(TaskError::DeadlineExceeded(_))));
+        assert_eq!(runner.count.load(Ordering::SeqCst), 0);
+        let trigger = receiver.recv().await.unwrap();
+        assert_eq!(trigger.state, ExecutionState::Failed);
+        assert!(trigger.stats.deadline_overrun.unwrap() >= Duration::from_secs(5 * 60));
+    }
+
+    /// runs until it is cancelled
+    #[derive(Debug)]
+    struct CooperativeRunner {}
+
+    #[async_trait]
+    impl Runnable for CooperativeRunner {
+        async fn run(&self, context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
+            while !context.cancel.is_cancelled() {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+            Err(TaskError::Cancelled)
+        }
+    }
+
+    #[derive(Debug)]
+    struct FailingRunner {}
+
+    #[async_trait]
+    impl Runnable for FailingRunner {
+        async fn run(&self, _context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
+            Err(TaskError::UnexpectedError(anyhow::anyhow!(
+                "connection reset"
+            )))
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct CountingRunner {
+        count: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Runnable for CountingRunner {
+        async fn run(&self, _context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            Ok(None)
+        }
+    }
+
     async fn run(counter: Rc<RefCell<u8>>) -> Result<Option<()>, TaskError> {
         let mut counter_ref = counter.borrow_mut();
         if *counter_ref > 0 {
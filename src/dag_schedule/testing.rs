@@ -0,0 +1,394 @@
+use crate::dag_schedule::context::TaskContext;
+use crate::dag_schedule::schedule::{Schedule, TaskDependenciesSpecs, TaskSpec, TaskSpecRef};
+use crate::dag_schedule::task::{ExecutionMode, RetryOptions, Runnable, StatsMap, TaskError};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Behavior of a [`FakeRunnable`] on each execution
+#[derive(Clone, Debug)]
+pub enum FakeBehavior {
+    Succeed,
+    /// fails with a retryable error on the first attempts, succeeds afterwards
+    FailTimes(usize),
+    /// fails with a fatal error on every attempt
+    Fail,
+    /// succeeds after sleeping, stops early with [`TaskError::Cancelled`] if cancelled
+    Sleep(Duration),
+    Panic,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FakeEvent {
+    Started,
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+/// Event of a fake, `at` is the time passed since the log was created
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedEvent {
+    pub task: String,
+    pub event: FakeEvent,
+    pub at: Duration,
+}
+
+/// Events of all fakes of a harness in the order they happened
+#[derive(Clone, Debug)]
+pub struct ExecutionLog {
+    created: Instant,
+    events: Arc<Mutex<Vec<RecordedEvent>>>,
+}
+
+impl Default for ExecutionLog {
+    fn default() -> Self {
+        ExecutionLog {
+            created: Instant::now(),
+            events: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+}
+
+impl ExecutionLog {
+    pub fn new() -> Self {
+        ExecutionLog::default()
+    }
+
+    fn record(&self, task: &str, event: FakeEvent) {
+        self.events.lock().unwrap().push(RecordedEvent {
+            task: task.to_string(),
+            event,
+            at: self.created.elapsed(),
+        });
+    }
+
+    pub fn events(&self) -> Vec<RecordedEvent> {
+        self.events.lock().unwrap().clone()
+    }
+
+    /// names of the tasks in the order their attempts started, retried tasks appear several times
+    pub fn started(&self) -> Vec<String> {
+        self.events()
+            .into_iter()
+            .filter(|e| e.event == FakeEvent::Started)
+            .map(|e| e.task)
+            .collect()
+    }
+
+    /// start times of all attempts of the task
+    pub fn starts_of(&self, task: &str) -> Vec<Duration> {
+        self.events()
+            .into_iter()
+            .filter(|e| e.task == task && e.event == FakeEvent::Started)
+            .map(|e| e.at)
+            .collect()
+    }
+
+    /// maximum number of attempts running at the same time, panicking attempts count until the
+    /// end of the run since they record no end
+    pub fn max_concurrency(&self) -> usize {
+        let mut running: usize = 0;
+        let mut max_running = 0;
+        for event in self.events() {
+            match event.event {
+                FakeEvent::Started => {
+                    running += 1;
+                    max_running = max_running.max(running);
+                }
+                _ => running = running.saturating_sub(1),
+            }
+        }
+        max_running
+    }
+}
+
+/// Runnable whose behavior is configured by the test, records its events on the log
+#[derive(Debug)]
+pub struct FakeRunnable {
+    name: String,
+    behavior: FakeBehavior,
+    attempts: AtomicUsize,
+    log: ExecutionLog,
+}
+
+impl FakeRunnable {
+    pub fn new(name: &str, behavior: FakeBehavior, log: ExecutionLog) -> Self {
+        FakeRunnable {
+            name: name.to_string(),
+            behavior,
+            attempts: AtomicUsize::new(0),
+            log,
+        }
+    }
+
+    pub fn attempts(&self) -> usize {
+        self.attempts.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl Runnable for FakeRunnable {
+    async fn run(&self, context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
+        let attempt = self.attempts.fetch_add(1, Ordering::SeqCst) + 1;
+        self.log.record(&self.name, FakeEvent::Started);
+        let result = match &self.behavior {
+            FakeBehavior::Succeed => Ok(None),
+            FakeBehavior::FailTimes(failures) if attempt <= *failures => Err(
+                TaskError::UnexpectedError(anyhow::anyhow!("attempt {} failed", attempt)),
+            ),
+            FakeBehavior::FailTimes(_) => Ok(None),
+            FakeBehavior::Fail => Err(TaskError::UnexpectedError(
+                serde_json::from_str::<u32>("<html>").unwrap_err().into(),
+            )),
+            FakeBehavior::Sleep(duration) => {
+                tokio::select! {
+                    _ = tokio::time::sleep(*duration) => Ok(None),
+                    _ = context.cancel.cancelled() => Err(TaskError::Cancelled),
+                }
+            }
+            FakeBehavior::Panic => panic!("fake task {} panicked", self.name),
+        };
+        let event = match &result {
+            Ok(_) => FakeEvent::Succeeded,
+            Err(TaskError::Cancelled) => FakeEvent::Cancelled,
+            Err(_) => FakeEvent::Failed,
+        };
+        self.log.record(&self.name, event);
+        result
+    }
+}
+
+/// Deterministic test harness for schedules: builds a schedule of fakes from task names, the fakes
+/// record when they start and end on the shared log. Run tests with paused time
+/// (`#[tokio::test(start_paused = true)]`), so that sleeps and back offs advance the clock exactly.
+#[derive(Default)]
+pub struct TestDag {
+    log: ExecutionLog,
+    tasks: Vec<(TaskSpecRef, Vec<String>)>,
+    runnables: HashMap<String, Arc<FakeRunnable>>,
+}
+
+impl TestDag {
+    pub fn new() -> Self {
+        TestDag::default()
+    }
+
+    pub fn with_task(self, name: &str, behavior: FakeBehavior, dependencies: &[&str]) -> Self {
+        self.with_configured_task(name, behavior, dependencies, |spec| spec)
+    }
+
+    /// Adds a task whose spec is adjusted before it is scheduled, e.g. to set retries or resources
+    pub fn with_configured_task(
+        mut self,
+        name: &str,
+        behavior: FakeBehavior,
+        dependencies: &[&str],
+        configure: impl FnOnce(TaskSpec) -> TaskSpec,
+    ) -> Self {
+        let runnable = Arc::new(FakeRunnable::new(name, behavior, self.log.clone()));
+        let spec = TaskSpec::new(
+            name.to_string(),
+            RetryOptions::default(),
+            ExecutionMode::Once,
+            Arc::new(Default::default()),
+            runnable.clone(),
+        );
+        self.runnables.insert(name.to_string(), runnable);
+        self.tasks.push((
+            TaskSpecRef::from(configure(spec)),
+            dependencies.iter().map(|d| d.to_string()).collect(),
+        ));
+        self
+    }
+
+    pub fn log(&self) -> ExecutionLog {
+        self.log.clone()
+    }
+
+    pub fn runnable(&self, name: &str) -> Arc<FakeRunnable> {
+        self.runnables[name].clone()
+    }
+
+    /// Schedules and checks the tasks on the given schedule, panics on unknown dependencies
+    #[allow(clippy::mutable_key_type)]
+    pub async fn build(&self, mut schedule: Schedule) -> Schedule {
+        let specs_by_name: HashMap<&str, &TaskSpecRef> = self
+            .tasks
+            .iter()
+            .map(|(spec, _)| (spec.name.as_str(), spec))
+            .collect();
+        let task_specs: TaskDependenciesSpecs = self
+            .tasks
+            .iter()
+            .map(|(spec, dependencies)| {
+                let dependencies = dependencies
+                    .iter()
+                    .map(|d| (*specs_by_name[d.as_str()]).clone())
+                    .collect();
+                (spec.clone(), dependencies)
+            })
+            .collect();
+        schedule.schedule_tasks(task_specs).await.unwrap();
+        schedule.run_checks().await.unwrap();
+        schedule
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dag_schedule::concurrency::ConcurrencyLimits;
+    use crate::dag_schedule::schedule::Schedule;
+    use crate::dag_schedule::task::{BackOff, ExecutionState, RetryOptions};
+    use crate::dag_schedule::testing::{FakeBehavior, FakeEvent, TestDag};
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn exponential_back_off_waits_exact_times() {
+        let retry = RetryOptions::new(
+            3,
+            BackOff::Exponential {
+                base: 2,
+                min_back_off: ms(100),
+                max_back_off: ms(250),
+            },
+        );
+        let dag = TestDag::new().with_configured_task(
+            "flaky",
+            FakeBehavior::FailTimes(3),
+            &[],
+            |mut spec| {
+                spec.retry_options = retry;
+                spec
+            },
+        );
+        let mut schedule = dag.build(Schedule::new()).await;
+
+        schedule.run_schedule().await.unwrap();
+
+        // back offs of 100, 200 and 250 (capped) milliseconds
+        assert_eq!(
+            dag.log().starts_of("flaky"),
+            vec![ms(0), ms(100), ms(300), ms(550)]
+        );
+        let result = &schedule.results()["flaky"];
+        assert_eq!(result.state, ExecutionState::Finished);
+        assert_eq!(result.stats.attempts, 4);
+        assert_eq!(result.stats.retries, Some(3));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn fan_in_starts_once_after_slowest_upstream() {
+        let dag = TestDag::new()
+            .with_task("source", FakeBehavior::Succeed, &[])
+            .with_task("fast", FakeBehavior::Sleep(ms(100)), &["source"])
+            .with_task("slow", FakeBehavior::Sleep(ms(300)), &["source"])
+            .with_task("join", FakeBehavior::Succeed, &["fast", "slow"]);
+        let mut schedule = dag.build(Schedule::new()).await;
+
+        schedule.run_schedule().await.unwrap();
+
+        let log = dag.log();
+        assert_eq!(log.starts_of("join"), vec![ms(300)]);
+        assert_eq!(log.started().first().unwrap(), "source");
+        assert_eq!(log.started().last().unwrap(), "join");
+        assert_eq!(log.max_concurrency(), 2);
+        assert_eq!(dag.runnable("join").attempts(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn resource_pool_serializes_tasks() {
+        let dag = ["first", "second", "third"]
+            .into_iter()
+            .fold(TestDag::new(), |dag, name| {
+                dag.with_configured_task(name, FakeBehavior::Sleep(ms(100)), &[], |spec| {
+                    spec.with_resources(vec!["api".to_string()])
+                })
+            });
+        let limits = ConcurrencyLimits::new(None, HashMap::from([("api".to_string(), 1)]));
+        let mut schedule = dag
+            .build(Schedule::new().with_concurrency_limits(limits))
+            .await;
+
+        schedule.run_schedule().await.unwrap();
+
+        let log = dag.log();
+        assert_eq!(log.max_concurrency(), 1);
+        let mut starts: Vec<Duration> = ["first", "second", "third"]
+            .iter()
+            .flat_map(|name| log.starts_of(name))
+            .collect();
+        starts.sort();
+        assert_eq!(starts, vec![ms(0), ms(100), ms(200)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failures_and_panics_skip_dependents() {
+        let retry = RetryOptions::new(2, BackOff::Constant { back_off: ms(50) });
+        let dag = TestDag::new()
+            .with_task("source", FakeBehavior::Succeed, &[])
+            .with_configured_task("fatal", FakeBehavior::Fail, &["source"], |mut spec| {
+                spec.retry_options = retry;
+                spec
+            })
+            .with_task("panicking", FakeBehavior::Panic, &["source"])
+            .with_task("after_fatal", FakeBehavior::Succeed, &["fatal"])
+            .with_task("after_panic", FakeBehavior::Succeed, &["panicking"]);
+        let mut schedule = dag.build(Schedule::new()).await;
+
+        schedule.run_schedule().await.unwrap();
+
+        let states: HashMap<&str, ExecutionState> = schedule
+            .results()
+            .iter()
+            .map(|(name, result)| (name.as_str(), result.state))
+            .collect();
+        assert_eq!(
+            states,
+            HashMap::from([
+                ("source", ExecutionState::Finished),
+                ("fatal", ExecutionState::Failed),
+                ("panicking", ExecutionState::Failed),
+                ("after_fatal", ExecutionState::Skipped),
+                ("after_panic", ExecutionState::Skipped),
+            ])
+        );
+        // fatal errors are not retried
+        assert_eq!(dag.runnable("fatal").attempts(), 1);
+        assert_eq!(dag.runnable("panicking").attempts(), 1);
+        assert!(dag.log().starts_of("after_fatal").is_empty());
+        let fatal_events: Vec<FakeEvent> = dag
+            .log()
+            .events()
+            .into_iter()
+            .filter(|e| e.task == "fatal")
+            .map(|e| e.event)
+            .collect();
+        assert_eq!(fatal_events, vec![FakeEvent::Started, FakeEvent::Failed]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn timeout_cancels_sleeping_task_at_exact_time() {
+        let dag = TestDag::new().with_configured_task(
+            "slow",
+            FakeBehavior::Sleep(Duration::from_secs(3600)),
+            &[],
+            |spec| spec.with_timeout(Some(ms(500))),
+        );
+        let mut schedule = dag.build(Schedule::new()).await;
+
+        schedule.run_schedule().await.unwrap();
+
+        assert_eq!(schedule.results()["slow"].state, ExecutionState::Failed);
+        assert_eq!(dag.log().starts_of("slow"), vec![ms(0)]);
+        assert_eq!(schedule.results()["slow"].stats.runtime, ms(500));
+    }
+}
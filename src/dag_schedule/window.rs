@@ -0,0 +1,141 @@
+use chrono::{DateTime, Days, NaiveTime, Utc};
+
+/// Daily time window (UTC) in which a task may run, e.g. after market close until the next open.
+/// A task is only started after `not_before` and must finish before `deadline`.
+/// If `deadline` is before `not_before`, the window spans midnight.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TimeWindow {
+    pub not_before: Option<NaiveTime>,
+    pub deadline: Option<NaiveTime>,
+}
+
+impl TimeWindow {
+    pub fn new(not_before: Option<NaiveTime>, deadline: Option<NaiveTime>) -> Self {
+        TimeWindow {
+            not_before,
+            deadline,
+        }
+    }
+
+    /// A window without `not_before` is always open
+    pub fn is_open(&self, now: DateTime<Utc>) -> bool {
+        let Some(not_before) = self.not_before else {
+            return true;
+        };
+        let time = now.time();
+        match self.deadline {
+            None => time >= not_before,
+            Some(deadline) if not_before <= deadline => not_before <= time && time < deadline,
+            Some(deadline) => time >= not_before || time < deadline,
+        }
+    }
+
+    /// `now` if the window is open, otherwise the next time the window opens
+    pub fn opens_at(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self.not_before {
+            Some(not_before) if !self.is_open(now) => next_occurrence(now, not_before),
+            _ => now,
+        }
+    }
+
+    /// the first deadline after the given point in time, none if the window has no deadline
+    pub fn deadline_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.deadline
+            .map(|deadline| next_occurrence(after, deadline))
+    }
+
+    /// deadline of the tasks of a run started at the given point in time: the first deadline after
+    /// the window opened for the run, so that a task started late in the run still misses it
+    pub fn deadline_of_run(&self, run_started_at: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.deadline_after(self.opens_at(run_started_at))
+    }
+}
+
+/// the next point in time after `after` with the given time of day
+fn next_occurrence(after: DateTime<Utc>, time: NaiveTime) -> DateTime<Utc> {
+    let same_day = after.date_naive().and_time(time).and_utc();
+    if same_day > after {
+        same_day
+    } else {
+        same_day
+            .checked_add_days(Days::new(1))
+            .expect("Adding one day must always work, given the operating date context.")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dag_schedule::window::TimeWindow;
+    use chrono::{NaiveTime, TimeZone, Utc};
+
+    fn time(hour: u32, minute: u32) -> Option<NaiveTime> {
+        NaiveTime::from_hms_opt(hour, minute, 0)
+    }
+
+    #[test]
+    fn window_spanning_midnight_is_open_over_night() {
+        // after us market close until the next open
+        let window = TimeWindow::new(time(21, 0), time(13, 30));
+
+        assert!(window.is_open(Utc.with_ymd_and_hms(2024, 3, 1, 22, 0, 0).unwrap()));
+        assert!(window.is_open(Utc.with_ymd_and_hms(2024, 3, 2, 3, 0, 0).unwrap()));
+        assert!(!window.is_open(Utc.with_ymd_and_hms(2024, 3, 2, 15, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn closed_window_opens_at_next_not_before() {
+        let window = TimeWindow::new(time(21, 0), time(13, 30));
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 15, 0, 0).unwrap();
+
+        assert_eq!(
+            window.opens_at(now),
+            Utc.with_ymd_and_hms(2024, 3, 1, 21, 0, 0).unwrap()
+        );
+        assert_eq!(
+            window.deadline_after(window.opens_at(now)),
+            Some(Utc.with_ymd_and_hms(2024, 3, 2, 13, 30, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn open_window_opens_now() {
+        let window = TimeWindow::new(time(8, 0), None);
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 9, 0, 0).unwrap();
+
+        assert_eq!(window.opens_at(now), now);
+        assert_eq!(window.deadline_after(now), None);
+        assert_eq!(
+            window.opens_at(Utc.with_ymd_and_hms(2024, 3, 1, 7, 0, 0).unwrap()),
+            Utc.with_ymd_and_hms(2024, 3, 1, 8, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn window_without_not_before_is_always_open() {
+        let window = TimeWindow::new(None, time(13, 30));
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 14, 0, 0).unwrap();
+
+        assert!(window.is_open(now));
+        assert_eq!(
+            window.deadline_after(now),
+            Some(Utc.with_ymd_and_hms(2024, 3, 2, 13, 30, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn deadline_of_run_is_taken_from_its_start() {
+        let run_started_at = Utc.with_ymd_and_hms(2024, 3, 1, 10, 0, 0).unwrap();
+        let today = Some(Utc.with_ymd_and_hms(2024, 3, 1, 13, 30, 0).unwrap());
+
+        assert_eq!(
+            TimeWindow::new(None, time(13, 30)).deadline_of_run(run_started_at),
+            today
+        );
+        // started before the window opened, so the deadline after its opening applies
+        assert_eq!(
+            TimeWindow::new(time(21, 0), time(13, 30))
+                .deadline_of_run(Utc.with_ymd_and_hms(2024, 3, 1, 15, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2024, 3, 2, 13, 30, 0).unwrap())
+        );
+    }
+}
@@ -0,0 +1,544 @@
+use crate::dag_schedule::context::TaskContext;
+use crate::dag_schedule::task::{custom_stats_to_json, error_chain, Runnable, StatsMap, TaskError};
+use crate::database::task_queue_service::{
+    QueueEntry, QueueResult, QueueState, TaskQueueServiceTrait,
+};
+use anyhow::anyhow;
+use async_trait::async_trait;
+use chrono::Utc;
+use futures::future::join_all;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// Intervals used by the scheduler and the workers to communicate via the task queue
+#[derive(Clone, Copy, Debug)]
+pub struct QueueOptions {
+    /// how often the scheduler checks the result and idle workers check for queued tasks
+    pub poll_interval: Duration,
+    /// how often a worker signals that it still executes a task
+    pub heartbeat_interval: Duration,
+    /// a task whose worker did not send a heartbeat for this duration is queued again
+    pub stale_after: Duration,
+}
+
+impl Default for QueueOptions {
+    fn default() -> Self {
+        QueueOptions {
+            poll_interval: Duration::from_secs(1),
+            heartbeat_interval: Duration::from_secs(10),
+            stale_after: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Runnable of the scheduler in distributed mode: queues the task and waits until a worker
+/// executed it. Outputs cannot be passed between processes, downstream tasks receive none.
+pub struct QueuedRunnable {
+    pipeline: String,
+    task_name: String,
+    queue: Arc<dyn TaskQueueServiceTrait>,
+    options: QueueOptions,
+}
+
+impl QueuedRunnable {
+    pub fn new(
+        pipeline: String,
+        task_name: String,
+        queue: Arc<dyn TaskQueueServiceTrait>,
+        options: QueueOptions,
+    ) -> Self {
+        QueuedRunnable {
+            pipeline,
+            task_name,
+            queue,
+            options,
+        }
+    }
+
+    /// Queues the task again if its worker stopped sending heartbeats, e.g. because it died
+    async fn requeue_if_stale(&self, entry: &QueueEntry) {
+        let stale = entry.heartbeat_at.is_some_and(|heartbeat_at| {
+            (Utc::now() - heartbeat_at)
+                .to_std()
+                .is_ok_and(|age| age > self.options.stale_after)
+        });
+        if !stale {
+            return;
+        }
+        match self
+            .queue
+            .requeue_stale(entry.queue_id, self.options.stale_after)
+            .await
+        {
+            Ok(true) => warn!(
+                "Worker of task {} stopped sending heartbeats, task was queued again",
+                self.task_name
+            ),
+            Ok(false) => {}
+            Err(e) => warn!("Failed to queue task {} again: {:#}", self.task_name, e),
+        }
+    }
+}
+
+impl fmt::Debug for QueuedRunnable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("QueuedRunnable")
+            .field("pipeline", &self.pipeline)
+            .field("task_name", &self.task_name)
+            .finish()
+    }
+}
+
+#[async_trait]
+impl Runnable for QueuedRunnable {
+    #[tracing::instrument(name = "Run QueuedRunnable", skip(self, context))]
+    async fn run(&self, context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
+        let queue_id = self
+            .queue
+            .enqueue(&self.pipeline, &self.task_name)
+            .await
+            .map_err(TaskError::UnexpectedError)?;
+        info!("Task {} queued as {}", self.task_name, queue_id);
+        loop {
+            tokio::select! {
+                _ = context.cancel.cancelled() => {
+                    if let Err(e) = self.queue.cancel(queue_id).await {
+                        warn!("Failed to cancel queued task {}: {:#}", self.task_name, e);
+                    }
+                    return Err(TaskError::Cancelled);
+                }
+                _ = tokio::time::sleep(self.options.poll_interval) => {}
+            }
+            let entry = match self.queue.get_entry(queue_id).await {
+                Ok(Some(entry)) => entry,
+                Ok(None) => {
+                    return Err(TaskError::UnexpectedError(anyhow!(
+                        "Queue entry {} of task {} does not exist anymore",
+                        queue_id,
+                        self.task_name
+                    )))
+                }
+                // the worker keeps running, try again with the next poll
+                Err(e) => {
+                    warn!("Failed to poll task {}: {:#}", self.task_name, e);
+                    continue;
+                }
+            };
+            match entry.state {
+                QueueState::Queued => {}
+                QueueState::Running => self.requeue_if_stale(&entry).await,
+                QueueState::Finished => return Ok(entry.custom_stats.map(stats_from_json)),
+                QueueState::Failed => {
+                    return Err(TaskError::WorkerError {
+                        message: entry.error.unwrap_or_default(),
+                        retryable: entry.retryable,
+                    })
+                }
+                QueueState::Cancelled => return Err(TaskError::Cancelled),
+            }
+        }
+    }
+}
+
+fn stats_from_json(stats: serde_json::Value) -> StatsMap {
+    let map = match stats {
+        serde_json::Value::Object(map) => map
+            .into_iter()
+            .map(|(key, value)| (key, Arc::new(value) as _))
+            .collect(),
+        _ => HashMap::new(),
+    };
+    Arc::new(Mutex::new(map))
+}
+
+/// Claims queued tasks and executes their actions, any number of workers can share the queue.
+/// Each slot executes one task at a time.
+pub struct Worker {
+    id: Uuid,
+    queue: Arc<dyn TaskQueueServiceTrait>,
+    actions: HashMap<String, Arc<dyn Runnable>>,
+    options: QueueOptions,
+    slots: usize,
+}
+
+impl Worker {
+    /// the worker executes the tasks with the names of the given actions
+    pub fn new(
+        queue: Arc<dyn TaskQueueServiceTrait>,
+        actions: HashMap<String, Arc<dyn Runnable>>,
+        options: QueueOptions,
+    ) -> Self {
+        Worker {
+            id: Uuid::new_v4(),
+            queue,
+            actions,
+            options,
+            slots: 1,
+        }
+    }
+
+    /// number of tasks executed at the same time, at least one
+    pub fn with_slots(mut self, slots: usize) -> Self {
+        self.slots = slots.max(1);
+        self
+    }
+
+    /// Executes queued tasks until cancelled, running tasks are cancelled as well
+    #[tracing::instrument(skip_all, fields(worker = %self.id))]
+    pub async fn run(&self, cancel: CancellationToken) {
+        info!(
+            "Worker {} started with {} slots for tasks: {}",
+            self.id,
+            self.slots,
+            self.task_names().join(", ")
+        );
+        join_all((0..self.slots).map(|_| self.run_slot(&cancel))).await;
+        info!("Worker {} stopped", self.id);
+    }
+
+    fn task_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.actions.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    async fn run_slot(&self, cancel: &CancellationToken) {
+        let task_names = self.task_names();
+        while !cancel.is_cancelled() {
+            match self.queue.claim_next(self.id, &task_names).await {
+                Ok(Some(entry)) => {
+                    self.execute(entry, cancel).await;
+                    continue;
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Failed to claim a queued task: {:#}", e),
+            }
+            tokio::select! {
+                _ = cancel.cancelled() => {}
+                _ = tokio::time::sleep(self.options.poll_interval) => {}
+            }
+        }
+    }
+
+    /// runs the action of the task while sending heartbeats and reports its result
+    async fn execute(&self, entry: QueueEntry, cancel: &CancellationToken) {
+        info!("Worker {} executes task {}", self.id, entry.task_name);
+        let result = match self.actions.get(&entry.task_name) {
+            Some(action) => {
+                let context = TaskContext::new(cancel.child_token(), HashMap::new());
+                let execution = action.run(context.clone());
+                let heartbeat = self.send_heartbeats(entry.queue_id, &context.cancel);
+                tokio::select! {
+                    result = execution => to_queue_result(result).await,
+                    _ = heartbeat => unreachable!("heartbeats are sent until the execution finished"),
+                }
+            }
+            None => QueueResult {
+                state: QueueState::Failed,
+                error: Some(format!("Task {} is unknown to the worker", entry.task_name)),
+                retryable: false,
+                custom_stats: None,
+            },
+        };
+        // a task interrupted by stopping the worker is left to another worker
+        if cancel.is_cancelled() && result.state == QueueState::Cancelled {
+            info!(
+                "Worker {} stops, task {} is queued again",
+                self.id, entry.task_name
+            );
+            if let Err(e) = self
+                .queue
+                .requeue_stale(entry.queue_id, Duration::ZERO)
+                .await
+            {
+                error!("Failed to queue task {} again: {:#}", entry.task_name, e);
+            }
+            return;
+        }
+        if let Err(e) = self.queue.complete(entry.queue_id, self.id, &result).await {
+            error!(
+                "Failed to report the result of task {}: {:#}",
+                entry.task_name, e
+            );
+        }
+    }
+
+    /// cancels the execution once the task is not running on this worker anymore
+    async fn send_heartbeats(&self, queue_id: Uuid, cancel: &CancellationToken) {
+        loop {
+            tokio::time::sleep(self.options.heartbeat_interval).await;
+            match self.queue.heartbeat(queue_id, self.id).await {
+                Ok(QueueState::Running) => {}
+                Ok(state) => {
+                    if !cancel.is_cancelled() {
+                        info!("Task {} is {}, cancel it", queue_id, state.as_str());
+                        cancel.cancel();
+                    }
+                }
+                Err(e) => warn!("Failed to send heartbeat of task {}: {:#}", queue_id, e),
+            }
+        }
+    }
+}
+
+async fn to_queue_result(result: Result<Option<StatsMap>, TaskError>) -> QueueResult {
+    match result {
+        Ok(stats) => QueueResult {
+            state: QueueState::Finished,
+            error: None,
+            retryable: false,
+            custom_stats: match stats {
+                Some(stats) => Some(custom_stats_to_json(&stats).await),
+                None => None,
+            },
+        },
+        Err(TaskError::Cancelled) => QueueResult {
+            state: QueueState::Cancelled,
+            error: None,
+            retryable: false,
+            custom_stats: None,
+        },
+        Err(e) => QueueResult {
+            state: QueueState::Failed,
+            error: Some(error_chain(&e)),
+            retryable: e.is_retryable(),
+            custom_stats: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dag_schedule::context::TaskContext;
+    use crate::dag_schedule::task::{custom_stats_to_json, Runnable, StatsMap, Task, TaskError};
+    use crate::dag_schedule::worker::{QueueOptions, QueuedRunnable, Worker};
+    use crate::database::task_queue_service::{
+        MockTaskQueueServiceTrait, QueueEntry, QueueResult, QueueState,
+    };
+    use async_trait::async_trait;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::sync::{mpsc, Mutex};
+    use tokio_util::sync::CancellationToken;
+    use uuid::Uuid;
+
+    fn fast_options() -> QueueOptions {
+        QueueOptions {
+            poll_interval: Duration::from_millis(5),
+            heartbeat_interval: Duration::from_millis(5),
+            stale_after: Duration::from_secs(60),
+        }
+    }
+
+    fn create_entry(queue_id: Uuid, state: QueueState) -> QueueEntry {
+        QueueEntry {
+            queue_id,
+            pipeline: "default".to_string(),
+            task_name: "collector".to_string(),
+            state,
+            worker_id: None,
+            heartbeat_at: None,
+            error: None,
+            retryable: false,
+            custom_stats: None,
+        }
+    }
+
+    #[derive(Debug)]
+    struct RowsRunner {}
+
+    #[async_trait]
+    impl Runnable for RowsRunner {
+        async fn run(&self, _context: TaskContext) -> Result<Option<StatsMap>, TaskError> {
+            let stats: StatsMap = Arc::new(Mutex::new(HashMap::new()));
+            stats
+                .lock()
+                .await
+                .insert("rows".to_string(), Arc::new(42_usize));
+            Ok(Some(stats))
+        }
+    }
+
+    #[tokio::test]
+    async fn queued_runnable_returns_result_of_worker() {
+        let queue_id = Uuid::new_v4();
+        let polls = Arc::new(AtomicUsize::new(0));
+        let mut queue = MockTaskQueueServiceTrait::new();
+        queue
+            .expect_enqueue()
+            .times(1)
+            .returning(move |_, _| Box::pin(async move { Ok(queue_id) }));
+        let polled = polls.clone();
+        queue.expect_get_entry().returning(move |_| {
+            let state = match polled.fetch_add(1, Ordering::SeqCst) {
+                0 => QueueState::Queued,
+                1 => QueueState::Running,
+                _ => QueueState::Finished,
+            };
+            let mut entry = create_entry(queue_id, state);
+            entry.custom_stats = Some(serde_json::json!({"rows": 42}));
+            Box::pin(async move { Ok(Some(entry)) })
+        });
+        let runnable = QueuedRunnable::new(
+            "default".to_string(),
+            "collector".to_string(),
+            Arc::new(queue),
+            fast_options(),
+        );
+
+        let stats = runnable.run(TaskContext::default()).await.unwrap().unwrap();
+
+        assert_eq!(polls.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            custom_stats_to_json(&stats).await,
+            serde_json::json!({"rows": 42})
+        );
+    }
+
+    #[tokio::test]
+    async fn queued_runnable_fails_with_error_of_worker() {
+        let queue_id = Uuid::new_v4();
+        let mut queue = MockTaskQueueServiceTrait::new();
+        queue
+            .expect_enqueue()
+            .returning(move |_, _| Box::pin(async move { Ok(queue_id) }));
+        queue.expect_get_entry().returning(move |_| {
+            let mut entry = create_entry(queue_id, QueueState::Failed);
+            entry.error = Some("Nothing was executed".to_string());
+            entry.retryable = true;
+            Box::pin(async move { Ok(Some(entry)) })
+        });
+        let runnable = QueuedRunnable::new(
+            "default".to_string(),
+            "collector".to_string(),
+            Arc::new(queue),
+            fast_options(),
+        );
+
+        let error = runnable.run(TaskContext::default()).await.unwrap_err();
+
+        assert!(error.is_retryable());
+        assert!(
+            matches!(error, TaskError::WorkerError { message, .. } if message == "Nothing was executed")
+        );
+    }
+
+    #[tokio::test]
+    async fn cancelled_queued_runnable_cancels_queue_entry() {
+        let mut queue = MockTaskQueueServiceTrait::new();
+        queue
+            .expect_enqueue()
+            .returning(|_, _| Box::pin(async { Ok(Uuid::new_v4()) }));
+        queue.expect_get_entry().returning(|queue_id| {
+            let entry = create_entry(queue_id, QueueState::Queued);
+            Box::pin(async move { Ok(Some(entry)) })
+        });
+        queue
+            .expect_cancel()
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(()) }));
+        let runnable = QueuedRunnable::new(
+            "default".to_string(),
+            "collector".to_string(),
+            Arc::new(queue),
+            fast_options(),
+        );
+        let context = TaskContext::default();
+        let cancel = context.cancel.clone();
+
+        let handle = tokio::spawn(async move { runnable.run(context).await });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        cancel.cancel();
+
+        assert!(matches!(handle.await.unwrap(), Err(TaskError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn timed_out_queued_task_cancels_queue_entry() {
+        let mut queue = MockTaskQueueServiceTrait::new();
+        queue
+            .expect_enqueue()
+            .returning(|_, _| Box::pin(async { Ok(Uuid::new_v4()) }));
+        queue.expect_get_entry().returning(|queue_id| {
+            let entry = create_entry(queue_id, QueueState::Running);
+            Box::pin(async move { Ok(Some(entry)) })
+        });
+        queue
+            .expect_cancel()
+            .times(1)
+            .returning(|_| Box::pin(async { Ok(()) }));
+        let runnable = QueuedRunnable::new(
+            "default".to_string(),
+            "collector".to_string(),
+            Arc::new(queue),
+            fast_options(),
+        );
+        let task = Task::new(
+            "collector".to_string(),
+            Arc::new(runnable),
+            Arc::new(Default::default()),
+            None,
+        );
+        task.lock().await.timeout = Some(Duration::from_millis(20));
+        let (sender, _receiver) = mpsc::channel(1);
+
+        let result = task.lock().await.run(sender).await;
+
+        assert!(matches!(result, Err(TaskError::Timeout(_))));
+    }
+
+    #[tokio::test]
+    async fn worker_executes_claimed_task_and_reports_result() {
+        let queue_id = Uuid::new_v4();
+        let cancel = CancellationToken::new();
+        let claims = Arc::new(AtomicUsize::new(0));
+        let mut queue = MockTaskQueueServiceTrait::new();
+        let claimed = claims.clone();
+        queue.expect_claim_next().returning(move |_, task_names| {
+            assert_eq!(task_names, ["collector".to_string()]);
+            let entry = match claimed.fetch_add(1, Ordering::SeqCst) {
+                0 => Some(create_entry(queue_id, QueueState::Running)),
+                _ => None,
+            };
+            Box::pin(async move { Ok(entry) })
+        });
+        queue
+            .expect_heartbeat()
+            .returning(|_, _| Box::pin(async { Ok(QueueState::Running) }));
+        let stop = cancel.clone();
+        queue
+            .expect_complete()
+            .times(1)
+            .returning(move |id, _, result| {
+                assert_eq!(id, queue_id);
+                assert_eq!(
+                    *result,
+                    QueueResult {
+                        state: QueueState::Finished,
+                        error: None,
+                        retryable: false,
+                        custom_stats: Some(serde_json::json!({"rows": 42})),
+                    }
+                );
+                stop.cancel();
+                Box::pin(async { Ok(()) })
+            });
+        let actions: HashMap<String, Arc<dyn Runnable>> =
+            HashMap::from([("collector".to_string(), Arc::new(RowsRunner {}) as _)]);
+        let worker = Worker::new(Arc::new(queue), actions, fast_options());
+
+        tokio::time::timeout(Duration::from_secs(3), worker.run(cancel))
+            .await
+            .expect("worker must stop once cancelled");
+
+        assert!(claims.load(Ordering::SeqCst) >= 1);
+    }
+}
@@ -1,3 +1,7 @@
 pub mod master_data_service;
 pub mod polygon_dividends_service;
+pub mod raw_data_listener;
+pub mod run_history_service;
+pub mod task_control_service;
+pub mod task_queue_service;
 pub mod warden_service;
@@ -0,0 +1,117 @@
+use chrono::NaiveDate;
+use serde::Deserialize;
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+/// Channel the insert triggers of the raw data tables notify on
+pub const RAW_DATA_CHANNEL: &str = "raw_data_inserted";
+
+/// Payload of a notification, sent once per insert statement with at least one new row
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct RawDataEvent {
+    pub table: String,
+    pub rows: i64,
+    /// range of the date column of the table, e.g. the business date, if the table has one
+    pub min_date: Option<NaiveDate>,
+    pub max_date: Option<NaiveDate>,
+}
+
+/// Listens for inserts into the raw data tables and forwards the name of the table of each event.
+/// Returns when cancelled or when the receiver was dropped. Notifications sent while the connection
+/// is lost are missed, the listener reconnects on the next receive.
+pub async fn listen_raw_data_events(
+    pool: &PgPool,
+    tables: mpsc::Sender<String>,
+    cancel: CancellationToken,
+) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(RAW_DATA_CHANNEL).await?;
+    info!("Listening for new raw data on channel {}", RAW_DATA_CHANNEL);
+    loop {
+        let notification = tokio::select! {
+            notification = listener.recv() => notification,
+            _ = cancel.cancelled() => return Ok(()),
+        };
+        let notification = match notification {
+            Ok(notification) => notification,
+            Err(e) => {
+                warn!("Failed to receive raw data notification: {}", e);
+                continue;
+            }
+        };
+        let event: RawDataEvent = match serde_json::from_str(notification.payload()) {
+            Ok(event) => event,
+            Err(e) => {
+                warn!(
+                    "Ignore raw data notification with invalid payload {}: {}",
+                    notification.payload(),
+                    e
+                );
+                continue;
+            }
+        };
+        debug!(
+            "{} rows inserted into {} from {:?} to {:?}",
+            event.rows, event.table, event.min_date, event.max_date
+        );
+        if tables.send(event.table).await.is_err() {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::database::raw_data_listener::{listen_raw_data_events, RawDataEvent};
+    use chrono::NaiveDate;
+    use sqlx::{Pool, Postgres};
+    use std::time::Duration;
+    use tokio::sync::mpsc;
+    use tokio_util::sync::CancellationToken;
+
+    #[test]
+    fn payload_of_trigger_is_parsed() {
+        let event: RawDataEvent = serde_json::from_str(
+            r#"{"table" : "polygon_grouped_daily", "rows" : 3, "min_date" : "2024-03-01", "max_date" : "2024-03-04"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            event,
+            RawDataEvent {
+                table: "polygon_grouped_daily".to_string(),
+                rows: 3,
+                min_date: NaiveDate::from_ymd_opt(2024, 3, 1),
+                max_date: NaiveDate::from_ymd_opt(2024, 3, 4),
+            }
+        );
+    }
+
+    #[sqlx::test]
+    async fn inserts_into_raw_data_tables_are_forwarded(pool: Pool<Postgres>) {
+        let (sender, mut receiver) = mpsc::channel(10);
+        let cancel = CancellationToken::new();
+        let listener_pool = pool.clone();
+        let listener_cancel = cancel.clone();
+        let listener = tokio::spawn(async move {
+            listen_raw_data_events(&listener_pool, sender, listener_cancel).await
+        });
+        // give the listener time to subscribe
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        sqlx::query("INSERT INTO sec_companies (cik, sic, \"name\", ticker, exchange, state_of_incorporation) VALUES (1, 1, 'Apple', 'AAPL', 'Nasdaq', 'CA')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let table = tokio::time::timeout(Duration::from_secs(3), receiver.recv())
+            .await
+            .expect("insert must be notified")
+            .unwrap();
+        assert_eq!(table, "sec_companies");
+        cancel.cancel();
+        listener.await.unwrap().unwrap();
+    }
+}
@@ -0,0 +1,395 @@
+use crate::dag_schedule::task::ExecutionState;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, Pool, Postgres};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Clone, Debug)]
+pub struct RunHistoryService {
+    pool: Pool<Postgres>,
+}
+
+/// Final result of a single task within a run of the schedule
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskRunEntry {
+    pub run_id: Uuid,
+    pub task_name: String,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: DateTime<Utc>,
+    pub state: ExecutionState,
+    pub attempts: u32,
+    pub error: Option<String>,
+    pub custom_stats: Option<serde_json::Value>,
+}
+
+#[derive(Debug, FromRow)]
+struct TaskRunRow {
+    run_id: Uuid,
+    task_name: String,
+    started_at: Option<DateTime<Utc>>,
+    finished_at: DateTime<Utc>,
+    state: String,
+    attempts: i32,
+    error: Option<String>,
+    custom_stats: Option<String>,
+}
+
+impl TryFrom<TaskRunRow> for TaskRunEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(row: TaskRunRow) -> Result<Self, Self::Error> {
+        Ok(TaskRunEntry {
+            run_id: row.run_id,
+            task_name: row.task_name,
+            started_at: row.started_at,
+            finished_at: row.finished_at,
+            state: ExecutionState::try_from(row.state).map_err(anyhow::Error::msg)?,
+            attempts: u32::try_from(row.attempts)?,
+            error: row.error,
+            custom_stats: row
+                .custom_stats
+                .map(|stats| serde_json::from_str(&stats))
+                .transpose()?,
+        })
+    }
+}
+
+impl RunHistoryService {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    pub async fn start_schedule_run(
+        &self,
+        run_id: Uuid,
+        pipeline: &str,
+        started_at: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query("INSERT INTO schedule_runs (run_id, pipeline, started_at) VALUES ($1, $2, $3)")
+            .bind(run_id)
+            .bind(pipeline)
+            .bind(started_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn finish_schedule_run(
+        &self,
+        run_id: Uuid,
+        finished_at: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query("UPDATE schedule_runs SET finished_at = $2 WHERE run_id = $1")
+            .bind(run_id)
+            .bind(finished_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Stores the result of the task, a successful execution also becomes the last success of the task
+    /// if `updates_freshness` is set. Runs which only reload past data, like a backfill, leave it unset.
+    /// Tasks which finished without execution, e.g. because they were not due, do not count as success.
+    pub async fn save_task_run(
+        &self,
+        entry: &TaskRunEntry,
+        updates_freshness: bool,
+    ) -> Result<(), anyhow::Error> {
+        let query = r#"
+    INSERT INTO task_runs (
+        run_id,
+        task_name,
+        started_at,
+        finished_at,
+        state,
+        attempts,
+        error,
+        custom_stats
+    )
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8::jsonb)
+    "#;
+
+        sqlx::query(query)
+            .bind(entry.run_id)
+            .bind(&entry.task_name)
+            .bind(entry.started_at)
+            .bind(entry.finished_at)
+            .bind(entry.state.as_str())
+            .bind(i32::try_from(entry.attempts).unwrap_or(i32::MAX))
+            .bind(&entry.error)
+            .bind(entry.custom_stats.as_ref().map(|stats| stats.to_string()))
+            .execute(&self.pool)
+            .await?;
+        if updates_freshness
+            && entry.state == ExecutionState::Finished
+            && entry.started_at.is_some()
+        {
+            self.save_task_success(&entry.task_name, entry.finished_at)
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// keeps the later of the stored and the given time of success
+    async fn save_task_success(
+        &self,
+        task_name: &str,
+        succeeded_at: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        let query = r#"
+    INSERT INTO task_freshness (task_name, succeeded_at)
+    VALUES ($1, $2)
+    ON CONFLICT (task_name)
+    DO UPDATE SET succeeded_at = GREATEST(task_freshness.succeeded_at, EXCLUDED.succeeded_at)
+    "#;
+
+        sqlx::query(query)
+            .bind(task_name)
+            .bind(succeeded_at)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// time of the last successful execution by task name, tasks which never succeeded are missing
+    pub async fn get_last_successes(
+        &self,
+        task_names: &[String],
+    ) -> Result<HashMap<String, DateTime<Utc>>, anyhow::Error> {
+        let rows: Vec<(String, DateTime<Utc>)> = sqlx::query_as(
+            "SELECT task_name, succeeded_at FROM task_freshness WHERE task_name = ANY($1)",
+        )
+        .bind(task_names)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().collect())
+    }
+
+    /// id of the most recently started run of the schedule of the given pipeline
+    pub async fn get_last_run_id(&self, pipeline: &str) -> Result<Option<Uuid>, anyhow::Error> {
+        let run_id = sqlx::query_scalar(
+            "SELECT run_id FROM schedule_runs WHERE pipeline = $1 ORDER BY started_at DESC LIMIT 1",
+        )
+        .bind(pipeline)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(run_id)
+    }
+
+    pub async fn get_task_runs(&self, run_id: Uuid) -> Result<Vec<TaskRunEntry>, anyhow::Error> {
+        let query = r#"
+    SELECT run_id, task_name, started_at, finished_at, state, attempts, error, custom_stats::text
+    FROM task_runs
+    WHERE run_id = $1
+    ORDER BY finished_at
+    "#;
+
+        let rows: Vec<TaskRunRow> = sqlx::query_as(query)
+            .bind(run_id)
+            .fetch_all(&self.pool)
+            .await?;
+        rows.into_iter().map(TaskRunEntry::try_from).collect()
+    }
+}
+
+#[async_trait]
+#[cfg_attr(test, mockall::automock)]
+pub trait RunHistoryServiceTrait: Send + Sync {
+    async fn start_schedule_run(
+        &self,
+        run_id: Uuid,
+        pipeline: &str,
+        started_at: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error>;
+    async fn finish_schedule_run(
+        &self,
+        run_id: Uuid,
+        finished_at: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error>;
+    async fn save_task_run(
+        &self,
+        entry: &TaskRunEntry,
+        updates_freshness: bool,
+    ) -> Result<(), anyhow::Error>;
+    async fn get_last_successes(
+        &self,
+        task_names: &[String],
+    ) -> Result<HashMap<String, DateTime<Utc>>, anyhow::Error>;
+}
+
+#[async_trait]
+impl RunHistoryServiceTrait for RunHistoryService {
+    async fn start_schedule_run(
+        &self,
+        run_id: Uuid,
+        pipeline: &str,
+        started_at: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        self.start_schedule_run(run_id, pipeline, started_at).await
+    }
+
+    async fn finish_schedule_run(
+        &self,
+        run_id: Uuid,
+        finished_at: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        self.finish_schedule_run(run_id, finished_at).await
+    }
+
+    async fn save_task_run(
+        &self,
+        entry: &TaskRunEntry,
+        updates_freshness: bool,
+    ) -> Result<(), anyhow::Error> {
+        self.save_task_run(entry, updates_freshness).await
+    }
+
+    async fn get_last_successes(
+        &self,
+        task_names: &[String],
+    ) -> Result<HashMap<String, DateTime<Utc>>, anyhow::Error> {
+        self.get_last_successes(task_names).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dag_schedule::task::ExecutionState;
+    use crate::database::run_history_service::{RunHistoryService, TaskRunEntry};
+    use chrono::{DurationRound, TimeDelta, Utc};
+    use sqlx::{Pool, Postgres};
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    #[sqlx::test]
+    async fn task_runs_are_stored_and_loaded(pool: Pool<Postgres>) {
+        let service = RunHistoryService::new(pool);
+        let run_id = Uuid::new_v4();
+        // postgres stores microseconds only
+        let now = Utc::now()
+            .duration_trunc(TimeDelta::microseconds(1))
+            .unwrap();
+        let entry = TaskRunEntry {
+            run_id,
+            task_name: "task".to_string(),
+            started_at: Some(now),
+            finished_at: now,
+            state: ExecutionState::Failed,
+            attempts: 3,
+            error: Some("Database interaction failed: pool timed out".to_string()),
+            custom_stats: Some(serde_json::json!({"rows": 42})),
+        };
+
+        service
+            .start_schedule_run(run_id, "default", now)
+            .await
+            .unwrap();
+        service.save_task_run(&entry, true).await.unwrap();
+        service.finish_schedule_run(run_id, now).await.unwrap();
+
+        assert_eq!(service.get_task_runs(run_id).await.unwrap(), vec![entry]);
+    }
+
+    #[sqlx::test]
+    async fn last_run_is_the_latest_started_run(pool: Pool<Postgres>) {
+        let service = RunHistoryService::new(pool);
+        assert_eq!(service.get_last_run_id("default").await.unwrap(), None);
+
+        let older_run = Uuid::new_v4();
+        let newer_run = Uuid::new_v4();
+        let other_pipeline_run = Uuid::new_v4();
+        let now = Utc::now();
+        service
+            .start_schedule_run(newer_run, "default", now)
+            .await
+            .unwrap();
+        service
+            .start_schedule_run(older_run, "default", now - TimeDelta::hours(1))
+            .await
+            .unwrap();
+        service
+            .start_schedule_run(other_pipeline_run, "weekly", now + TimeDelta::hours(1))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            service.get_last_run_id("default").await.unwrap(),
+            Some(newer_run)
+        );
+        assert_eq!(
+            service.get_last_run_id("weekly").await.unwrap(),
+            Some(other_pipeline_run)
+        );
+    }
+
+    #[sqlx::test]
+    async fn only_executed_successful_tasks_outside_backfills_become_last_success(
+        pool: Pool<Postgres>,
+    ) {
+        let service = RunHistoryService::new(pool);
+        let now = Utc::now()
+            .duration_trunc(TimeDelta::microseconds(1))
+            .unwrap();
+        let earlier_run = Uuid::new_v4();
+        let later_run = Uuid::new_v4();
+        service
+            .start_schedule_run(earlier_run, "default", now - TimeDelta::hours(2))
+            .await
+            .unwrap();
+        service
+            .start_schedule_run(later_run, "default", now)
+            .await
+            .unwrap();
+        let entry = |run_id, task_name: &str, started_at, finished_at, state| TaskRunEntry {
+            run_id,
+            task_name: task_name.to_string(),
+            started_at,
+            finished_at,
+            state,
+            attempts: 1,
+            error: None,
+            custom_stats: None,
+        };
+        let succeeded_at = now - TimeDelta::hours(1);
+        for entry in [
+            entry(
+                later_run,
+                "collect",
+                Some(succeeded_at),
+                succeeded_at,
+                ExecutionState::Finished,
+            ),
+            // stored after the later success, but does not replace it
+            entry(
+                earlier_run,
+                "collect",
+                Some(now - TimeDelta::hours(2)),
+                now - TimeDelta::hours(2),
+                ExecutionState::Finished,
+            ),
+            entry(earlier_run, "stage", None, now, ExecutionState::Finished),
+            entry(later_run, "stage", Some(now), now, ExecutionState::Failed),
+        ] {
+            service.save_task_run(&entry, true).await.unwrap();
+        }
+        // a backfill does not make the task fresh
+        service
+            .save_task_run(
+                &entry(later_run, "stage", Some(now), now, ExecutionState::Finished),
+                false,
+            )
+            .await
+            .unwrap();
+
+        let last_successes = service
+            .get_last_successes(&["collect".to_string(), "stage".to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            last_successes,
+            HashMap::from([("collect".to_string(), succeeded_at)])
+        );
+    }
+}
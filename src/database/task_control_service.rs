@@ -0,0 +1,226 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, Pool, Postgres};
+use std::collections::BTreeMap;
+
+/// Service to pause, disable or force tasks via the `task_controls` table without changing the configuration
+#[derive(Clone, Debug)]
+pub struct TaskControlService {
+    pool: Pool<Postgres>,
+}
+
+/// Control of a task set by an operator, a task without control runs as configured
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TaskControl {
+    pub paused: bool,
+    pub disabled_until: Option<DateTime<Utc>>,
+    /// executes the task once even if it is fresh, not due or already finished in a resumed run
+    pub force_run: bool,
+}
+
+impl TaskControl {
+    /// A paused task and a task disabled until a later time are skipped, also if a run is forced
+    pub fn is_paused(&self, now: DateTime<Utc>) -> bool {
+        self.paused || self.disabled_until.is_some_and(|until| until > now)
+    }
+}
+
+#[derive(Debug, FromRow)]
+struct TaskControlRow {
+    task_name: String,
+    paused: bool,
+    disabled_until: Option<DateTime<Utc>>,
+    force_run: bool,
+}
+
+impl TaskControlRow {
+    fn into_named_control(self) -> (String, TaskControl) {
+        (
+            self.task_name,
+            TaskControl {
+                paused: self.paused,
+                disabled_until: self.disabled_until,
+                force_run: self.force_run,
+            },
+        )
+    }
+}
+
+impl TaskControlService {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get_control(&self, task_name: &str) -> Result<Option<TaskControl>, anyhow::Error> {
+        let row: Option<TaskControlRow> = sqlx::query_as(
+            "SELECT task_name, paused, disabled_until, force_run FROM task_controls WHERE task_name = $1",
+        )
+        .bind(task_name)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|row| row.into_named_control().1))
+    }
+
+    /// controls of all tasks by task name
+    pub async fn get_controls(&self) -> Result<BTreeMap<String, TaskControl>, anyhow::Error> {
+        let rows: Vec<TaskControlRow> = sqlx::query_as(
+            "SELECT task_name, paused, disabled_until, force_run FROM task_controls",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(TaskControlRow::into_named_control)
+            .collect())
+    }
+
+    /// skips the task until it is resumed
+    pub async fn pause(&self, task_name: &str) -> Result<(), anyhow::Error> {
+        let query = r#"
+    INSERT INTO task_controls (task_name, paused, updated_at)
+    VALUES ($1, true, now())
+    ON CONFLICT (task_name)
+    DO UPDATE SET paused = true, updated_at = now()
+    "#;
+
+        sqlx::query(query)
+            .bind(task_name)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// skips the task until the given time
+    pub async fn disable_until(
+        &self,
+        task_name: &str,
+        until: DateTime<Utc>,
+    ) -> Result<(), anyhow::Error> {
+        let query = r#"
+    INSERT INTO task_controls (task_name, disabled_until, updated_at)
+    VALUES ($1, $2, now())
+    ON CONFLICT (task_name)
+    DO UPDATE SET disabled_until = EXCLUDED.disabled_until, updated_at = now()
+    "#;
+
+        sqlx::query(query)
+            .bind(task_name)
+            .bind(until)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// clears the pause and the disabled time of the task, a forced run is kept
+    pub async fn resume(&self, task_name: &str) -> Result<(), anyhow::Error> {
+        sqlx::query(
+            "UPDATE task_controls SET paused = false, disabled_until = NULL, updated_at = now() WHERE task_name = $1",
+        )
+        .bind(task_name)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// executes the task the next time it is started by the scheduler
+    pub async fn force_run(&self, task_name: &str) -> Result<(), anyhow::Error> {
+        let query = r#"
+    INSERT INTO task_controls (task_name, force_run, updated_at)
+    VALUES ($1, true, now())
+    ON CONFLICT (task_name)
+    DO UPDATE SET force_run = true, updated_at = now()
+    "#;
+
+        sqlx::query(query)
+            .bind(task_name)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn clear_force_run(&self, task_name: &str) -> Result<(), anyhow::Error> {
+        sqlx::query(
+            "UPDATE task_controls SET force_run = false, updated_at = now() WHERE task_name = $1",
+        )
+        .bind(task_name)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+#[cfg_attr(test, mockall::automock)]
+pub trait TaskControlServiceTrait: Send + Sync {
+    async fn get_control(&self, task_name: &str) -> Result<Option<TaskControl>, anyhow::Error>;
+    async fn clear_force_run(&self, task_name: &str) -> Result<(), anyhow::Error>;
+}
+
+#[async_trait]
+impl TaskControlServiceTrait for TaskControlService {
+    async fn get_control(&self, task_name: &str) -> Result<Option<TaskControl>, anyhow::Error> {
+        self.get_control(task_name).await
+    }
+
+    async fn clear_force_run(&self, task_name: &str) -> Result<(), anyhow::Error> {
+        self.clear_force_run(task_name).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::database::task_control_service::{TaskControl, TaskControlService};
+    use chrono::{DurationRound, TimeDelta, Utc};
+    use sqlx::{Pool, Postgres};
+
+    #[test]
+    fn paused_or_disabled_tasks_are_paused() {
+        let now = Utc::now();
+        assert!(!TaskControl::default().is_paused(now));
+        assert!(TaskControl {
+            paused: true,
+            ..Default::default()
+        }
+        .is_paused(now));
+        let disabled = TaskControl {
+            disabled_until: Some(now + TimeDelta::hours(1)),
+            ..Default::default()
+        };
+        assert!(disabled.is_paused(now));
+        assert!(!disabled.is_paused(now + TimeDelta::hours(2)));
+    }
+
+    #[sqlx::test]
+    async fn controls_are_set_and_cleared(pool: Pool<Postgres>) {
+        let service = TaskControlService::new(pool);
+        // postgres stores microseconds only
+        let until = (Utc::now() + TimeDelta::days(1))
+            .duration_trunc(TimeDelta::microseconds(1))
+            .unwrap();
+        assert_eq!(service.get_control("MassiveDividends").await.unwrap(), None);
+
+        service.pause("MassiveDividends").await.unwrap();
+        service
+            .disable_until("MassiveDividends", until)
+            .await
+            .unwrap();
+        service.force_run("PolygonOpenClose").await.unwrap();
+
+        assert_eq!(
+            service.get_control("MassiveDividends").await.unwrap(),
+            Some(TaskControl {
+                paused: true,
+                disabled_until: Some(until),
+                force_run: false,
+            })
+        );
+
+        service.resume("MassiveDividends").await.unwrap();
+        service.clear_force_run("PolygonOpenClose").await.unwrap();
+
+        let controls = service.get_controls().await.unwrap();
+        assert_eq!(controls.len(), 2);
+        assert_eq!(controls["MassiveDividends"], TaskControl::default());
+        assert_eq!(controls["PolygonOpenClose"], TaskControl::default());
+    }
+}
@@ -0,0 +1,437 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, Pool, Postgres};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Service to distribute the execution of tasks over worker processes via the `task_queue` table
+#[derive(Clone, Debug)]
+pub struct TaskQueueService {
+    pool: Pool<Postgres>,
+}
+
+/// State of a task in the queue
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QueueState {
+    Queued,
+    Running,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+impl QueueState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QueueState::Queued => "queued",
+            QueueState::Running => "running",
+            QueueState::Finished => "finished",
+            QueueState::Failed => "failed",
+            QueueState::Cancelled => "cancelled",
+        }
+    }
+}
+
+impl TryFrom<String> for QueueState {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "queued" => Ok(QueueState::Queued),
+            "running" => Ok(QueueState::Running),
+            "finished" => Ok(QueueState::Finished),
+            "failed" => Ok(QueueState::Failed),
+            "cancelled" => Ok(QueueState::Cancelled),
+            other => Err(format!("{} is not a valid queue state", other)),
+        }
+    }
+}
+
+/// Task in the queue
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueueEntry {
+    pub queue_id: Uuid,
+    pub pipeline: String,
+    pub task_name: String,
+    pub state: QueueState,
+    pub worker_id: Option<Uuid>,
+    pub heartbeat_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+    pub retryable: bool,
+    pub custom_stats: Option<serde_json::Value>,
+}
+
+/// Result of a task reported by the worker which executed it
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueueResult {
+    pub state: QueueState,
+    pub error: Option<String>,
+    pub retryable: bool,
+    pub custom_stats: Option<serde_json::Value>,
+}
+
+#[derive(Debug, FromRow)]
+struct QueueRow {
+    queue_id: Uuid,
+    pipeline: String,
+    task_name: String,
+    state: String,
+    worker_id: Option<Uuid>,
+    heartbeat_at: Option<DateTime<Utc>>,
+    error: Option<String>,
+    retryable: bool,
+    custom_stats: Option<String>,
+}
+
+impl TryFrom<QueueRow> for QueueEntry {
+    type Error = anyhow::Error;
+
+    fn try_from(row: QueueRow) -> Result<Self, Self::Error> {
+        Ok(QueueEntry {
+            queue_id: row.queue_id,
+            pipeline: row.pipeline,
+            task_name: row.task_name,
+            state: QueueState::try_from(row.state).map_err(anyhow::Error::msg)?,
+            worker_id: row.worker_id,
+            heartbeat_at: row.heartbeat_at,
+            error: row.error,
+            retryable: row.retryable,
+            custom_stats: row
+                .custom_stats
+                .map(|stats| serde_json::from_str(&stats))
+                .transpose()?,
+        })
+    }
+}
+
+const QUEUE_COLUMNS: &str = "queue_id, pipeline, task_name, state, worker_id, heartbeat_at, error, retryable, custom_stats::text";
+
+impl TaskQueueService {
+    pub fn new(pool: Pool<Postgres>) -> Self {
+        Self { pool }
+    }
+
+    /// queues the task for execution by a worker, returns the id of the queue entry
+    pub async fn enqueue(&self, pipeline: &str, task_name: &str) -> Result<Uuid, anyhow::Error> {
+        let queue_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO task_queue (queue_id, pipeline, task_name, state, enqueued_at) VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(queue_id)
+        .bind(pipeline)
+        .bind(task_name)
+        .bind(QueueState::Queued.as_str())
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        Ok(queue_id)
+    }
+
+    /// Claims the longest queued task the worker can execute. Rows locked by other workers
+    /// are skipped, so that each task is claimed by exactly one worker.
+    pub async fn claim_next(
+        &self,
+        worker_id: Uuid,
+        task_names: &[String],
+    ) -> Result<Option<QueueEntry>, anyhow::Error> {
+        let query = format!(
+            r#"
+    UPDATE task_queue
+    SET state = 'running', worker_id = $1, claimed_at = $3, heartbeat_at = $3
+    WHERE queue_id = (
+        SELECT queue_id
+        FROM task_queue
+        WHERE state = 'queued' AND task_name = ANY($2)
+        ORDER BY enqueued_at
+        LIMIT 1
+        FOR UPDATE SKIP LOCKED
+    )
+    RETURNING {}
+    "#,
+            QUEUE_COLUMNS
+        );
+        let row: Option<QueueRow> = sqlx::query_as(&query)
+            .bind(worker_id)
+            .bind(task_names)
+            .bind(Utc::now())
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(QueueEntry::try_from).transpose()
+    }
+
+    /// Signals that the worker still executes the task. Returns the current state of the entry,
+    /// the worker stops the execution if it is not running anymore, e.g. because it was cancelled.
+    pub async fn heartbeat(
+        &self,
+        queue_id: Uuid,
+        worker_id: Uuid,
+    ) -> Result<QueueState, anyhow::Error> {
+        sqlx::query(
+            "UPDATE task_queue SET heartbeat_at = $3 WHERE queue_id = $1 AND worker_id = $2 AND state = 'running'",
+        )
+        .bind(queue_id)
+        .bind(worker_id)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        let state: Option<(String, Option<Uuid>)> =
+            sqlx::query_as("SELECT state, worker_id FROM task_queue WHERE queue_id = $1")
+                .bind(queue_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        match state {
+            // the task was queued again and possibly claimed by another worker
+            Some((_, owner)) if owner != Some(worker_id) => Ok(QueueState::Cancelled),
+            Some((state, _)) => QueueState::try_from(state).map_err(anyhow::Error::msg),
+            None => Ok(QueueState::Cancelled),
+        }
+    }
+
+    /// stores the result of the task, ignored if the task is not running on the worker anymore
+    pub async fn complete(
+        &self,
+        queue_id: Uuid,
+        worker_id: Uuid,
+        result: &QueueResult,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query(
+            r#"
+    UPDATE task_queue
+    SET state = $3, finished_at = $4, error = $5, retryable = $6, custom_stats = $7::jsonb
+    WHERE queue_id = $1 AND worker_id = $2 AND state = 'running'
+    "#,
+        )
+        .bind(queue_id)
+        .bind(worker_id)
+        .bind(result.state.as_str())
+        .bind(Utc::now())
+        .bind(&result.error)
+        .bind(result.retryable)
+        .bind(result.custom_stats.as_ref().map(|stats| stats.to_string()))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// cancels the task if it is queued or running, the worker stops it with its next heartbeat
+    pub async fn cancel(&self, queue_id: Uuid) -> Result<(), anyhow::Error> {
+        sqlx::query(
+            "UPDATE task_queue SET state = 'cancelled', finished_at = $2 WHERE queue_id = $1 AND state IN ('queued', 'running')",
+        )
+        .bind(queue_id)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_entry(&self, queue_id: Uuid) -> Result<Option<QueueEntry>, anyhow::Error> {
+        let query = format!(
+            "SELECT {} FROM task_queue WHERE queue_id = $1",
+            QUEUE_COLUMNS
+        );
+        let row: Option<QueueRow> = sqlx::query_as(&query)
+            .bind(queue_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(QueueEntry::try_from).transpose()
+    }
+
+    /// Queues the running task again if its worker did not send a heartbeat within the given
+    /// duration, e.g. because the worker process died. Returns whether the task was queued again.
+    pub async fn requeue_stale(
+        &self,
+        queue_id: Uuid,
+        stale_after: Duration,
+    ) -> Result<bool, anyhow::Error> {
+        let stale_before = Utc::now() - chrono::Duration::from_std(stale_after)?;
+        let result = sqlx::query(
+            r#"
+    UPDATE task_queue
+    SET state = 'queued', worker_id = NULL, claimed_at = NULL, heartbeat_at = NULL
+    WHERE queue_id = $1 AND state = 'running' AND heartbeat_at < $2
+    "#,
+        )
+        .bind(queue_id)
+        .bind(stale_before)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[async_trait]
+#[cfg_attr(test, mockall::automock)]
+pub trait TaskQueueServiceTrait: Send + Sync {
+    async fn enqueue(&self, pipeline: &str, task_name: &str) -> Result<Uuid, anyhow::Error>;
+    async fn claim_next(
+        &self,
+        worker_id: Uuid,
+        task_names: &[String],
+    ) -> Result<Option<QueueEntry>, anyhow::Error>;
+    async fn heartbeat(&self, queue_id: Uuid, worker_id: Uuid)
+        -> Result<QueueState, anyhow::Error>;
+    async fn complete(
+        &self,
+        queue_id: Uuid,
+        worker_id: Uuid,
+        result: &QueueResult,
+    ) -> Result<(), anyhow::Error>;
+    async fn cancel(&self, queue_id: Uuid) -> Result<(), anyhow::Error>;
+    async fn get_entry(&self, queue_id: Uuid) -> Result<Option<QueueEntry>, anyhow::Error>;
+    async fn requeue_stale(
+        &self,
+        queue_id: Uuid,
+        stale_after: Duration,
+    ) -> Result<bool, anyhow::Error>;
+}
+
+#[async_trait]
+impl TaskQueueServiceTrait for TaskQueueService {
+    async fn enqueue(&self, pipeline: &str, task_name: &str) -> Result<Uuid, anyhow::Error> {
+        self.enqueue(pipeline, task_name).await
+    }
+
+    async fn claim_next(
+        &self,
+        worker_id: Uuid,
+        task_names: &[String],
+    ) -> Result<Option<QueueEntry>, anyhow::Error> {
+        self.claim_next(worker_id, task_names).await
+    }
+
+    async fn heartbeat(
+        &self,
+        queue_id: Uuid,
+        worker_id: Uuid,
+    ) -> Result<QueueState, anyhow::Error> {
+        self.heartbeat(queue_id, worker_id).await
+    }
+
+    async fn complete(
+        &self,
+        queue_id: Uuid,
+        worker_id: Uuid,
+        result: &QueueResult,
+    ) -> Result<(), anyhow::Error> {
+        self.complete(queue_id, worker_id, result).await
+    }
+
+    async fn cancel(&self, queue_id: Uuid) -> Result<(), anyhow::Error> {
+        self.cancel(queue_id).await
+    }
+
+    async fn get_entry(&self, queue_id: Uuid) -> Result<Option<QueueEntry>, anyhow::Error> {
+        self.get_entry(queue_id).await
+    }
+
+    async fn requeue_stale(
+        &self,
+        queue_id: Uuid,
+        stale_after: Duration,
+    ) -> Result<bool, anyhow::Error> {
+        self.requeue_stale(queue_id, stale_after).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::database::task_queue_service::{QueueResult, QueueState, TaskQueueService};
+    use sqlx::{Pool, Postgres};
+    use std::time::Duration;
+    use uuid::Uuid;
+
+    #[sqlx::test]
+    async fn queued_task_is_claimed_by_one_worker_only(pool: Pool<Postgres>) {
+        let service = TaskQueueService::new(pool);
+        let queue_id = service.enqueue("default", "collector").await.unwrap();
+        let tasks = vec!["collector".to_string()];
+
+        let claimed = service
+            .claim_next(Uuid::new_v4(), &tasks)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(claimed.queue_id, queue_id);
+        assert_eq!(claimed.state, QueueState::Running);
+        assert_eq!(
+            service.claim_next(Uuid::new_v4(), &tasks).await.unwrap(),
+            None
+        );
+    }
+
+    #[sqlx::test]
+    async fn worker_only_claims_tasks_it_knows(pool: Pool<Postgres>) {
+        let service = TaskQueueService::new(pool);
+        service.enqueue("default", "collector").await.unwrap();
+
+        let claimed = service
+            .claim_next(Uuid::new_v4(), &["stager".to_string()])
+            .await
+            .unwrap();
+
+        assert_eq!(claimed, None);
+    }
+
+    #[sqlx::test]
+    async fn result_of_worker_is_stored(pool: Pool<Postgres>) {
+        let service = TaskQueueService::new(pool);
+        let worker_id = Uuid::new_v4();
+        let queue_id = service.enqueue("default", "collector").await.unwrap();
+        service
+            .claim_next(worker_id, &["collector".to_string()])
+            .await
+            .unwrap();
+        let result = QueueResult {
+            state: QueueState::Failed,
+            error: Some("The action of the task failed".to_string()),
+            retryable: true,
+            custom_stats: Some(serde_json::json!({"rows": 42})),
+        };
+
+        assert_eq!(
+            service.heartbeat(queue_id, worker_id).await.unwrap(),
+            QueueState::Running
+        );
+        service
+            .complete(queue_id, worker_id, &result)
+            .await
+            .unwrap();
+
+        let entry = service.get_entry(queue_id).await.unwrap().unwrap();
+        assert_eq!(entry.state, QueueState::Failed);
+        assert_eq!(entry.error, result.error);
+        assert!(entry.retryable);
+        assert_eq!(entry.custom_stats, result.custom_stats);
+    }
+
+    #[sqlx::test]
+    async fn stale_task_is_queued_again(pool: Pool<Postgres>) {
+        let service = TaskQueueService::new(pool);
+        let dead_worker = Uuid::new_v4();
+        let queue_id = service.enqueue("default", "collector").await.unwrap();
+        service
+            .claim_next(dead_worker, &["collector".to_string()])
+            .await
+            .unwrap();
+
+        assert!(!service
+            .requeue_stale(queue_id, Duration::from_secs(60))
+            .await
+            .unwrap());
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(service
+            .requeue_stale(queue_id, Duration::from_millis(10))
+            .await
+            .unwrap());
+
+        assert_eq!(
+            service.heartbeat(queue_id, dead_worker).await.unwrap(),
+            QueueState::Cancelled
+        );
+        let entry = service.get_entry(queue_id).await.unwrap().unwrap();
+        assert_eq!(entry.state, QueueState::Queued);
+        assert_eq!(entry.worker_id, None);
+    }
+}
@@ -7,6 +7,7 @@ const MASSIVE_DIVIDENDS_CUTOFF_DAYS: u64 = 30;
 #[derive(Clone, Debug)]
 pub struct WardenService {
     pool: Pool<Postgres>,
+    massive_dividends_cutoff_days: u64,
 }
 
 #[derive(Debug, FromRow)]
@@ -32,7 +33,16 @@ pub enum WardenType {
 
 impl WardenService {
     pub fn new(pool: Pool<Postgres>) -> Self {
-        Self { pool }
+        Self {
+            pool,
+            massive_dividends_cutoff_days: MASSIVE_DIVIDENDS_CUTOFF_DAYS,
+        }
+    }
+
+    /// Symbols without massive dividends are skipped for this many days
+    pub fn with_massive_dividends_cutoff_days(mut self, days: u64) -> Self {
+        self.massive_dividends_cutoff_days = days;
+        self
     }
 
     pub async fn add_or_update(
@@ -103,7 +113,7 @@ impl WardenService {
     async fn get_missing_massive_dividend_symbols(&self) -> Result<Vec<String>, anyhow::Error> {
         let cutoff = Utc::now()
             .date_naive()
-            .checked_sub_days(Days::new(MASSIVE_DIVIDENDS_CUTOFF_DAYS))
+            .checked_sub_days(Days::new(self.massive_dividends_cutoff_days))
             .unwrap();
 
         let rows = sqlx::query!(
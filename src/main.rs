@@ -1,23 +1,162 @@
 extern crate tracing;
 
-use data_collector::configuration::get_configuration;
+use data_collector::configuration::{get_configuration, Settings};
 use data_collector::utils::telemetry::{get_open_telemetry_subscriber, init_subscriber};
 
-use data_collector::startup::Application;
+use chrono::{NaiveDate, NaiveTime};
+use clap::{Parser, Subcommand};
+use data_collector::database::task_control_service::TaskControlService;
+use data_collector::startup::{get_connection_pool, shutdown_signal, Application};
 use opentelemetry::global::shutdown_tracer_provider;
 
 use std::error::Error;
+use std::process::ExitCode;
+use std::sync::Arc;
+use tracing::{error, info};
 
+/// Collects market data by running the configured pipelines
+#[derive(Parser)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Runs the configured pipelines, the default without subcommand
+    Run,
+    /// Pauses, disables or forces a task, picked up by running schedules before the task is started
+    #[command(subcommand)]
+    Task(TaskCommand),
+}
+
+#[derive(Subcommand)]
+enum TaskCommand {
+    /// Skips the task until it is resumed
+    Pause { name: String },
+    /// Clears the pause and the disabled date of the task
+    Resume { name: String },
+    /// Skips the task until the start of the given day (UTC)
+    Disable {
+        name: String,
+        #[arg(long)]
+        until: NaiveDate,
+    },
+    /// Executes the task on its next start even if it is fresh or not due
+    ForceRun { name: String },
+    /// Lists the controls of all tasks
+    List,
+}
+
+impl TaskCommand {
+    fn task_name(&self) -> Option<&str> {
+        match self {
+            TaskCommand::Pause { name }
+            | TaskCommand::Resume { name }
+            | TaskCommand::Disable { name, .. }
+            | TaskCommand::ForceRun { name } => Some(name),
+            TaskCommand::List => None,
+        }
+    }
+}
+
+/// Exits with a failure code if a task failed, so that the calling orchestrator can alert
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+async fn main() -> Result<ExitCode, Box<dyn Error + Send + Sync>> {
+    let cli = Cli::parse();
     let subscriber =
         get_open_telemetry_subscriber("data_collector".into(), "info".into(), std::io::stdout);
     init_subscriber(subscriber);
 
     let configuration = get_configuration().expect("Failed to read configuration.");
 
-    Application::build(configuration).await.run().await?;
+    if let Some(Command::Task(command)) = cli.command {
+        let result = run_task_command(command, &configuration).await;
+        shutdown_tracer_provider();
+        result?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let application = Arc::new(Application::build(configuration).await);
+    let signalled_application = application.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("Received shutdown signal, cancel running tasks");
+        signalled_application.shutdown();
+    });
+
+    let result = application.run().await;
 
+    let has_failures = result.iter().flatten().any(|report| report.has_failures());
+    let failed_tasks: Vec<String> = result
+        .iter()
+        .flatten()
+        .flat_map(|report| {
+            report
+                .failed_tasks()
+                .into_iter()
+                .map(|task| format!("{}/{}", report.pipeline, task))
+        })
+        .collect();
+    if has_failures {
+        error!("Failed tasks: {}", failed_tasks.join(", "));
+    }
+
+    // flush traces also if the run failed
     shutdown_tracer_provider();
+    result?;
+    Ok(if has_failures {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+async fn run_task_command(
+    command: TaskCommand,
+    configuration: &Settings,
+) -> Result<(), anyhow::Error> {
+    if let Some(name) = command.task_name() {
+        if !configuration
+            .application
+            .tasks
+            .iter()
+            .any(|task| task.name == name)
+        {
+            anyhow::bail!("Task {} is not configured", name);
+        }
+    }
+    let service = TaskControlService::new(get_connection_pool(&configuration.database));
+    match command {
+        TaskCommand::Pause { name } => {
+            service.pause(&name).await?;
+            info!("Paused task {}", name);
+        }
+        TaskCommand::Resume { name } => {
+            service.resume(&name).await?;
+            info!("Resumed task {}", name);
+        }
+        TaskCommand::Disable { name, until } => {
+            let until = until.and_time(NaiveTime::MIN).and_utc();
+            service.disable_until(&name, until).await?;
+            info!("Disabled task {} until {}", name, until);
+        }
+        TaskCommand::ForceRun { name } => {
+            service.force_run(&name).await?;
+            info!("Forced the next run of task {}", name);
+        }
+        TaskCommand::List => {
+            for (name, control) in service.get_controls().await? {
+                let disabled_until = control
+                    .disabled_until
+                    .map(|until| until.to_string())
+                    .unwrap_or_default();
+                println!(
+                    "{}\tpaused={}\tdisabled_until={}\tforce_run={}",
+                    name, control.paused, disabled_until, control.force_run
+                );
+            }
+        }
+    }
     Ok(())
 }
@@ -1,24 +1,67 @@
+use anyhow::Context;
 use reqwest::Client;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use std::sync::Arc;
+use std::time::Duration;
 
 use crate::configuration::{
-    DatabaseSettings, HttpClientSettings, SecretKeys, Settings, TaskDependency, TaskName,
-    TaskSetting,
+    BackOffSetting, BackfillSetting, CadenceSetting, DatabaseSettings, DistributedRole,
+    DistributedSetting, ExecutionModeSetting, HttpClientSettings, PipelineSetting, RetrySetting,
+    RunReportFormat, SecretKeys, Settings, TaskDependency, TaskName, TaskSetting,
 };
 
-use crate::actions::action::create_action;
-use crate::dag_schedule::schedule::{Schedule, TaskDependenciesSpecs, TaskSpec, TaskSpecRef};
-use crate::dag_schedule::task::{ExecutionMode, RetryOptions};
+use crate::actions::action::{
+    create_action, create_backfill_action, create_fan_out_action, Action,
+};
+use crate::dag_schedule::cadence::Cadence;
+use crate::dag_schedule::concurrency::ConcurrencyLimits;
+use crate::dag_schedule::events::EventTriggers;
+use crate::dag_schedule::export::DagGraph;
+use crate::dag_schedule::fan_out::FanOutOptions;
+use crate::dag_schedule::report::RunReport;
+use crate::dag_schedule::schedule::{
+    Schedule, ScheduleError, TaskDependenciesSpecs, TaskGroups, TaskSpec, TaskSpecRef,
+};
+use crate::dag_schedule::scheduler::{Scheduler, DEFAULT_PIPELINE};
+use crate::dag_schedule::task::{BackOff, ExecutionMode, ExecutionState, RetryOptions};
+use crate::dag_schedule::window::TimeWindow;
+use crate::dag_schedule::worker::{QueueOptions, QueuedRunnable, Worker};
+use crate::database::raw_data_listener::listen_raw_data_events;
+use crate::database::run_history_service::RunHistoryService;
+use crate::database::task_control_service::TaskControlService;
+use crate::database::task_queue_service::TaskQueueService;
+use crate::utils::action_helpers::DateRange;
+use crate::utils::notification::{NotificationSink, Notifier};
 use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
+use tokio::sync::{broadcast, mpsc};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+
+/// Name of the pipeline a backfill runs in, used to tell backfills apart in the run history
+const BACKFILL_PIPELINE: &str = "backfill";
 
 pub struct Application {
     pool: PgPool,
-    task_dependencies: Vec<TaskDependency>,
+    pipelines: Vec<(String, PipelineSetting)>,
+    task_groups: TaskGroups,
     task_settings: Vec<TaskSetting>,
     client: Client,
     secrets: SecretKeys,
+    notifier: Notifier,
+    daemon: bool,
+    resume_last_run: bool,
+    dry_run: bool,
+    dag_export_dir: Option<String>,
+    run_report_format: RunReportFormat,
+    max_parallel_tasks: Option<usize>,
+    resource_pools: HashMap<String, usize>,
+    distributed: Option<DistributedSetting>,
+    event_triggers: EventTriggers,
+    backfill: Option<BackfillSetting>,
+    kill_switch: broadcast::Sender<()>,
+    shutdown: CancellationToken,
 }
 
 impl Application {
@@ -26,46 +69,444 @@ impl Application {
         let connection_pool = get_connection_pool(&configuration.database);
         connection_pool.set_connect_options(configuration.database.with_db());
         let client = build_http_client(configuration.application.http_client);
+        let notifier = configuration.application.notifications.into_iter().fold(
+            Notifier::new(client.clone()),
+            |notifier, setting| {
+                notifier.with_sink(NotificationSink::new(
+                    setting.url,
+                    setting.format,
+                    setting.events,
+                ))
+            },
+        );
+        let (kill_switch, _) = broadcast::channel(1);
         Application {
             pool: connection_pool,
-            task_dependencies: configuration.application.task_dependencies,
+            pipelines: collect_pipelines(
+                configuration.application.task_dependencies,
+                configuration.application.pipelines,
+            ),
+            task_groups: configuration.application.task_groups,
             task_settings: configuration.application.tasks,
             client,
             secrets: configuration.application.secrets,
+            notifier,
+            daemon: configuration.application.daemon,
+            resume_last_run: configuration.application.resume_last_run,
+            dry_run: configuration.application.dry_run,
+            dag_export_dir: configuration.application.dag_export_dir,
+            run_report_format: configuration.application.run_report_format,
+            max_parallel_tasks: configuration.application.max_parallel_tasks,
+            resource_pools: configuration.application.resource_pools,
+            distributed: configuration.application.distributed,
+            event_triggers: configuration.application.event_triggers.into_iter().fold(
+                EventTriggers::new(),
+                |triggers, trigger| {
+                    triggers.with_trigger(
+                        trigger.table,
+                        trigger.task,
+                        Duration::from_secs(trigger.debounce_seconds),
+                    )
+                },
+            ),
+            backfill: configuration.application.backfill,
+            kill_switch,
+            shutdown: CancellationToken::new(),
         }
     }
 
-    #[allow(clippy::mutable_key_type)]
+    /// Stops the daemon and all continuously running tasks after their current execution
+    pub fn stop(&self) {
+        // an error only means that nobody is listening anymore
+        let _ = self.kill_switch.send(());
+    }
+
+    /// Cancels all running tasks and stops the schedule. Cancelled tasks get a grace period to stop,
+    /// `run` returns as soon as all of them stopped and were recorded as cancelled.
+    pub fn shutdown(&self) {
+        self.stop();
+        self.shutdown.cancel();
+    }
+
+    /// Runs the pipelines once and returns a report per pipeline. A daemon, a worker or a dry run
+    /// returns no reports, the daemon logs the summary of each of its runs instead.
     #[tracing::instrument(name = "Run application", skip(self))]
-    pub async fn run(&self) -> Result<(), anyhow::Error> {
+    pub async fn run(&self) -> Result<Vec<RunReport>, anyhow::Error> {
+        if let Some(setting) = self
+            .distributed
+            .as_ref()
+            .filter(|setting| setting.role == DistributedRole::Worker)
+        {
+            self.run_worker(setting).await?;
+            return Ok(vec![]);
+        }
+
+        // a backfill only runs the task to backfill once
+        let (pipelines, daemon) = match &self.backfill {
+            Some(backfill) => (self.backfill_pipeline(backfill)?, false),
+            None => (self.pipelines.clone(), self.daemon),
+        };
+
+        // build and check the schedules of all pipelines and report all problems at once before running
+        let run_history = Arc::new(RunHistoryService::new(self.pool.clone()));
+        let mut scheduler = Scheduler::new();
+        let configured_tasks: HashSet<&str> = self
+            .task_settings
+            .iter()
+            .map(|ts| ts.name.as_str())
+            .collect();
+        let mut problems = self.event_triggers.check_tasks(&configured_tasks);
+        for problem in problems.iter() {
+            error!("Invalid event trigger: {}", problem);
+        }
+        let mut task_event_senders = vec![];
+        for (name, pipeline) in pipelines.iter() {
+            let (mut schedule, pipeline_problems) = self
+                .build_schedule(name, pipeline, run_history.clone())
+                .await?;
+            if daemon && !self.event_triggers.is_empty() {
+                let (sender, receiver) = mpsc::channel(100);
+                let task_names = pipeline
+                    .task_dependencies
+                    .iter()
+                    .map(|task_dependency| task_dependency.name.clone())
+                    .collect();
+                task_event_senders.push((sender, task_names));
+                schedule = schedule.with_task_events(receiver);
+            }
+            for problem in pipeline_problems.iter() {
+                error!("Invalid schedule of pipeline {}: {}", name, problem);
+            }
+            problems.extend(pipeline_problems);
+            scheduler.add_schedule(schedule);
+        }
+        ScheduleError::from_problems(problems)?;
+
+        if self.dry_run {
+            for schedule in scheduler.schedules() {
+                let graph = schedule.graph().await;
+                for (wave, tasks) in graph.execution_waves().iter().enumerate() {
+                    info!(
+                        "Pipeline {} wave {}: {}",
+                        schedule.name(),
+                        wave + 1,
+                        tasks.join(", ")
+                    );
+                }
+                self.export_dag(schedule.name(), &graph)?;
+            }
+            return Ok(vec![]);
+        }
+
+        if self.resume_last_run && self.backfill.is_none() {
+            for schedule in scheduler.schedules_mut() {
+                resume_last_run(schedule, &run_history).await?;
+            }
+        }
+
+        let reports = if daemon {
+            if !task_event_senders.is_empty() {
+                self.spawn_event_listener(task_event_senders);
+            }
+            scheduler.run_daemons(&self.kill_switch).await?;
+            vec![]
+        } else {
+            scheduler.run_schedules().await?
+        };
+        for schedule in scheduler.schedules() {
+            self.export_dag(schedule.name(), &schedule.graph().await)?;
+        }
+        for report in reports.iter() {
+            self.log_run_report(report)?;
+        }
+        Ok(reports)
+    }
+
+    fn log_run_report(&self, report: &RunReport) -> Result<(), anyhow::Error> {
+        match self.run_report_format {
+            RunReportFormat::Table => info!("Report of pipeline {}:\n{}", report.pipeline, report),
+            RunReportFormat::Json => info!("{}", serde_json::to_string(report)?),
+        }
+        Ok(())
+    }
+
+    /// Builds the schedule of the pipeline and checks it, the problems found are returned
+    #[allow(clippy::mutable_key_type)]
+    async fn build_schedule(
+        &self,
+        name: &str,
+        pipeline: &PipelineSetting,
+        run_history: Arc<RunHistoryService>,
+    ) -> Result<(Schedule, Vec<ScheduleError>), anyhow::Error> {
+        // the cadence of the pipeline applies to all its tasks without own cadence
+        let task_settings: Vec<TaskSetting> = self
+            .task_settings
+            .iter()
+            .cloned()
+            .map(|mut ts| {
+                ts.cadence = ts.cadence.or_else(|| pipeline.cadence.clone());
+                ts
+            })
+            .collect();
         // init specs from config
         let task_specs = build_task_specs(
-            &self.task_settings,
-            &self.task_dependencies,
-            &self.pool,
-            &self.client,
-            &self.secrets,
-        );
+            &task_settings,
+            &pipeline.task_dependencies,
+            &self.task_groups,
+            &|ts| self.build_action(name, ts),
+            &self.kill_switch,
+        )?;
 
         // build adj list from specs
-        let task_dep_specs = add_dependencies_to_task_specs(task_specs, &self.task_dependencies);
+        let (task_dep_specs, mut problems) = add_dependencies_to_task_specs(
+            task_specs,
+            &pipeline.task_dependencies,
+            &self.task_groups,
+        );
+
+        let mut schedule = Schedule::new()
+            .with_name(name.to_string())
+            .with_cancellation(self.shutdown.clone())
+            .with_run_history(run_history)
+            .with_freshness_updates(name != BACKFILL_PIPELINE)
+            .with_notifier(self.notifier.clone())
+            .with_task_controls(Arc::new(TaskControlService::new(self.pool.clone())))
+            .with_task_groups(self.task_groups.clone())
+            .with_concurrency_limits(ConcurrencyLimits::new(
+                self.max_parallel_tasks,
+                self.resource_pools.clone(),
+            ));
+        if let Err(e) = schedule.schedule_tasks(task_dep_specs).await {
+            problems.extend(e.into_problems());
+        }
+        if let Err(e) = schedule.run_checks().await {
+            problems.extend(e.into_problems());
+        }
+        Ok((schedule, problems))
+    }
+
+    /// Listens for new raw data and sends the tasks triggered by it to the schedules of the
+    /// pipelines containing them
+    fn spawn_event_listener(&self, schedules: Vec<(mpsc::Sender<String>, HashSet<String>)>) {
+        let (sender, tables) = mpsc::channel(100);
+        let pool = self.pool.clone();
+        let cancel = self.shutdown.clone();
+        tokio::spawn(async move {
+            if let Err(e) = listen_raw_data_events(&pool, sender, cancel).await {
+                error!("Listening for new raw data failed: {}", e);
+            }
+        });
+        let event_triggers = self.event_triggers.clone();
+        let cancel = self.shutdown.clone();
+        tokio::spawn(async move { event_triggers.run(tables, schedules, cancel).await });
+    }
+
+    /// In distributed mode the scheduler only queues the tasks, workers execute their actions
+    fn build_action(&self, pipeline: &str, ts: &TaskSetting) -> Result<Action, anyhow::Error> {
+        if let Some(backfill) = self.backfill.as_ref().filter(|b| b.task == ts.name) {
+            let range = DateRange::new(backfill.from, backfill.to)?;
+            return create_backfill_action(
+                &ts.task_type,
+                ts.params.as_ref(),
+                range,
+                &self.pool,
+                &self.client,
+                &self.secrets,
+                &self.notifier,
+            )
+            .with_context(|| format!("Cannot backfill task {}", ts.name));
+        }
+        // the params are checked on the scheduler as well, the workers create the same action
+        let action = self.create_task_action(ts)?;
+        Ok(match &self.distributed {
+            Some(setting) if setting.role == DistributedRole::Scheduler => {
+                Arc::new(QueuedRunnable::new(
+                    pipeline.to_string(),
+                    ts.name.clone(),
+                    Arc::new(TaskQueueService::new(self.pool.clone())),
+                    build_queue_options(setting),
+                ))
+            }
+            _ => action,
+        })
+    }
 
-        // schedule, check resulting dag and run
-        let mut schedule = Schedule::new();
-        schedule.schedule_tasks(task_dep_specs).await;
-        schedule.run_checks().await;
-        schedule.run_schedule().await;
+    /// Creates the action of the task, a fan-out if the task has a fan-out setting
+    fn create_task_action(&self, ts: &TaskSetting) -> Result<Action, anyhow::Error> {
+        match ts.fan_out {
+            Some(setting) => create_fan_out_action(
+                &ts.task_type,
+                ts.params.as_ref(),
+                FanOutOptions {
+                    max_parallel_items: setting.max_parallel_items,
+                    max_failed_items: setting.max_failed_items,
+                },
+                &self.pool,
+                &self.client,
+                &self.secrets,
+                &self.notifier,
+            ),
+            None => create_action(
+                &ts.task_type,
+                ts.params.as_ref(),
+                &self.pool,
+                &self.client,
+                &self.secrets,
+                &self.notifier,
+            ),
+        }
+        .with_context(|| format!("Invalid task {}", ts.name))
+    }
+
+    /// The backfill runs as pipeline of its own containing only the task to backfill,
+    /// the retries and the timeout of the task apply as usual
+    fn backfill_pipeline(
+        &self,
+        backfill: &BackfillSetting,
+    ) -> Result<Vec<(String, PipelineSetting)>, anyhow::Error> {
+        if !self.task_settings.iter().any(|ts| ts.name == backfill.task) {
+            anyhow::bail!("Task {} to backfill is not configured", backfill.task);
+        }
+        let pipeline = PipelineSetting {
+            task_dependencies: vec![TaskDependency {
+                name: backfill.task.clone(),
+                dependencies: vec![],
+                trigger_rules: HashMap::new(),
+            }],
+            cadence: None,
+        };
+        Ok(vec![(BACKFILL_PIPELINE.to_string(), pipeline)])
+    }
+
+    /// executes queued tasks of all configured tasks until the application is shut down
+    async fn run_worker(&self, setting: &DistributedSetting) -> Result<(), anyhow::Error> {
+        let actions = self
+            .task_settings
+            .iter()
+            .map(|ts| Ok((ts.name.clone(), self.create_task_action(ts)? as _)))
+            .collect::<Result<_, anyhow::Error>>()?;
+        Worker::new(
+            Arc::new(TaskQueueService::new(self.pool.clone())),
+            actions,
+            build_queue_options(setting),
+        )
+        .with_slots(setting.worker_slots)
+        .run(self.shutdown.clone())
+        .await;
         Ok(())
     }
+
+    /// writes the dag of the pipeline as DOT and Mermaid file to the export directory, if one is
+    /// configured. Files of other pipelines than the default pipeline are suffixed with its name.
+    fn export_dag(&self, pipeline: &str, graph: &DagGraph) -> Result<(), anyhow::Error> {
+        let Some(export_dir) = &self.dag_export_dir else {
+            return Ok(());
+        };
+        let export_dir = Path::new(export_dir);
+        let file_stem = match pipeline {
+            DEFAULT_PIPELINE => "dependencies".to_string(),
+            pipeline => format!("dependencies_{}", pipeline),
+        };
+        for (extension, content) in [("dot", graph.to_dot()), ("mmd", graph.to_mermaid())] {
+            let path = export_dir.join(format!("{}.{}", file_stem, extension));
+            std::fs::write(&path, content)
+                .with_context(|| format!("Failed to export the dag to {}", path.display()))?;
+            info!("Exported the dag to {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+/// The top level task dependencies form the default pipeline, if they are not empty or if no
+/// other pipeline is configured. Pipelines are sorted by name.
+fn collect_pipelines(
+    task_dependencies: Vec<TaskDependency>,
+    pipelines: HashMap<String, PipelineSetting>,
+) -> Vec<(String, PipelineSetting)> {
+    let mut pipelines: Vec<(String, PipelineSetting)> = pipelines.into_iter().collect();
+    if !task_dependencies.is_empty() || pipelines.is_empty() {
+        pipelines.push((
+            DEFAULT_PIPELINE.to_string(),
+            PipelineSetting {
+                task_dependencies,
+                cadence: None,
+            },
+        ));
+    }
+    pipelines.sort_by(|a, b| a.0.cmp(&b.0));
+    pipelines
+}
+
+/// Completes when the process receives SIGTERM (e.g. on container stop) or ctrl+c
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for ctrl+c: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// marks all tasks which finished in the last run, so that they are not executed again
+async fn resume_last_run(
+    schedule: &mut Schedule,
+    run_history: &RunHistoryService,
+) -> Result<(), anyhow::Error> {
+    let Some(run_id) = run_history
+        .get_last_run_id(schedule.name())
+        .await
+        .context("Failed to load the last run")?
+    else {
+        info!(
+            "No previous run of pipeline {} found, execute all tasks",
+            schedule.name()
+        );
+        return Ok(());
+    };
+    let finished_tasks: HashSet<TaskName> = run_history
+        .get_task_runs(run_id)
+        .await
+        .context("Failed to load the task runs of the last run")?
+        .into_iter()
+        .filter(|task_run| task_run.state == ExecutionState::Finished)
+        .map(|task_run| task_run.task_name)
+        .collect();
+    info!(
+        "Resume run {} of pipeline {}, {} tasks finished already",
+        run_id,
+        schedule.name(),
+        finished_tasks.len()
+    );
+    schedule.resume(&finished_tasks).await;
+    Ok(())
 }
 
 fn build_task_specs(
     task_settings: &[TaskSetting],
     task_dependencies: &[TaskDependency],
-    pool: &PgPool,
-    client: &Client,
-    secrets: &SecretKeys,
-) -> HashMap<TaskName, TaskSpecRef> {
+    task_groups: &TaskGroups,
+    build_action: &dyn Fn(&TaskSetting) -> Result<Action, anyhow::Error>,
+    kill_switch: &broadcast::Sender<()>,
+) -> Result<HashMap<TaskName, TaskSpecRef>, anyhow::Error> {
     let required_tasks: Vec<TaskName> = task_dependencies.iter().map(|t| t.name.clone()).collect();
 
     task_settings
@@ -74,38 +515,171 @@ fn build_task_specs(
         .filter(|ts| required_tasks.contains(&ts.name))
         .map(|ts| {
             let task_name: TaskName = ts.name.clone();
-            let action = create_action(&ts.task_type, pool, client, secrets);
+            let action = build_action(ts)?;
+            let cadence = ts
+                .cadence
+                .as_ref()
+                .map(build_cadence)
+                .transpose()
+                .with_context(|| format!("Invalid cadence for task {}", task_name))?;
+            let trigger_rules = task_dependencies
+                .iter()
+                .filter(|task_dependency| task_dependency.name == task_name)
+                .flat_map(|task_dependency| task_dependency.trigger_rules.iter())
+                // the rule of a group applies to all its members
+                .flat_map(|(name, rule)| {
+                    expand_task_group(name, task_groups)
+                        .into_iter()
+                        .map(move |member| (member.clone(), *rule))
+                })
+                .collect();
             let task_spec = TaskSpec::new(
                 task_name.clone(),
-                RetryOptions::default(),
-                ExecutionMode::Once,
+                ts.retry
+                    .as_ref()
+                    .map(build_retry_options)
+                    .unwrap_or_default(),
+                build_execution_mode(&ts.execution_mode, kill_switch),
                 Arc::new(Default::default()),
                 action,
-            );
+            )
+            .with_cadence(cadence)
+            .with_trigger_rules(trigger_rules)
+            .with_resources(ts.resources.clone())
+            .with_timeout(ts.timeout_seconds.map(Duration::from_secs))
+            .with_freshness(
+                ts.freshness_hours
+                    .map(|hours| Duration::from_secs(hours * 3600)),
+            )
+            .with_priority(ts.priority)
+            .with_window(TimeWindow::new(ts.not_before, ts.deadline));
             let task_spec_ref: TaskSpecRef = TaskSpecRef::from(task_spec);
-            (task_name, task_spec_ref)
+            Ok((task_name, task_spec_ref))
         })
         .collect()
 }
 
+fn build_execution_mode(
+    setting: &ExecutionModeSetting,
+    kill_switch: &broadcast::Sender<()>,
+) -> ExecutionMode {
+    match setting {
+        ExecutionModeSetting::Once => ExecutionMode::Once,
+        ExecutionModeSetting::Continuously => ExecutionMode::Continuously {
+            kill: kill_switch.clone(),
+        },
+        ExecutionModeSetting::RepeatLimited { count } => {
+            ExecutionMode::RepeatLimited { count: *count }
+        }
+        ExecutionModeSetting::RepeatForDuration { seconds } => ExecutionMode::RepeatForDuration {
+            duration: Duration::from_secs(*seconds),
+        },
+    }
+}
+
+fn build_retry_options(setting: &RetrySetting) -> RetryOptions {
+    let min_back_off = Duration::from_millis(setting.min_back_off_milliseconds);
+    let max_back_off = setting
+        .max_back_off_milliseconds
+        .map(Duration::from_millis)
+        .unwrap_or(min_back_off);
+    let back_off = match setting.back_off {
+        BackOffSetting::Constant => BackOff::Constant {
+            back_off: min_back_off,
+        },
+        BackOffSetting::Linear => BackOff::Linear {
+            min_back_off,
+            max_back_off,
+        },
+        BackOffSetting::Exponential => BackOff::Exponential {
+            base: setting.base,
+            min_back_off,
+            max_back_off,
+        },
+    };
+    RetryOptions::new(setting.max_retries, back_off)
+        .with_jitter(Duration::from_millis(setting.jitter_milliseconds))
+}
+
+fn build_queue_options(setting: &DistributedSetting) -> QueueOptions {
+    QueueOptions {
+        poll_interval: Duration::from_millis(setting.poll_interval_milliseconds),
+        heartbeat_interval: Duration::from_secs(setting.heartbeat_interval_seconds),
+        stale_after: Duration::from_secs(setting.stale_after_seconds),
+    }
+}
+
+fn build_cadence(setting: &CadenceSetting) -> Result<Cadence, anyhow::Error> {
+    match setting {
+        CadenceSetting::Cron(expression) => Ok(Cadence::from_cron(expression)?),
+        CadenceSetting::IntervalSeconds(seconds) => {
+            Ok(Cadence::Interval(Duration::from_secs(*seconds)))
+        }
+    }
+}
+
+/// Builds the adjacency list from the dependencies of the configured tasks.
+/// Task groups listed as dependency are replaced by their members.
+/// Dependencies which are not configured as task, tasks without task setting and trigger rules
+/// of names which are no dependency are returned as problems.
 #[allow(clippy::mutable_key_type)]
 fn add_dependencies_to_task_specs(
     task_specs_map: HashMap<TaskName, TaskSpecRef>,
     task_dependencies: &[TaskDependency],
-) -> TaskDependenciesSpecs {
-    task_specs_map
+    task_groups: &TaskGroups,
+) -> (TaskDependenciesSpecs, Vec<ScheduleError>) {
+    let mut problems: Vec<ScheduleError> = task_groups
+        .keys()
+        .filter(|group| task_specs_map.contains_key(*group))
+        .map(|group| ScheduleError::AmbiguousTaskGroup(group.clone()))
+        .collect();
+    for task_dependency in task_dependencies {
+        if !task_specs_map.contains_key(&task_dependency.name) {
+            problems.push(ScheduleError::MissingTaskSetting(
+                task_dependency.name.clone(),
+            ));
+        }
+        // a rule of a name which is no dependency, e.g. a typo, would never apply
+        for dependency in task_dependency.trigger_rules.keys() {
+            if !task_dependency.dependencies.contains(dependency) {
+                problems.push(ScheduleError::UnknownTriggerRuleDependency {
+                    task: task_dependency.name.clone(),
+                    dependency: dependency.clone(),
+                });
+            }
+        }
+    }
+    let task_dependencies_specs = task_specs_map
         .values()
         .map(|task_specs_ref| {
-            let deps: Vec<TaskSpecRef> = task_dependencies
+            let mut deps: Vec<TaskSpecRef> = vec![];
+            for task_name in task_dependencies
                 .iter()
                 .filter(|task_dependency| task_dependency.name == task_specs_ref.name)
                 .flat_map(|task_dependency| &task_dependency.dependencies)
-                .filter_map(|task_name| task_specs_map.get(task_name))
-                .cloned()
-                .collect();
+                .flat_map(|name| expand_task_group(name, task_groups))
+            {
+                match task_specs_map.get(task_name) {
+                    Some(dependency) if !deps.contains(dependency) => deps.push(dependency.clone()),
+                    Some(_) => {}
+                    None => problems.push(ScheduleError::UnknownDependency {
+                        task: task_specs_ref.name.clone(),
+                        dependency: task_name.clone(),
+                    }),
+                }
+            }
             (task_specs_ref.clone(), deps)
         })
-        .collect()
+        .collect();
+    (task_dependencies_specs, problems)
+}
+
+/// members of the group with the given name, the name itself if it is no group
+fn expand_task_group<'a>(name: &'a TaskName, task_groups: &'a TaskGroups) -> Vec<&'a TaskName> {
+    match task_groups.get(name) {
+        Some(members) => members.iter().collect(),
+        None => vec![name],
+    }
 }
 
 pub fn get_connection_pool(configuration: &DatabaseSettings) -> PgPool {
@@ -120,3 +694,108 @@ pub fn build_http_client(configuration: HttpClientSettings) -> Client {
         .build()
         .expect("Error building http client")
 }
+
+#[cfg(test)]
+mod test {
+    use crate::actions::collect::dummy::DummyCollector;
+    use crate::configuration::{TaskDependency, TaskName};
+    use crate::dag_schedule::schedule::{ScheduleError, TaskSpec, TaskSpecRef};
+    use crate::dag_schedule::task::{ExecutionMode, RetryOptions, TriggerRule};
+    use crate::startup::add_dependencies_to_task_specs;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn create_task_specs(names: &[&str]) -> HashMap<TaskName, TaskSpecRef> {
+        names
+            .iter()
+            .map(|name| {
+                let task_spec = TaskSpec::new(
+                    name.to_string(),
+                    RetryOptions::default(),
+                    ExecutionMode::Once,
+                    Arc::new(Default::default()),
+                    Arc::new(DummyCollector::new()),
+                );
+                (name.to_string(), TaskSpecRef::from(task_spec))
+            })
+            .collect()
+    }
+
+    fn dependency(name: &str, dependencies: &[&str]) -> TaskDependency {
+        TaskDependency {
+            name: name.to_string(),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            trigger_rules: HashMap::new(),
+        }
+    }
+
+    #[test]
+    #[allow(clippy::mutable_key_type)]
+    fn task_groups_are_expanded_into_their_members() {
+        let task_specs = create_task_specs(&["a", "b", "c", "report"]);
+        let task_dependencies = vec![
+            dependency("a", &[]),
+            dependency("b", &[]),
+            dependency("c", &[]),
+            dependency("report", &["collectors", "a", "unknown_group"]),
+        ];
+        let task_groups = HashMap::from([
+            (
+                "collectors".to_string(),
+                vec!["a".to_string(), "b".to_string()],
+            ),
+            ("unknown_group".to_string(), vec!["missing".to_string()]),
+        ]);
+
+        let (task_dependencies_specs, problems) =
+            add_dependencies_to_task_specs(task_specs.clone(), &task_dependencies, &task_groups);
+
+        let mut report_dependencies: Vec<String> = task_dependencies_specs[&task_specs["report"]]
+            .iter()
+            .map(|spec| spec.name.clone())
+            .collect();
+        report_dependencies.sort();
+        assert_eq!(report_dependencies, vec!["a", "b"]);
+        assert!(matches!(
+            problems.as_slice(),
+            [ScheduleError::UnknownDependency { task, dependency }]
+                if task == "report" && dependency == "missing"
+        ));
+    }
+
+    #[test]
+    fn task_group_must_not_be_named_like_a_task() {
+        let task_specs = create_task_specs(&["a"]);
+        let task_groups = HashMap::from([("a".to_string(), vec!["a".to_string()])]);
+
+        let (_, problems) =
+            add_dependencies_to_task_specs(task_specs, &[dependency("a", &[])], &task_groups);
+
+        assert!(matches!(
+            problems.as_slice(),
+            [ScheduleError::AmbiguousTaskGroup(group)] if group == "a"
+        ));
+    }
+
+    #[test]
+    fn tasks_without_setting_and_rules_of_no_dependency_are_reported() {
+        let task_specs = create_task_specs(&["a", "b"]);
+        let mut b = dependency("b", &["a"]);
+        b.trigger_rules = HashMap::from([("c".to_string(), TriggerRule::Always)]);
+        let task_dependencies = vec![dependency("a", &[]), b, dependency("c", &[])];
+
+        let (_, problems) =
+            add_dependencies_to_task_specs(task_specs, &task_dependencies, &HashMap::new());
+
+        assert_eq!(
+            problems,
+            vec![
+                ScheduleError::UnknownTriggerRuleDependency {
+                    task: "b".to_string(),
+                    dependency: "c".to_string()
+                },
+                ScheduleError::MissingTaskSetting("c".to_string()),
+            ]
+        );
+    }
+}
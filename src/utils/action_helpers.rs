@@ -1,3 +1,4 @@
+use chrono::NaiveDate;
 use serde::de;
 
 /// Try to interpret the string as .json and parse it into the deserializable struct given as generic parameter.
@@ -22,3 +23,53 @@ pub fn parse_response<'a, T: de::Deserialize<'a>>(response: &'a str) -> Result<T
 pub fn pages_available(items_available: u32, page_size: u32) -> u32 {
     (items_available as f32 / page_size as f32).ceil() as u32
 }
+
+/// Inclusive range of dates, e.g. business dates to collect again in a backfill
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DateRange {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl DateRange {
+    pub fn new(from: NaiveDate, to: NaiveDate) -> Result<Self, anyhow::Error> {
+        if from > to {
+            anyhow::bail!("Date range from {} to {} is empty", from, to);
+        }
+        Ok(DateRange { from, to })
+    }
+
+    /// Fails if the range ends after the latest stored date. Collecting such a range would move the
+    /// watermark of the incremental collection and leave the dates in between uncollected.
+    pub fn check_within_watermark(
+        &self,
+        watermark: Option<NaiveDate>,
+    ) -> Result<(), anyhow::Error> {
+        match watermark {
+            Some(watermark) if self.to <= watermark => Ok(()),
+            Some(watermark) => anyhow::bail!(
+                "Backfill until {} goes beyond the latest collected date {}, use the incremental collection instead",
+                self.to,
+                watermark
+            ),
+            None => anyhow::bail!("Nothing collected yet, use the incremental collection instead"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::utils::action_helpers::DateRange;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn backfill_range_must_not_pass_watermark() {
+        let date = |day| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+        assert!(DateRange::new(date(5), date(4)).is_err());
+
+        let range = DateRange::new(date(4), date(6)).unwrap();
+        assert!(range.check_within_watermark(Some(date(6))).is_ok());
+        assert!(range.check_within_watermark(Some(date(5))).is_err());
+        assert!(range.check_within_watermark(None).is_err());
+    }
+}
@@ -1,5 +1,6 @@
 pub mod action_helpers;
 pub mod futures;
+pub mod notification;
 pub mod telemetry;
 #[cfg(test)]
 pub mod test_helpers;
@@ -0,0 +1,320 @@
+use crate::dag_schedule::report::{RunReport, TaskReport};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// Events a sink can be notified about
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationEvent {
+    TaskFailed,
+    ScheduleCompleted,
+    KeysExhausted,
+}
+
+/// Payload of a notification, generic webhooks receive it as json tagged with the event
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Notification {
+    TaskFailed {
+        pipeline: String,
+        run_id: Uuid,
+        task: TaskReport,
+    },
+    ScheduleCompleted {
+        report: RunReport,
+    },
+    /// no api key of the platform is ready, the collector waits for the next one or gives up
+    KeysExhausted {
+        platform: String,
+        next_ready_at: Option<DateTime<Utc>>,
+    },
+}
+
+impl Notification {
+    pub fn event(&self) -> NotificationEvent {
+        match self {
+            Notification::TaskFailed { .. } => NotificationEvent::TaskFailed,
+            Notification::ScheduleCompleted { .. } => NotificationEvent::ScheduleCompleted,
+            Notification::KeysExhausted { .. } => NotificationEvent::KeysExhausted,
+        }
+    }
+
+    /// Single line summary, used as message of Slack-compatible webhooks
+    pub fn text(&self) -> String {
+        match self {
+            Notification::TaskFailed { pipeline, task, .. } => format!(
+                "Task {} of pipeline {} failed after {} attempts: {}",
+                task.name,
+                pipeline,
+                task.attempts,
+                task.error.as_deref().unwrap_or("unknown error")
+            ),
+            Notification::ScheduleCompleted { report } => {
+                let failed_tasks = report.failed_tasks();
+                if failed_tasks.is_empty() {
+                    format!(
+                        "Pipeline {} completed run {} with {} tasks",
+                        report.pipeline,
+                        report.run_id,
+                        report.tasks.len()
+                    )
+                } else {
+                    format!(
+                        "Pipeline {} completed run {} with failed tasks: {}",
+                        report.pipeline,
+                        report.run_id,
+                        failed_tasks.join(", ")
+                    )
+                }
+            }
+            Notification::KeysExhausted {
+                platform,
+                next_ready_at,
+            } => match next_ready_at {
+                Some(next_ready_at) => format!(
+                    "All {} api keys are exhausted, the next key is ready at {}",
+                    platform, next_ready_at
+                ),
+                None => format!("All {} api keys are in use or exhausted", platform),
+            },
+        }
+    }
+}
+
+/// Format of the body posted to a sink
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SinkFormat {
+    /// the notification as json
+    #[default]
+    Json,
+    /// `{"text": "..."}` as expected by Slack incoming webhooks and compatible chat tools
+    Slack,
+}
+
+/// Webhook notified about the given events
+#[derive(Clone, Debug)]
+pub struct NotificationSink {
+    url: Secret<String>,
+    format: SinkFormat,
+    events: Vec<NotificationEvent>,
+}
+
+impl NotificationSink {
+    pub fn new(url: Secret<String>, format: SinkFormat, events: Vec<NotificationEvent>) -> Self {
+        NotificationSink {
+            url,
+            format,
+            events,
+        }
+    }
+
+    fn body(&self, notification: &Notification) -> serde_json::Value {
+        match self.format {
+            SinkFormat::Json => {
+                serde_json::to_value(notification).expect("notifications are always serializable")
+            }
+            SinkFormat::Slack => serde_json::json!({ "text": notification.text() }),
+        }
+    }
+}
+
+/// Notifications are sent while the scheduler waits, so a sink gets far less time to answer than
+/// the requests of the collectors.
+const DEFAULT_NOTIFICATION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Posts notifications to all sinks subscribed to their event. Failing sinks are logged and never
+/// fail the caller, a notifier without sinks does nothing.
+#[derive(Clone, Debug)]
+pub struct Notifier {
+    client: Client,
+    timeout: Duration,
+    sinks: Vec<NotificationSink>,
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Notifier::new(Client::default())
+    }
+}
+
+impl Notifier {
+    pub fn new(client: Client) -> Self {
+        Notifier {
+            client,
+            timeout: DEFAULT_NOTIFICATION_TIMEOUT,
+            sinks: Vec::new(),
+        }
+    }
+
+    /// time a sink has to answer, overrides the timeout of the client
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_sink(mut self, sink: NotificationSink) -> Self {
+        self.sinks.push(sink);
+        self
+    }
+
+    pub async fn notify(&self, notification: &Notification) {
+        let event = notification.event();
+        for sink in self
+            .sinks
+            .iter()
+            .filter(|sink| sink.events.contains(&event))
+        {
+            let result = self
+                .client
+                .post(sink.url.expose_secret())
+                .timeout(self.timeout)
+                .json(&sink.body(notification))
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            match result {
+                Ok(_) => debug!("Sent {:?} notification", event),
+                // the url may contain a token, so only the error kind is logged
+                Err(e) => warn!(
+                    "Failed to send {:?} notification with status {:?}",
+                    event,
+                    e.status()
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::dag_schedule::report::{RunReport, TaskReport};
+    use crate::dag_schedule::task::ExecutionState;
+    use crate::utils::notification::{
+        Notification, NotificationEvent, NotificationSink, Notifier, SinkFormat,
+    };
+    use crate::utils::test_helpers::get_test_client;
+    use chrono::Utc;
+    use httpmock::Method::POST;
+    use httpmock::MockServer;
+    use secrecy::Secret;
+    use serde_json::json;
+    use std::time::{Duration, Instant};
+    use uuid::Uuid;
+
+    fn failed_task() -> TaskReport {
+        TaskReport {
+            name: "PolygonGroupedDaily".to_string(),
+            state: ExecutionState::Failed,
+            started_at: None,
+            finished_at: Utc::now(),
+            runtime_milliseconds: 20,
+            attempts: 3,
+            error: Some("The action of the task failed: connection refused".to_string()),
+            custom_stats: Some(json!({"rows": 10})),
+            items: None,
+        }
+    }
+
+    fn sink(server: &MockServer, path: &str, format: SinkFormat) -> NotificationSink {
+        NotificationSink::new(
+            Secret::new(server.url(path)),
+            format,
+            vec![
+                NotificationEvent::TaskFailed,
+                NotificationEvent::KeysExhausted,
+            ],
+        )
+    }
+
+    #[tokio::test]
+    async fn task_failure_is_posted_as_json_and_slack_message() {
+        let server = MockServer::start();
+        let webhook = server.mock(|when, then| {
+            when.method(POST)
+                .path("/webhook")
+                .json_body_partial(
+                    r#"{"event": "task_failed", "pipeline": "daily", "task": {"name": "PolygonGroupedDaily", "attempts": 3, "error": "The action of the task failed: connection refused", "custom_stats": {"rows": 10}}}"#,
+                );
+            then.status(200);
+        });
+        let slack = server.mock(|when, then| {
+            when.method(POST).path("/slack").json_body(json!({
+                "text": "Task PolygonGroupedDaily of pipeline daily failed after 3 attempts: The action of the task failed: connection refused"
+            }));
+            then.status(200);
+        });
+        let notifier = Notifier::new(get_test_client())
+            .with_sink(sink(&server, "/webhook", SinkFormat::Json))
+            .with_sink(sink(&server, "/slack", SinkFormat::Slack));
+
+        notifier
+            .notify(&Notification::TaskFailed {
+                pipeline: "daily".to_string(),
+                run_id: Uuid::new_v4(),
+                task: failed_task(),
+            })
+            .await;
+
+        webhook.assert();
+        slack.assert();
+    }
+
+    #[tokio::test]
+    async fn only_subscribed_events_are_posted_and_failing_sinks_are_ignored() {
+        let server = MockServer::start();
+        let webhook = server.mock(|when, then| {
+            when.method(POST).path("/webhook");
+            then.status(500);
+        });
+        let notifier =
+            Notifier::new(get_test_client()).with_sink(sink(&server, "/webhook", SinkFormat::Json));
+
+        notifier
+            .notify(&Notification::ScheduleCompleted {
+                report: RunReport {
+                    pipeline: "daily".to_string(),
+                    run_id: Uuid::new_v4(),
+                    tasks: vec![failed_task()],
+                },
+            })
+            .await;
+        webhook.assert_hits(0);
+
+        notifier
+            .notify(&Notification::KeysExhausted {
+                platform: "Polygon".to_string(),
+                next_ready_at: None,
+            })
+            .await;
+        webhook.assert_hits(1);
+    }
+
+    #[tokio::test]
+    async fn slow_sinks_do_not_hold_up_the_caller() {
+        let server = MockServer::start();
+        let webhook = server.mock(|when, then| {
+            when.method(POST).path("/webhook");
+            then.status(200).delay(Duration::from_secs(5));
+        });
+        let notifier = Notifier::new(get_test_client())
+            .with_timeout(Duration::from_millis(100))
+            .with_sink(sink(&server, "/webhook", SinkFormat::Json));
+
+        let started = Instant::now();
+        notifier
+            .notify(&Notification::KeysExhausted {
+                platform: "Polygon".to_string(),
+                next_ready_at: None,
+            })
+            .await;
+
+        assert!(started.elapsed() < Duration::from_secs(2));
+        webhook.assert();
+    }
+}
@@ -72,11 +72,23 @@ async fn start_task() {
         sp500_fields: vec![],
         include_sources: vec![],
         exclude_sources: vec![],
+        execution_mode: Default::default(),
+        cadence: None,
+        resources: vec![],
+        timeout_seconds: None,
+        freshness_hours: None,
+        priority: 0,
+        retry: None,
+        not_before: None,
+        deadline: None,
+        params: None,
+        fan_out: None,
     }];
 
     let dep = TaskDependency {
         name: "task_1".to_string(),
         dependencies: vec![],
+        trigger_rules: Default::default(),
     };
 
     let app = spawn_app_with_test_tasks(tasks, vec![dep]).await;
@@ -99,6 +111,17 @@ async fn running_collect_action_on_dummy_source() {
         sp500_fields: vec![],
         include_sources: vec![],
         exclude_sources: vec![],
+        execution_mode: Default::default(),
+        cadence: None,
+        resources: vec![],
+        timeout_seconds: None,
+        freshness_hours: None,
+        priority: 0,
+        retry: None,
+        not_before: None,
+        deadline: None,
+        params: None,
+        fan_out: None,
     };
     let mut tasks = vec![];
     let mut deps = vec![];
@@ -109,6 +132,7 @@ async fn running_collect_action_on_dummy_source() {
         let dep = TaskDependency {
             name,
             dependencies: vec![],
+            trigger_rules: Default::default(),
         };
         tasks.push(task);
         deps.push(dep);